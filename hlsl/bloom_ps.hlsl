@@ -0,0 +1,50 @@
+#include "post_process.hlsli"
+
+cbuffer BloomParams : register(b0)
+{
+    float gThreshold;
+    float gIntensity;
+    float2 gTexelSize;
+};
+
+Texture2D gBloom : register(t1);
+
+static const float BLUR_WEIGHTS[5] = { 0.227027f, 0.1945946f, 0.1216216f, 0.054054f, 0.016216f };
+
+float4 BrightPassPS(PostVertexOut pIn) : SV_Target
+{
+    float3 color = gInput.SampleLevel(gLinearClamp, pIn.tex, 0).rgb;
+    float brightness = dot(color, LUMA_WEIGHTS);
+    float contribution = max(brightness - gThreshold, 0.0f) / max(brightness, 1e-4f);
+    return float4(color * contribution, 1.0f);
+}
+
+float4 Blur(float2 tex, float2 direction)
+{
+    float3 color = gInput.SampleLevel(gLinearClamp, tex, 0).rgb * BLUR_WEIGHTS[0];
+    [unroll]
+    for (int i = 1; i < 5; ++i)
+    {
+        float2 offset = direction * gTexelSize * i;
+        color += gInput.SampleLevel(gLinearClamp, tex + offset, 0).rgb * BLUR_WEIGHTS[i];
+        color += gInput.SampleLevel(gLinearClamp, tex - offset, 0).rgb * BLUR_WEIGHTS[i];
+    }
+    return float4(color, 1.0f);
+}
+
+float4 BlurHorizontalPS(PostVertexOut pIn) : SV_Target
+{
+    return Blur(pIn.tex, float2(1.0f, 0.0f));
+}
+
+float4 BlurVerticalPS(PostVertexOut pIn) : SV_Target
+{
+    return Blur(pIn.tex, float2(0.0f, 1.0f));
+}
+
+float4 CompositePS(PostVertexOut pIn) : SV_Target
+{
+    float4 scene = gInput.SampleLevel(gLinearClamp, pIn.tex, 0);
+    float3 bloom = gBloom.SampleLevel(gLinearClamp, pIn.tex, 0).rgb;
+    return float4(scene.rgb + bloom * gIntensity, scene.a);
+}
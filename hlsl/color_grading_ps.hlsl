@@ -0,0 +1,22 @@
+#include "post_process.hlsli"
+
+cbuffer ColorGradingParams : register(b0)
+{
+    float gLutSize;
+    float gStrength;
+    float2 gPad;
+};
+
+Texture3D gLut : register(t1);
+
+float4 PS(PostVertexOut pIn) : SV_Target
+{
+    float4 color = gInput.SampleLevel(gLinearClamp, pIn.tex, 0);
+    // LUT 以 sRGB 編碼後的值為索引，8 位元精度才不會集中在暗部
+    float3 encoded = LinearToSrgb(color.rgb);
+    // 讓取樣點落在 LUT 第一個與最後一個 texel 的中心
+    float scale = (gLutSize - 1.0f) / gLutSize;
+    float offset = 0.5f / gLutSize;
+    float3 graded = SrgbToLinear(gLut.SampleLevel(gLinearClamp, encoded * scale + offset, 0).rgb);
+    return float4(lerp(color.rgb, graded, gStrength), color.a);
+}
@@ -0,0 +1,10 @@
+struct DebugVertexOut
+{
+    float4 posH : SV_POSITION;
+    float4 color : COLOR;
+};
+
+float4 PS(DebugVertexOut pIn) : SV_Target
+{
+    return pIn.color;
+}
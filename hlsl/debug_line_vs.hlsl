@@ -0,0 +1,24 @@
+cbuffer DebugConstants : register(b0)
+{
+    float4x4 gViewProj;
+};
+
+struct DebugVertexIn
+{
+    float3 pos : POSITION;
+    float4 color : COLOR;
+};
+
+struct DebugVertexOut
+{
+    float4 posH : SV_POSITION;
+    float4 color : COLOR;
+};
+
+DebugVertexOut VS(DebugVertexIn vIn)
+{
+    DebugVertexOut vOut;
+    vOut.posH = mul(float4(vIn.pos, 1.0f), gViewProj);
+    vOut.color = vIn.color;
+    return vOut;
+}
@@ -0,0 +1,10 @@
+#include "post_process.hlsli"
+
+// 不需要頂點緩衝區，以 SV_VertexID 產生覆蓋整個畫面的三角形
+PostVertexOut VS(uint id : SV_VertexID)
+{
+    PostVertexOut vOut;
+    vOut.tex = float2((id << 1) & 2, id & 2);
+    vOut.posH = float4(vOut.tex * float2(2.0f, -2.0f) + float2(-1.0f, 1.0f), 0.0f, 1.0f);
+    return vOut;
+}
@@ -0,0 +1,49 @@
+#include "post_process.hlsli"
+
+cbuffer FxaaParams : register(b0)
+{
+    float2 gRcpFrame;
+    float gSpanMax;
+    float gReduceMul;
+};
+
+static const float FXAA_REDUCE_MIN = 1.0f / 128.0f;
+
+float3 SampleColor(float2 tex)
+{
+    return gInput.SampleLevel(gLinearClamp, tex, 0).rgb;
+}
+
+// 簡化版 FXAA：以四角亮度差估計邊緣方向，沿邊緣方向取樣混合
+float4 PS(PostVertexOut pIn) : SV_Target
+{
+    float2 tex = pIn.tex;
+    float3 rgbNW = SampleColor(tex + float2(-1.0f, -1.0f) * gRcpFrame);
+    float3 rgbNE = SampleColor(tex + float2(1.0f, -1.0f) * gRcpFrame);
+    float3 rgbSW = SampleColor(tex + float2(-1.0f, 1.0f) * gRcpFrame);
+    float3 rgbSE = SampleColor(tex + float2(1.0f, 1.0f) * gRcpFrame);
+    float4 center = gInput.SampleLevel(gLinearClamp, tex, 0);
+
+    float lumaNW = dot(rgbNW, LUMA_WEIGHTS);
+    float lumaNE = dot(rgbNE, LUMA_WEIGHTS);
+    float lumaSW = dot(rgbSW, LUMA_WEIGHTS);
+    float lumaSE = dot(rgbSE, LUMA_WEIGHTS);
+    float lumaM = dot(center.rgb, LUMA_WEIGHTS);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    float2 dir;
+    dir.x = -((lumaNW + lumaNE) - (lumaSW + lumaSE));
+    dir.y = ((lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * (0.25f * gReduceMul), FXAA_REDUCE_MIN);
+    float rcpDirMin = 1.0f / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, -gSpanMax, gSpanMax) * gRcpFrame;
+
+    float3 rgbA = 0.5f * (SampleColor(tex + dir * (1.0f / 3.0f - 0.5f)) + SampleColor(tex + dir * (2.0f / 3.0f - 0.5f)));
+    float3 rgbB = rgbA * 0.5f + 0.25f * (SampleColor(tex - dir * 0.5f) + SampleColor(tex + dir * 0.5f));
+    float lumaB = dot(rgbB, LUMA_WEIGHTS);
+
+    float3 color = (lumaB < lumaMin || lumaB > lumaMax) ? rgbA : rgbB;
+    return float4(color, center.a);
+}
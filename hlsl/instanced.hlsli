@@ -0,0 +1,21 @@
+cbuffer PerFrame : register(b0)
+{
+    float4x4 gViewProj;
+};
+
+struct InstancedVertexIn
+{
+    float3 pos : POSITION;
+    float4 color : COLOR;
+    float4 world0 : WORLD0;
+    float4 world1 : WORLD1;
+    float4 world2 : WORLD2;
+    float4 world3 : WORLD3;
+    float4 instanceColor : INSTANCE_COLOR;
+};
+
+struct InstancedVertexOut
+{
+    float4 posH : SV_POSITION;
+    float4 color : COLOR;
+};
@@ -0,0 +1,6 @@
+#include "instanced.hlsli"
+
+float4 PS(InstancedVertexOut pIn) : SV_Target
+{
+    return pIn.color;
+}
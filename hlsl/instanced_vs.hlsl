@@ -0,0 +1,11 @@
+#include "instanced.hlsli"
+
+InstancedVertexOut VS(InstancedVertexIn vIn)
+{
+    InstancedVertexOut vOut;
+    float4x4 world = float4x4(vIn.world0, vIn.world1, vIn.world2, vIn.world3);
+    float4 posW = mul(float4(vIn.pos, 1.0f), world);
+    vOut.posH = mul(posW, gViewProj);
+    vOut.color = vIn.color * vIn.instanceColor;
+    return vOut;
+}
@@ -0,0 +1,57 @@
+#include "post_process.hlsli"
+
+// 數值需與 output_format.rs 的 OutputEncoding 一致
+#define ENCODING_LINEAR 0
+#define ENCODING_SRGB 1
+#define ENCODING_PQ 2
+#define ENCODING_SCRGB 3
+
+cbuffer OutputParams : register(b0)
+{
+    uint gEncoding;
+    float gPaperWhiteNits;
+    float2 gPad;
+};
+
+float3 Rec709ToRec2020(float3 color)
+{
+    static const float3x3 conversion =
+    {
+        0.627402f, 0.329292f, 0.043306f,
+        0.069095f, 0.919544f, 0.011360f,
+        0.016394f, 0.088028f, 0.895578f
+    };
+    return mul(conversion, color);
+}
+
+// SMPTE ST.2084，輸入為以 10000 nits 正規化的線性亮度
+float3 LinearToPq(float3 color)
+{
+    const float m1 = 0.1593017578125f;
+    const float m2 = 78.84375f;
+    const float c1 = 0.8359375f;
+    const float c2 = 18.8515625f;
+    const float c3 = 18.6875f;
+    float3 p = pow(max(color, 0.0f), m1);
+    return pow((c1 + c2 * p) / (1.0f + c3 * p), m2);
+}
+
+float4 PS(PostVertexOut pIn) : SV_Target
+{
+    float4 color = gInput.SampleLevel(gLinearClamp, pIn.tex, 0);
+    float3 rgb = color.rgb;
+    if (gEncoding == ENCODING_SRGB)
+    {
+        rgb = LinearToSrgb(rgb);
+    }
+    else if (gEncoding == ENCODING_PQ)
+    {
+        rgb = LinearToPq(Rec709ToRec2020(rgb) * gPaperWhiteNits / 10000.0f);
+    }
+    else if (gEncoding == ENCODING_SCRGB)
+    {
+        // scRGB 的 1.0 對應 80 nits
+        rgb = rgb * gPaperWhiteNits / 80.0f;
+    }
+    return float4(rgb, color.a);
+}
@@ -0,0 +1,21 @@
+struct PostVertexOut
+{
+    float4 posH : SV_POSITION;
+    float2 tex : TEXCOORD;
+};
+
+Texture2D gInput : register(t0);
+SamplerState gLinearClamp : register(s0);
+
+static const float3 LUMA_WEIGHTS = float3(0.2126f, 0.7152f, 0.0722f);
+
+float3 LinearToSrgb(float3 color)
+{
+    color = saturate(color);
+    return color <= 0.0031308f ? color * 12.92f : 1.055f * pow(color, 1.0f / 2.4f) - 0.055f;
+}
+
+float3 SrgbToLinear(float3 color)
+{
+    return color <= 0.04045f ? color / 12.92f : pow((color + 0.055f) / 1.055f, 2.4f);
+}
@@ -0,0 +1,32 @@
+#include "post_process.hlsli"
+
+cbuffer ToneMappingParams : register(b0)
+{
+    float gExposure;
+    uint gOperator;
+    float2 gPad;
+};
+
+float3 Reinhard(float3 color)
+{
+    return color / (1.0f + color);
+}
+
+// Krzysztof Narkowicz 的 ACES filmic 近似
+float3 AcesFilmic(float3 color)
+{
+    const float a = 2.51f;
+    const float b = 0.03f;
+    const float c = 2.43f;
+    const float d = 0.59f;
+    const float e = 0.14f;
+    return saturate((color * (a * color + b)) / (color * (c * color + d) + e));
+}
+
+float4 PS(PostVertexOut pIn) : SV_Target
+{
+    float4 hdr = gInput.SampleLevel(gLinearClamp, pIn.tex, 0);
+    float3 color = hdr.rgb * gExposure;
+    color = gOperator == 0 ? Reinhard(color) : AcesFilmic(color);
+    return float4(color, hdr.a);
+}
@@ -1,4 +1,4 @@
-#include "Triangle.hlsli"
+#include "triangle.hlsli"
 
 
 float4 PS(VertexOut pIn) : SV_Target
@@ -0,0 +1,18 @@
+#include "post_process.hlsli"
+
+cbuffer VignetteParams : register(b0)
+{
+    float gIntensity;
+    float gRadius;
+    float gSoftness;
+    float gPad;
+};
+
+float4 PS(PostVertexOut pIn) : SV_Target
+{
+    float4 color = gInput.SampleLevel(gLinearClamp, pIn.tex, 0);
+    float dist = length(pIn.tex - 0.5f) * 1.41421356f;
+    float vignette = smoothstep(gRadius, gRadius - gSoftness, dist);
+    color.rgb *= lerp(1.0f, vignette, gIntensity);
+    return color;
+}
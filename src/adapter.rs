@@ -0,0 +1,291 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// 有多張顯示卡時偏好哪一張。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdapterPreference {
+    /// 系統列舉的第一張顯示卡，通常是主螢幕接的那一張。
+    #[default]
+    Default,
+    /// 專用記憶體最多的硬體顯示卡，通常是獨立顯示卡。
+    HighPerformance,
+    /// 專用記憶體最少的硬體顯示卡，通常是內建顯示卡。
+    MinimumPower,
+}
+
+impl AdapterPreference {
+    pub const ALL: [AdapterPreference; 3] = [AdapterPreference::Default, AdapterPreference::HighPerformance, AdapterPreference::MinimumPower];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdapterPreference::Default => "default",
+            AdapterPreference::HighPerformance => "high-performance",
+            AdapterPreference::MinimumPower => "minimum-power",
+        }
+    }
+}
+
+/// 要使用哪一張顯示卡。設定檔中寫成偏好名稱、`--list-adapters` 列出的編號或顯示卡名稱的一部分：
+///
+/// ```toml
+/// adapter = "high-performance"
+/// adapter = 1
+/// adapter = "radeon"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelection {
+    Preference(AdapterPreference),
+    Index(usize),
+    /// 不分大小寫比對名稱中的一段文字。
+    Name(String),
+}
+
+impl Default for AdapterSelection {
+    fn default() -> Self {
+        AdapterSelection::Preference(AdapterPreference::Default)
+    }
+}
+
+impl fmt::Display for AdapterSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdapterSelection::Preference(preference) => f.write_str(preference.name()),
+            AdapterSelection::Index(index) => write!(f, "#{}", index),
+            AdapterSelection::Name(name) => write!(f, "\"{}\"", name),
+        }
+    }
+}
+
+impl FromStr for AdapterSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("adapter name must not be empty".to_string());
+        }
+        if let Ok(index) = s.parse::<usize>() {
+            return Ok(AdapterSelection::Index(index));
+        }
+        let preference = AdapterPreference::ALL.into_iter().find(|preference| preference.name().eq_ignore_ascii_case(s));
+        Ok(match preference {
+            Some(preference) => AdapterSelection::Preference(preference),
+            None => AdapterSelection::Name(s.to_string()),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for AdapterSelection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SelectionVisitor;
+
+        impl Visitor<'_> for SelectionVisitor {
+            type Value = AdapterSelection;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an adapter index, a preference (default, high-performance, minimum-power) or part of an adapter name")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(AdapterSelection::Index(value as usize))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                usize::try_from(value)
+                    .map(AdapterSelection::Index)
+                    .map_err(|_| E::custom(format!("adapter index must not be negative, got {}", value)))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(SelectionVisitor)
+    }
+}
+
+/// 一張 DXGI 顯示卡的描述，`index` 是 `EnumAdapters1` 的編號。
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct AdapterInfo {
+    pub index: usize,
+    pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub dedicated_video_memory: u64,
+    pub shared_system_memory: u64,
+    /// 軟體實作，例如 Microsoft Basic Render Driver。
+    pub software: bool,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl AdapterInfo {
+    pub fn vendor_name(&self) -> &'static str {
+        match self.vendor_id {
+            0x10DE => "NVIDIA",
+            0x1002 | 0x1022 => "AMD",
+            0x8086 => "Intel",
+            0x1414 => "Microsoft",
+            0x5143 => "Qualcomm",
+            _ => "unknown vendor",
+        }
+    }
+}
+
+impl fmt::Display for AdapterInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: u64 = 1024 * 1024;
+        write!(
+            f,
+            "#{} {} ({}, {:04X}:{:04X}), {} MiB dedicated, {} MiB shared",
+            self.index,
+            self.name,
+            self.vendor_name(),
+            self.vendor_id,
+            self.device_id,
+            self.dedicated_video_memory / MIB,
+            self.shared_system_memory / MIB,
+        )?;
+        if self.software {
+            f.write_str(", software")?;
+        }
+        Ok(())
+    }
+}
+
+/// 從列舉結果中選出顯示卡，回傳在 `adapters` 中的位置。
+/// 偏好只考慮硬體顯示卡，沒有硬體顯示卡時才退回軟體實作。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn select_adapter(adapters: &[AdapterInfo], selection: &AdapterSelection) -> Result<usize, String> {
+    if adapters.is_empty() {
+        return Err("no display adapters found".to_string());
+    }
+    match selection {
+        AdapterSelection::Index(index) => adapters.iter()
+            .position(|adapter| adapter.index == *index)
+            .ok_or_else(|| format!("adapter #{} does not exist, {} adapters found", index, adapters.len())),
+        AdapterSelection::Name(name) => {
+            let name = name.to_lowercase();
+            adapters.iter()
+                .position(|adapter| adapter.name.to_lowercase().contains(&name))
+                .ok_or_else(|| format!("no adapter name contains \"{}\"", name))
+        }
+        AdapterSelection::Preference(preference) => {
+            let hardware = || adapters.iter().enumerate().filter(|(_, adapter)| !adapter.software);
+            let chosen = match preference {
+                AdapterPreference::Default => hardware().next(),
+                // 記憶體相同時取列舉順序較前面的
+                AdapterPreference::HighPerformance => hardware().rev().max_by_key(|(_, adapter)| adapter.dedicated_video_memory),
+                AdapterPreference::MinimumPower => hardware().min_by_key(|(_, adapter)| adapter.dedicated_video_memory),
+            };
+            Ok(chosen.map(|(position, _)| position).unwrap_or(0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn adapter(index: usize, name: &str, dedicated_mib: u64, software: bool) -> AdapterInfo {
+        AdapterInfo {
+            index,
+            name: name.to_string(),
+            vendor_id: 0x10DE,
+            device_id: 0x2684,
+            dedicated_video_memory: dedicated_mib * MIB,
+            shared_system_memory: 0,
+            software,
+        }
+    }
+
+    fn adapters() -> Vec<AdapterInfo> {
+        vec![
+            adapter(0, "Microsoft Basic Render Driver", 0, true),
+            adapter(1, "Intel(R) UHD Graphics 770", 128, false),
+            adapter(2, "AMD Radeon RX 7900 XTX", 24576, false),
+            adapter(3, "NVIDIA GeForce RTX 4090", 24576, false),
+            adapter(4, "Intel(R) Arc(TM) A380", 128, false),
+        ]
+    }
+
+    fn select(adapters: &[AdapterInfo], selection: &str) -> Result<usize, String> {
+        select_adapter(adapters, &selection.parse()?)
+    }
+
+    #[test]
+    fn preferences_skip_software_adapters() {
+        let adapters = adapters();
+        assert_eq!(select(&adapters, "default"), Ok(1));
+        // 記憶體相同時取前面的
+        assert_eq!(select(&adapters, "high-performance"), Ok(2));
+        assert_eq!(select(&adapters, "minimum-power"), Ok(1));
+    }
+
+    #[test]
+    fn falls_back_to_the_first_adapter_without_hardware() {
+        let adapters = [adapter(0, "Microsoft Basic Render Driver", 0, true), adapter(1, "WARP", 0, true)];
+        for preference in AdapterPreference::ALL {
+            assert_eq!(select_adapter(&adapters, &AdapterSelection::Preference(preference)), Ok(0));
+        }
+        assert_eq!(select_adapter(&[], &AdapterSelection::default()), Err("no display adapters found".to_string()));
+    }
+
+    #[test]
+    fn selects_by_name_or_index() {
+        let adapters = adapters();
+        assert_eq!(select(&adapters, "RADEON"), Ok(2));
+        assert_eq!(select(&adapters, "intel"), Ok(1));
+        assert_eq!(select(&adapters, "Geforce rtx"), Ok(3));
+        assert_eq!(select(&adapters, "Matrox"), Err("no adapter name contains \"matrox\"".to_string()));
+
+        assert_eq!(select(&adapters, "3"), Ok(3));
+        assert_eq!(select(&adapters, "5"), Err("adapter #5 does not exist, 5 adapters found".to_string()));
+        // 編號是列舉時的編號，不是在清單中的位置
+        assert_eq!(select(&adapters[2..], "3"), Ok(1));
+    }
+
+    #[test]
+    fn parses_selections() {
+        assert_eq!("1".parse(), Ok(AdapterSelection::Index(1)));
+        assert_eq!("high-performance".parse(), Ok(AdapterSelection::Preference(AdapterPreference::HighPerformance)));
+        assert_eq!(" Minimum-Power ".parse(), Ok(AdapterSelection::Preference(AdapterPreference::MinimumPower)));
+        assert_eq!("radeon".parse(), Ok(AdapterSelection::Name("radeon".to_string())));
+        assert_eq!("-1".parse(), Ok(AdapterSelection::Name("-1".to_string())));
+        assert!("  ".parse::<AdapterSelection>().is_err());
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        adapter: AdapterSelection,
+    }
+
+    fn deserialize(value: &str) -> Result<AdapterSelection, String> {
+        toml::from_str::<Settings>(&format!("adapter = {}", value)).map(|settings| settings.adapter).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn deserializes_from_toml() {
+        assert_eq!(deserialize("1"), Ok(AdapterSelection::Index(1)));
+        assert_eq!(deserialize("\"2\""), Ok(AdapterSelection::Index(2)));
+        assert_eq!(deserialize("\"minimum-power\""), Ok(AdapterSelection::Preference(AdapterPreference::MinimumPower)));
+        assert_eq!(deserialize("\"radeon\""), Ok(AdapterSelection::Name("radeon".to_string())));
+        assert!(deserialize("-1").unwrap_err().contains("adapter index must not be negative, got -1"));
+        assert!(deserialize("\"\"").is_err());
+        assert!(deserialize("1.5").is_err());
+    }
+
+    #[test]
+    fn formats_adapters() {
+        assert_eq!(adapter(3, "NVIDIA GeForce RTX 4090", 24576, false).to_string(), "#3 NVIDIA GeForce RTX 4090 (NVIDIA, 10DE:2684), 24576 MiB dedicated, 0 MiB shared");
+        assert!(adapter(0, "WARP", 0, true).to_string().ends_with(", software"));
+        assert_eq!(AdapterSelection::Index(2).to_string(), "#2");
+        assert_eq!(AdapterSelection::Name("radeon".to_string()).to_string(), "\"radeon\"");
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::lzss;
+use crate::vfs::{normalize_path, Mount};
+
+const MAGIC: [u8; 4] = *b"RPAK";
+const VERSION: u32 = 1;
+// magic、version、檔案數量、索引位置
+const HEADER_SIZE: u64 = 4 + 4 + 4 + 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Lzss,
+}
+
+impl Compression {
+    fn from_raw(raw: u8) -> Option<Compression> {
+        match raw {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lzss),
+            _ => None,
+        }
+    }
+
+    fn raw(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lzss => 1,
+        }
+    }
+}
+
+/// 封裝檔索引中的一個檔案。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub offset: u64,
+    /// 封裝檔中實際佔用的大小。
+    pub stored_size: u64,
+    /// 解壓縮後的大小。
+    pub size: u64,
+    pub compression: Compression,
+}
+
+/// 建立封裝檔。檔案格式（little-endian）：
+///
+/// ```text
+/// "RPAK" | version: u32 | 檔案數量: u32 | 索引位置: u64
+/// 各檔案的資料
+/// 索引：每個檔案 路徑長度: u16 | 路徑 (UTF-8) | 位置: u64 | 儲存大小: u64 | 原始大小: u64 | 壓縮方式: u8
+/// ```
+///
+/// 壓縮只在結果比較小時使用，否則原樣儲存。
+pub struct ArchiveBuilder {
+    files: BTreeMap<String, Vec<u8>>,
+    compress: bool,
+}
+
+impl ArchiveBuilder {
+    pub fn new(compress: bool) -> Self {
+        Self { files: BTreeMap::new(), compress }
+    }
+
+    /// 加入一個檔案，路徑相同時取代之前加入的內容。
+    pub fn add(&mut self, path: impl AsRef<Path>, data: Vec<u8>) -> Result<(), String> {
+        let path = normalize_path(path.as_ref())?;
+        if path.len() > u16::MAX as usize {
+            return Err(format!("{}: path is too long", path));
+        }
+        self.files.insert(path, data);
+        Ok(())
+    }
+
+    /// 遞迴加入 `dir` 下的所有檔案，封裝檔中的路徑為 `prefix` 加上相對於 `dir` 的路徑。
+    pub fn add_dir(&mut self, dir: impl AsRef<Path>, prefix: &str) -> Result<(), String> {
+        let dir = dir.as_ref();
+        let mut entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: {}", dir.display(), e))?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let archive_path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            if path.is_dir() {
+                self.add_dir(&path, &archive_path)?;
+            } else {
+                let data = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                self.add(&archive_path, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn write(&self, output: &mut impl Write) -> Result<ArchiveSummary, String> {
+        let io_error = |e: std::io::Error| e.to_string();
+        let mut summary = ArchiveSummary::default();
+        let mut index = Vec::new();
+        let mut offset = HEADER_SIZE;
+        let mut data = Vec::new();
+        for (path, contents) in &self.files {
+            let compressed = if self.compress { Some(lzss::compress(contents)) } else { None };
+            let (stored, compression) = match &compressed {
+                Some(compressed) if compressed.len() < contents.len() => (compressed.as_slice(), Compression::Lzss),
+                _ => (contents.as_slice(), Compression::None),
+            };
+            index.extend((path.len() as u16).to_le_bytes());
+            index.extend(path.as_bytes());
+            index.extend(offset.to_le_bytes());
+            index.extend((stored.len() as u64).to_le_bytes());
+            index.extend((contents.len() as u64).to_le_bytes());
+            index.push(compression.raw());
+            data.extend_from_slice(stored);
+            offset += stored.len() as u64;
+            summary.files += 1;
+            summary.size += contents.len() as u64;
+            summary.stored_size += stored.len() as u64;
+        }
+        output.write_all(&MAGIC).map_err(io_error)?;
+        output.write_all(&VERSION.to_le_bytes()).map_err(io_error)?;
+        output.write_all(&(self.files.len() as u32).to_le_bytes()).map_err(io_error)?;
+        output.write_all(&offset.to_le_bytes()).map_err(io_error)?;
+        output.write_all(&data).map_err(io_error)?;
+        output.write_all(&index).map_err(io_error)?;
+        Ok(summary)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<ArchiveSummary, String> {
+        let path = path.as_ref();
+        let mut file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.write(&mut file).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// 寫入封裝檔的結果。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ArchiveSummary {
+    pub files: usize,
+    /// 原始大小的總和。
+    pub size: u64,
+    /// 資料區實際佔用的大小，不含 header 與索引。
+    pub stored_size: u64,
+}
+
+impl fmt::Display for ArchiveSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} files, {} bytes stored as {} bytes", self.files, self.size, self.stored_size)?;
+        if self.size > 0 {
+            write!(f, " ({:.0}%)", self.stored_size as f64 / self.size as f64 * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// 唯讀的封裝檔。開啟時只讀取索引，檔案內容在讀取時才從來源取出並解壓縮。
+pub struct Archive<R> {
+    name: String,
+    source: Mutex<R>,
+    entries: BTreeMap<String, ArchiveEntry>,
+}
+
+impl Archive<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Archive::new(path.display().to_string(), BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl<R: Read + Seek> Archive<R> {
+    /// `name` 只用在錯誤訊息與 `describe`。
+    pub fn new(name: String, mut source: R) -> Result<Self, String> {
+        let io_error = |e: std::io::Error| format!("cannot read archive: {}", e);
+        let mut header = [0_u8; HEADER_SIZE as usize];
+        source.read_exact(&mut header).map_err(io_error)?;
+        if header[..4] != MAGIC {
+            return Err("not an archive file".to_string());
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(format!("unsupported archive version {}, expected {}", version, VERSION));
+        }
+        let count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let index_offset = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        let length = source.seek(SeekFrom::End(0)).map_err(io_error)?;
+        if index_offset < HEADER_SIZE || index_offset > length {
+            return Err("archive index lies outside the archive".to_string());
+        }
+        source.seek(SeekFrom::Start(index_offset)).map_err(io_error)?;
+        let mut index = Vec::new();
+        source.read_to_end(&mut index).map_err(io_error)?;
+
+        let mut reader = index.as_slice();
+        let mut take = |count: usize| -> Result<&[u8], String> {
+            if reader.len() < count {
+                return Err("archive index is truncated".to_string());
+            }
+            let (bytes, rest) = reader.split_at(count);
+            reader = rest;
+            Ok(bytes)
+        };
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let path_length = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            let path = std::str::from_utf8(take(path_length)?).map_err(|_| "archive path is not valid UTF-8".to_string())?.to_string();
+            let offset = u64::from_le_bytes(take(8)?.try_into().unwrap());
+            let stored_size = u64::from_le_bytes(take(8)?.try_into().unwrap());
+            let size = u64::from_le_bytes(take(8)?.try_into().unwrap());
+            let raw_compression = take(1)?[0];
+            let compression = Compression::from_raw(raw_compression)
+                .ok_or_else(|| format!("{}: unknown compression {}", path, raw_compression))?;
+            if offset < HEADER_SIZE || offset.checked_add(stored_size).is_none_or(|end| end > index_offset) {
+                return Err(format!("{}: data lies outside the archive", path));
+            }
+            // 讀取時依 size 配置記憶體，不能相信損壞的索引
+            let valid_size = match compression {
+                Compression::None => size == stored_size,
+                Compression::Lzss => size <= lzss::max_decompressed_size(stored_size as usize) as u64,
+            };
+            if !valid_size {
+                return Err(format!("{}: size {} does not match the {} stored bytes", path, size, stored_size));
+            }
+            entries.insert(path, ArchiveEntry { offset, stored_size, size, compression });
+        }
+        Ok(Self { name, source: Mutex::new(source), entries })
+    }
+
+    fn read_entry(&self, path: &str, entry: &ArchiveEntry) -> Result<Vec<u8>, String> {
+        let mut stored = vec![0_u8; entry.stored_size as usize];
+        {
+            let mut source = self.source.lock().unwrap();
+            source.seek(SeekFrom::Start(entry.offset))
+                .and_then(|_| source.read_exact(&mut stored))
+                .map_err(|e| format!("{} in {}: {}", path, self.name, e))?;
+        }
+        match entry.compression {
+            Compression::None => Ok(stored),
+            Compression::Lzss => lzss::decompress(&stored, entry.size as usize).map_err(|e| format!("{} in {}: {}", path, self.name, e)),
+        }
+    }
+}
+
+impl<R: Read + Seek + Send> Mount for Archive<R> {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.entries.get(path) {
+            Some(entry) => self.read_entry(path, entry).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn files(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+
+    fn describe(&self) -> String {
+        format!("archive {}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn build(compress: bool) -> Vec<u8> {
+        let mut builder = ArchiveBuilder::new(compress);
+        builder.add("hlsl/triangle_vs.hlsl", b"float4 VS() : SV_Position { return 0; }\n".repeat(20)).unwrap();
+        builder.add("meshes\\./cube.obj", b"v 1 2 3\n".to_vec()).unwrap();
+        builder.add("empty.txt", vec![]).unwrap();
+        let mut bytes = vec![];
+        let summary = builder.write(&mut bytes).unwrap();
+        assert_eq!(summary.files, 3);
+        bytes
+    }
+
+    fn open(bytes: Vec<u8>) -> Result<Archive<Cursor<Vec<u8>>>, String> {
+        Archive::new("test.rpak".to_string(), Cursor::new(bytes))
+    }
+
+    fn index_offset(bytes: &[u8]) -> usize {
+        u64::from_le_bytes(bytes[12..20].try_into().unwrap()) as usize
+    }
+
+    /// 索引中第一個檔案（依路徑排序為 `empty.txt`）的欄位位置：位置、儲存大小、原始大小。
+    fn first_entry_fields(bytes: &[u8]) -> (usize, usize, usize) {
+        let index = index_offset(bytes);
+        let path_length = u16::from_le_bytes(bytes[index..index + 2].try_into().unwrap()) as usize;
+        let offset = index + 2 + path_length;
+        (offset, offset + 8, offset + 16)
+    }
+
+    #[test]
+    fn round_trips_files() {
+        for compress in [false, true] {
+            let archive = open(build(compress)).unwrap();
+            assert_eq!(archive.files(), ["empty.txt", "hlsl/triangle_vs.hlsl", "meshes/cube.obj"]);
+            assert_eq!(archive.read("hlsl/triangle_vs.hlsl").unwrap().unwrap(), b"float4 VS() : SV_Position { return 0; }\n".repeat(20));
+            assert_eq!(archive.read("meshes/cube.obj").unwrap().unwrap(), b"v 1 2 3\n");
+            assert_eq!(archive.read("empty.txt").unwrap().unwrap(), b"");
+            assert_eq!(archive.read("missing.obj").unwrap(), None);
+
+            let shader = &archive.entries["hlsl/triangle_vs.hlsl"];
+            let expected = if compress { Compression::Lzss } else { Compression::None };
+            assert_eq!(shader.compression, expected);
+            // 壓縮後沒有變小的檔案原樣儲存
+            assert_eq!(archive.entries["meshes/cube.obj"].compression, Compression::None);
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = build(false);
+        bytes[0] = b'X';
+        assert_eq!(open(bytes).err().unwrap(), "not an archive file");
+
+        let mut bytes = build(false);
+        bytes[4] = 2;
+        assert!(open(bytes).err().unwrap().contains("unsupported archive version 2"));
+
+        assert!(open(b"RPAK".to_vec()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_index() {
+        let bytes = build(true);
+        for length in [index_offset(&bytes) + 1, bytes.len() - 1] {
+            assert_eq!(open(bytes[..length].to_vec()).err().unwrap(), "archive index is truncated");
+        }
+        // 索引位置本身被截掉
+        assert!(open(bytes[..index_offset(&bytes) - 1].to_vec()).is_err());
+    }
+
+    #[test]
+    fn rejects_index_outside_the_archive() {
+        let mut bytes = build(false);
+        bytes[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(open(bytes).err().unwrap(), "archive index lies outside the archive");
+    }
+
+    #[test]
+    fn rejects_data_outside_the_archive() {
+        let mut bytes = build(false);
+        let (offset, stored_size, size) = first_entry_fields(&bytes);
+        bytes[stored_size..stored_size + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes[size..size + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(open(bytes).err().unwrap().contains("data lies outside the archive"));
+
+        let mut bytes = build(false);
+        bytes[offset..offset + 8].copy_from_slice(&0_u64.to_le_bytes());
+        assert!(open(bytes).err().unwrap().contains("data lies outside the archive"));
+    }
+
+    #[test]
+    fn rejects_corrupt_sizes() {
+        // 沒有壓縮時兩個大小必須相同
+        let mut bytes = build(false);
+        let (_, _, size) = first_entry_fields(&bytes);
+        bytes[size..size + 8].copy_from_slice(&1_u64.to_le_bytes());
+        assert!(open(bytes).err().unwrap().contains("does not match"));
+
+        // 壓縮的檔案不能宣稱比壓縮資料可能解出的還大
+        let mut bytes = build(true);
+        let index = index_offset(&bytes);
+        let shader = bytes[index..].windows(21).position(|window| window == b"hlsl/triangle_vs.hlsl").unwrap() + index;
+        let size = shader + 21 + 16;
+        bytes[size..size + 8].copy_from_slice(&(1_u64 << 40).to_le_bytes());
+        assert!(open(bytes).err().unwrap().contains("does not match"));
+    }
+
+    #[test]
+    fn reports_corrupt_compressed_data_when_read() {
+        let mut bytes = build(true);
+        let archive = open(bytes.clone()).unwrap();
+        let entry = archive.entries["hlsl/triangle_vs.hlsl"];
+        // 把資料全部改成回頭複製，第一個項目就超出已解出的範圍
+        bytes[entry.offset as usize..(entry.offset + entry.stored_size) as usize].fill(0xFF);
+        let archive = open(bytes).unwrap();
+        let error = archive.read("hlsl/triangle_vs.hlsl").unwrap_err();
+        assert!(error.starts_with("hlsl/triangle_vs.hlsl in test.rpak: "), "{}", error);
+    }
+}
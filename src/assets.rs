@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::profiler::profile_scope;
+use crate::thread_pool::ThreadPool;
+use crate::vfs;
+
+/// 讀取資產檔案內容的函式，預設經由 `vfs::file_system` 讀取。
+pub type AssetReader = Arc<dyn Fn(&Path) -> Result<Vec<u8>, String> + Send + Sync>;
+
+/// 資產的編號。同一個路徑在卸載之前都對應同一個編號，卸載後再載入會拿到新的編號。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(pub u64);
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "asset #{}", self.0)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    /// `.obj`
+    Mesh,
+    /// `.ppm`（binary P6）
+    Texture,
+    /// `.cso` 或 `.hlsl`，檔名以 `_vs`、`_ps` 結尾決定階段
+    Shader,
+}
+
+impl AssetKind {
+    pub fn from_path(path: &Path) -> Option<AssetKind> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(AssetKind::Mesh),
+            "ppm" => Some(AssetKind::Texture),
+            "cso" | "hlsl" => Some(AssetKind::Shader),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+/// CPU 端的網格資料，上傳到 GPU 前的形式。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u16>,
+}
+
+impl MeshData {
+    /// 讀取 OBJ 的子集：`v x y z [r g b]` 與 `f a b c ...`，多邊形以扇形切成三角形。
+    /// 索引可以帶 `/vt/vn`（只取頂點），負數表示從目前最後一個頂點往回數。
+    pub fn from_obj(text: &str) -> Result<MeshData, String> {
+        let mut mesh = MeshData::default();
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("v") => {
+                    let values = parts.map(str::parse::<f32>).collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| format!("line {}: {}", line_number, e))?;
+                    let color = match values.len() {
+                        3 => [1.0; 4],
+                        6 => [values[3], values[4], values[5], 1.0],
+                        count => return Err(format!("line {}: a vertex needs 3 or 6 values, got {}", line_number, count)),
+                    };
+                    if mesh.vertices.len() > u16::MAX as usize {
+                        return Err(format!("line {}: meshes are limited to {} vertices", line_number, u16::MAX as usize + 1));
+                    }
+                    mesh.vertices.push(MeshVertex { position: [values[0], values[1], values[2]], color });
+                }
+                Some("f") => {
+                    let indices = parts.map(|part| Self::obj_index(part, mesh.vertices.len()))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| format!("line {}: {}", line_number, e))?;
+                    if indices.len() < 3 {
+                        return Err(format!("line {}: a face needs at least 3 vertices", line_number));
+                    }
+                    for i in 1..indices.len() - 1 {
+                        mesh.indices.extend([indices[0], indices[i], indices[i + 1]]);
+                    }
+                }
+                // 註解、法線、貼圖座標、群組與材質都不影響網格的形狀
+                _ => {}
+            }
+        }
+        if mesh.indices.is_empty() {
+            return Err("mesh has no faces".to_string());
+        }
+        Ok(mesh)
+    }
+
+    fn obj_index(part: &str, vertex_count: usize) -> Result<u16, String> {
+        let index = part.split('/').next().unwrap_or_default();
+        let index = index.parse::<i64>().map_err(|_| format!("invalid face index \"{}\"", part))?;
+        let resolved = match index {
+            0 => None,
+            index if index > 0 => Some(index - 1),
+            index => Some(vertex_count as i64 + index),
+        };
+        resolved.filter(|index| (0..vertex_count as i64).contains(index))
+            .map(|index| index as u16)
+            .ok_or_else(|| format!("face index {} is out of range, {} vertices defined", index, vertex_count))
+    }
+}
+
+/// CPU 端的 RGBA8 紋理。
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<[u8; 4]>,
+}
+
+impl TextureData {
+    /// 讀取 binary PPM（P6），最大值必須是 255，alpha 一律為 255。
+    pub fn from_ppm(bytes: &[u8]) -> Result<TextureData, String> {
+        let mut position = 0;
+        let mut fields = [0_u32; 3];
+        if bytes.get(..2) != Some(b"P6".as_slice()) {
+            return Err("not a binary PPM (P6) file".to_string());
+        }
+        position += 2;
+        for field in fields.iter_mut() {
+            // 欄位之間是空白或 # 開頭的註解
+            loop {
+                match bytes.get(position) {
+                    Some(b'#') => while bytes.get(position).is_some_and(|b| *b != b'\n') {
+                        position += 1;
+                    },
+                    Some(b) if b.is_ascii_whitespace() => position += 1,
+                    _ => break,
+                }
+            }
+            let start = position;
+            while bytes.get(position).is_some_and(u8::is_ascii_digit) {
+                position += 1;
+            }
+            *field = std::str::from_utf8(&bytes[start..position]).unwrap().parse()
+                .map_err(|_| "invalid PPM header".to_string())?;
+        }
+        let [width, height, max_value] = fields;
+        if max_value != 255 {
+            return Err(format!("only 8-bit PPM files are supported, max value is {}", max_value));
+        }
+        if width == 0 || height == 0 {
+            return Err("texture size must not be zero".to_string());
+        }
+        // header 之後恰好一個空白字元
+        let data = bytes.get(position + 1..).unwrap_or_default();
+        let expected = width as usize * height as usize * 3;
+        if data.len() < expected {
+            return Err(format!("PPM data is truncated, expected {} bytes, got {}", expected, data.len()));
+        }
+        let texels = data[..expected].chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect();
+        Ok(TextureData { width, height, texels })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Pixel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderCode {
+    /// 預先編譯的 `.cso`。
+    Bytecode(Vec<u8>),
+    /// `.hlsl` 原始碼，上傳時才編譯，進入點為 `VS` 或 `PS`。
+    Hlsl(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderData {
+    pub stage: ShaderStage,
+    pub code: ShaderCode,
+}
+
+impl ShaderData {
+    pub fn from_file(path: &Path, bytes: Vec<u8>) -> Result<ShaderData, String> {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_ascii_lowercase();
+        let stage = if stem.ends_with("_vs") {
+            ShaderStage::Vertex
+        } else if stem.ends_with("_ps") {
+            ShaderStage::Pixel
+        } else {
+            return Err("shader file names must end with _vs or _ps".to_string());
+        };
+        let is_hlsl = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("hlsl"));
+        let code = if is_hlsl {
+            ShaderCode::Hlsl(String::from_utf8(bytes).map_err(|_| "HLSL source is not valid UTF-8".to_string())?)
+        } else {
+            ShaderCode::Bytecode(bytes)
+        };
+        Ok(ShaderData { stage, code })
+    }
+}
+
+/// 載入完成的資產內容。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetData {
+    Mesh(MeshData),
+    Texture(TextureData),
+    Shader(ShaderData),
+}
+
+impl AssetData {
+    fn decode(kind: AssetKind, path: &Path, bytes: Vec<u8>) -> Result<AssetData, String> {
+        match kind {
+            AssetKind::Mesh => {
+                let text = String::from_utf8(bytes).map_err(|_| "OBJ file is not valid UTF-8".to_string())?;
+                MeshData::from_obj(&text).map(AssetData::Mesh)
+            }
+            AssetKind::Texture => TextureData::from_ppm(&bytes).map(AssetData::Texture),
+            AssetKind::Shader => ShaderData::from_file(path, bytes).map(AssetData::Shader),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetState {
+    Loading,
+    Loaded(AssetData),
+    Failed(String),
+}
+
+/// `AssetRegistry::update` 回報的變化，GPU 端依此建立或釋放對應的資源。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetEvent {
+    Loaded(AssetId),
+    Failed(AssetId, String),
+    /// 參考計數歸零後被卸載，之後不能再用這個編號取得資產。
+    Unloaded(AssetId),
+}
+
+/// 目前登錄的資產中有多少已經處理完。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct LoadProgress {
+    pub total: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl LoadProgress {
+    pub fn pending(&self) -> usize {
+        self.total - self.loaded - self.failed
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending() == 0
+    }
+}
+
+impl fmt::Display for LoadProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} assets loaded", self.loaded, self.total)?;
+        if self.failed > 0 {
+            write!(f, ", {} failed", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+struct AssetEntry {
+    path: PathBuf,
+    kind: AssetKind,
+    ref_count: usize,
+    state: AssetState,
+}
+
+type LoadResult = (AssetId, Result<AssetData, String>);
+
+/// 以路徑為鍵的資產登錄表。檔案在背景執行緒讀取與解析，結果在呼叫 `update` 時收進來；
+/// 重複要求同一個路徑只會載入一次並增加參考計數，`release` 到 0 時卸載。
+pub struct AssetRegistry {
+    reader: AssetReader,
+    pool: ThreadPool,
+    entries: HashMap<AssetId, AssetEntry>,
+    ids: HashMap<PathBuf, AssetId>,
+    next_id: u64,
+    sender: Sender<LoadResult>,
+    receiver: Receiver<LoadResult>,
+    // 已送到背景執行緒、還沒收到結果的工作數，包含重新載入
+    in_flight: usize,
+    // 在 `release` 時就卸載的資產，下一次 `update` 時回報
+    pending_events: Vec<AssetEvent>,
+}
+
+impl AssetRegistry {
+    /// 從檔案系統中的 `root` 目錄下讀取。
+    pub fn new(root: impl Into<PathBuf>, threads: usize) -> Self {
+        let root = root.into();
+        let file_system = vfs::file_system();
+        let reader: AssetReader = Arc::new(move |path: &Path| file_system.read(root.join(path)));
+        Self::with_reader(reader, threads)
+    }
+
+    /// 以自訂的函式讀取檔案內容，例如從封裝檔讀取。
+    pub fn with_reader(reader: AssetReader, threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            reader,
+            pool: ThreadPool::new(threads, "asset-loader"),
+            entries: HashMap::new(),
+            ids: HashMap::new(),
+            next_id: 0,
+            sender,
+            receiver,
+            in_flight: 0,
+            pending_events: vec![],
+        }
+    }
+
+    /// 以 `/` 分隔、去掉 `.` 的相對路徑，讓 `a/./b.obj` 與 `a\b.obj` 對應到同一個資產。
+    pub fn normalize_path(path: &Path) -> Result<PathBuf, String> {
+        vfs::normalize_path(path).map(PathBuf::from)
+    }
+
+    /// 要求載入資產並增加參考計數。已經登錄的路徑直接回傳原本的編號，不會重新讀取。
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<AssetId, String> {
+        let path = Self::normalize_path(path.as_ref())?;
+        if let Some(id) = self.ids.get(&path) {
+            self.entries.get_mut(id).unwrap().ref_count += 1;
+            return Ok(*id);
+        }
+        let kind = AssetKind::from_path(&path)
+            .ok_or_else(|| format!("{}: unsupported asset type", path.display()))?;
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+        self.entries.insert(id, AssetEntry { path: path.clone(), kind, ref_count: 1, state: AssetState::Loading });
+        self.ids.insert(path.clone(), id);
+        self.start_load(id, path, kind);
+        Ok(id)
+    }
+
+    fn start_load(&mut self, id: AssetId, path: PathBuf, kind: AssetKind) {
+        self.in_flight += 1;
+        let reader = self.reader.clone();
+        let sender = self.sender.clone();
+        self.pool.execute(move || {
+            profile_scope!("load_asset");
+            let result = reader(&path)
+                .and_then(|bytes| AssetData::decode(kind, &path, bytes))
+                .map_err(|e| format!("{}: {}", path.display(), e));
+            // registry 已經釋放時沒有人需要結果
+            let _ = sender.send((id, result));
+        });
+    }
+
+    /// 重新讀取檔案，例如檔案修改後。載入完成前 `get` 仍回傳舊的內容。
+    // 還沒有監看檔案修改，目前只有測試呼叫
+    #[allow(dead_code)]
+    pub fn reload(&mut self, id: AssetId) -> Result<(), String> {
+        let entry = self.entries.get(&id).ok_or_else(|| format!("{} is not loaded", id))?;
+        let (path, kind) = (entry.path.clone(), entry.kind);
+        self.start_load(id, path, kind);
+        Ok(())
+    }
+
+    /// 減少參考計數，歸零時卸載。仍在載入中的資產等結果回來後才卸載。
+    // 場景的資產在整個執行期間都保留，目前只有測試呼叫
+    #[allow(dead_code)]
+    pub fn release(&mut self, id: AssetId) -> Result<(), String> {
+        let entry = self.entries.get_mut(&id).ok_or_else(|| format!("{} is not loaded", id))?;
+        if entry.ref_count == 0 {
+            return Err(format!("{} ({}) has already been released", id, entry.path.display()));
+        }
+        entry.ref_count -= 1;
+        if entry.ref_count == 0 && entry.state != AssetState::Loading {
+            self.unload(id);
+        }
+        Ok(())
+    }
+
+    fn unload(&mut self, id: AssetId) {
+        if let Some(entry) = self.entries.remove(&id) {
+            self.ids.remove(&entry.path);
+            self.pending_events.push(AssetEvent::Unloaded(id));
+        }
+    }
+
+    /// 收取背景執行緒完成的結果，不會等待。
+    pub fn update(&mut self) -> Vec<AssetEvent> {
+        while let Ok(result) = self.receiver.try_recv() {
+            self.finish_load(result);
+        }
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// 等到目前所有要求都處理完或超過 `timeout`，用在載入畫面或啟動時。
+    pub fn wait(&mut self, timeout: Duration) -> Vec<AssetEvent> {
+        let deadline = Instant::now() + timeout;
+        while self.in_flight > 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(remaining) {
+                Ok(result) => self.finish_load(result),
+                Err(_) => break,
+            }
+        }
+        self.update()
+    }
+
+    fn finish_load(&mut self, (id, result): LoadResult) {
+        self.in_flight -= 1;
+        // 結果回來前已經卸載
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return;
+        };
+        let event = match result {
+            Ok(data) => {
+                entry.state = AssetState::Loaded(data);
+                AssetEvent::Loaded(id)
+            }
+            // 重新載入失敗時保留原本的內容
+            Err(e) if matches!(entry.state, AssetState::Loaded(_)) => AssetEvent::Failed(id, e),
+            Err(e) => {
+                entry.state = AssetState::Failed(e.clone());
+                AssetEvent::Failed(id, e)
+            }
+        };
+        let unreferenced = entry.ref_count == 0;
+        self.pending_events.push(event);
+        if unreferenced {
+            self.unload(id);
+        }
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn get(&self, id: AssetId) -> Option<&AssetData> {
+        match &self.entries.get(&id)?.state {
+            AssetState::Loaded(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn state(&self, id: AssetId) -> Option<&AssetState> {
+        self.entries.get(&id).map(|entry| &entry.state)
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn id(&self, path: impl AsRef<Path>) -> Option<AssetId> {
+        self.ids.get(&Self::normalize_path(path.as_ref()).ok()?).copied()
+    }
+
+    pub fn path(&self, id: AssetId) -> Option<&Path> {
+        self.entries.get(&id).map(|entry| entry.path.as_path())
+    }
+
+    #[allow(dead_code)]
+    pub fn ref_count(&self, id: AssetId) -> usize {
+        self.entries.get(&id).map_or(0, |entry| entry.ref_count)
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        let mut progress = LoadProgress { total: self.entries.len(), ..LoadProgress::default() };
+        for entry in self.entries.values() {
+            match entry.state {
+                AssetState::Loaded(_) => progress.loaded += 1,
+                AssetState::Failed(_) => progress.failed += 1,
+                AssetState::Loading => {}
+            }
+        }
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// 從記憶體中的檔案讀取，並記錄讀取次數。
+    fn memory_reader(files: &[(&str, &[u8])]) -> (AssetReader, Arc<AtomicUsize>) {
+        let files: HashMap<PathBuf, Vec<u8>> = files.iter().map(|(path, bytes)| (PathBuf::from(path), bytes.to_vec())).collect();
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counter = reads.clone();
+        let reader: AssetReader = Arc::new(move |path: &Path| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            files.get(path).cloned().ok_or_else(|| "file not found".to_string())
+        });
+        (reader, reads)
+    }
+
+    fn registry(files: &[(&str, &[u8])]) -> (AssetRegistry, Arc<AtomicUsize>) {
+        let (reader, reads) = memory_reader(files);
+        (AssetRegistry::with_reader(reader, 2), reads)
+    }
+
+    #[test]
+    fn same_path_is_loaded_once() {
+        let (mut assets, reads) = registry(&[("meshes/triangle.obj", TRIANGLE.as_bytes())]);
+        let id = assets.load("meshes/triangle.obj").unwrap();
+        assert_eq!(assets.load("meshes/./triangle.obj").unwrap(), id);
+        assert_eq!(assets.load("meshes\\triangle.obj").unwrap(), id);
+        assert_eq!(assets.wait(TIMEOUT), [AssetEvent::Loaded(id)]);
+
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+        assert_eq!(assets.ref_count(id), 3);
+        assert_eq!(assets.id("meshes/triangle.obj"), Some(id));
+        assert_eq!(assets.path(id), Some(Path::new("meshes/triangle.obj")));
+        assert!(matches!(assets.get(id), Some(AssetData::Mesh(mesh)) if mesh.indices == [0, 1, 2]));
+    }
+
+    #[test]
+    fn releasing_the_last_reference_unloads() {
+        let (mut assets, reads) = registry(&[("triangle.obj", TRIANGLE.as_bytes())]);
+        let id = assets.load("triangle.obj").unwrap();
+        assets.load("triangle.obj").unwrap();
+        assets.wait(TIMEOUT);
+
+        assets.release(id).unwrap();
+        assert_eq!(assets.ref_count(id), 1);
+        assert!(assets.update().is_empty());
+        assets.release(id).unwrap();
+        assert_eq!(assets.update(), [AssetEvent::Unloaded(id)]);
+        assert_eq!(assets.state(id), None);
+        assert_eq!(assets.id("triangle.obj"), None);
+        assert!(assets.release(id).is_err());
+
+        // 卸載後再載入是新的資產
+        let reloaded = assets.load("triangle.obj").unwrap();
+        assert_ne!(reloaded, id);
+        assets.wait(TIMEOUT);
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn release_while_loading_unloads_when_the_result_arrives() {
+        let (gate, blocked) = mpsc::channel::<()>();
+        let blocked = Mutex::new(blocked);
+        let reader: AssetReader = Arc::new(move |_: &Path| {
+            blocked.lock().unwrap().recv().map_err(|e| e.to_string())?;
+            Ok(TRIANGLE.as_bytes().to_vec())
+        });
+        let mut assets = AssetRegistry::with_reader(reader, 1);
+        let id = assets.load("triangle.obj").unwrap();
+        assets.release(id).unwrap();
+
+        // 還在讀取中，先保留登錄直到結果回來
+        assert_eq!(assets.state(id), Some(&AssetState::Loading));
+        assert!(assets.update().is_empty());
+        assert!(assets.release(id).is_err());
+
+        gate.send(()).unwrap();
+        assert_eq!(assets.wait(TIMEOUT), [AssetEvent::Loaded(id), AssetEvent::Unloaded(id)]);
+        assert_eq!(assets.state(id), None);
+        assert_eq!(assets.progress(), LoadProgress::default());
+    }
+
+    #[test]
+    fn load_failures_are_reported() {
+        let (mut assets, _) = registry(&[("broken.obj", b"v 0 0 0\n")]);
+        let missing = assets.load("missing.obj").unwrap();
+        let broken = assets.load("broken.obj").unwrap();
+        let events = assets.wait(TIMEOUT);
+
+        assert_eq!(events.len(), 2);
+        for id in [missing, broken] {
+            let reported = events.iter().find_map(|event| match event {
+                AssetEvent::Failed(failed, e) if *failed == id => Some(e.clone()),
+                _ => None,
+            });
+            let reported = reported.unwrap_or_else(|| panic!("no failure reported for {}", id));
+            assert_eq!(assets.state(id), Some(&AssetState::Failed(reported)));
+            assert_eq!(assets.get(id), None);
+        }
+        assert!(matches!(assets.state(missing), Some(AssetState::Failed(e)) if e == "missing.obj: file not found"));
+        assert_eq!(assets.progress(), LoadProgress { total: 2, loaded: 0, failed: 2 });
+    }
+
+    #[test]
+    fn failed_reload_keeps_the_previous_data() {
+        let contents = Arc::new(Mutex::new(TRIANGLE.as_bytes().to_vec()));
+        let current = contents.clone();
+        let reader: AssetReader = Arc::new(move |_: &Path| Ok(current.lock().unwrap().clone()));
+        let mut assets = AssetRegistry::with_reader(reader, 1);
+        let id = assets.load("triangle.obj").unwrap();
+        assets.wait(TIMEOUT);
+        let loaded = assets.get(id).cloned();
+
+        *contents.lock().unwrap() = b"not a mesh".to_vec();
+        assets.reload(id).unwrap();
+        let events = assets.wait(TIMEOUT);
+        assert!(matches!(events.as_slice(), [AssetEvent::Failed(failed, _)] if *failed == id));
+        assert_eq!(assets.get(id).cloned(), loaded);
+        assert_eq!(assets.progress().loaded, 1);
+        assert!(assets.reload(AssetId(99)).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_and_invalid_paths() {
+        let (mut assets, reads) = registry(&[]);
+        assert!(assets.load("readme.txt").is_err());
+        assert!(assets.load("no_extension").is_err());
+        assert!(assets.load("../outside.obj").is_err());
+        assert_eq!(assets.progress().total, 0);
+        assert!(assets.wait(TIMEOUT).is_empty());
+        assert_eq!(reads.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn progress_counts_finished_assets() {
+        let progress = LoadProgress { total: 4, loaded: 2, failed: 1 };
+        assert_eq!(progress.pending(), 1);
+        assert!(!progress.is_done());
+        assert_eq!(progress.to_string(), "2/4 assets loaded, 1 failed");
+        assert!(LoadProgress::default().is_done());
+    }
+
+    #[test]
+    fn parses_obj_meshes() {
+        let mesh = MeshData::from_obj("# quad\nv 0 0 0 1 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1/1/1 2 3 -1\n").unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[0].color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(mesh.vertices[1].color, [1.0; 4]);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+
+        assert!(MeshData::from_obj("v 0 0\nf 1 1 1\n").is_err());
+        assert!(MeshData::from_obj("v 0 0 0\nv 1 0 0\nf 1 2\n").is_err());
+        assert!(MeshData::from_obj("v 0 0 0\nf 1 1 4\n").is_err());
+        assert!(MeshData::from_obj("v 0 0 0\nf 0 1 1\n").is_err());
+        assert!(MeshData::from_obj("v 0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn parses_binary_ppm_textures() {
+        let mut ppm = b"P6\n# comment\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 0, 0, 128, 255]);
+        let texture = TextureData::from_ppm(&ppm).unwrap();
+        assert_eq!((texture.width, texture.height), (2, 1));
+        assert_eq!(texture.texels, [[255, 0, 0, 255], [0, 128, 255, 255]]);
+
+        assert!(TextureData::from_ppm(&ppm[..ppm.len() - 1]).is_err());
+        assert!(TextureData::from_ppm(b"P3\n1 1\n255\n0 0 0").is_err());
+        assert!(TextureData::from_ppm(b"P6\n1 1\n65535\n\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn shader_stage_comes_from_the_file_name() {
+        let vertex = ShaderData::from_file(Path::new("hlsl/Triangle_VS.hlsl"), b"float4 VS() : SV_Position { return 0; }".to_vec()).unwrap();
+        assert_eq!(vertex.stage, ShaderStage::Vertex);
+        assert!(matches!(vertex.code, ShaderCode::Hlsl(_)));
+
+        let pixel = ShaderData::from_file(Path::new("triangle_ps.cso"), vec![0x44, 0x58, 0x42, 0x43]).unwrap();
+        assert_eq!(pixel, ShaderData { stage: ShaderStage::Pixel, code: ShaderCode::Bytecode(vec![0x44, 0x58, 0x42, 0x43]) });
+
+        assert!(ShaderData::from_file(Path::new("triangle.hlsl"), vec![]).is_err());
+        assert!(ShaderData::from_file(Path::new("triangle_ps.hlsl"), vec![0xFF]).is_err());
+    }
+}
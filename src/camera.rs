@@ -0,0 +1,49 @@
+use directx_math::*;
+
+/// 透視投影攝影機，使用左手座標系（與 Direct3D 慣例相同）。
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Camera {
+    pub position: XMFLOAT3,
+    pub target: XMFLOAT3,
+    pub up: XMFLOAT3,
+    pub fov_y: f32,
+    pub aspect_ratio: f32,
+    pub near_z: f32,
+    pub far_z: f32,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Camera {
+    pub fn new(aspect_ratio: f32) -> Self {
+        Self {
+            position: XMFLOAT3 { x: 0.0, y: 8.0, z: -20.0 },
+            target: XMFLOAT3 { x: 0.0, y: 0.0, z: 0.0 },
+            up: XMFLOAT3 { x: 0.0, y: 1.0, z: 0.0 },
+            fov_y: XM_PIDIV4,
+            aspect_ratio,
+            near_z: 0.1,
+            far_z: 1000.0,
+        }
+    }
+
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        self.aspect_ratio = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    pub fn view(&self) -> XMMATRIX {
+        XMMatrixLookAtLH(
+            XMVectorSet(self.position.x, self.position.y, self.position.z, 1.0),
+            XMVectorSet(self.target.x, self.target.y, self.target.z, 1.0),
+            XMVectorSet(self.up.x, self.up.y, self.up.z, 0.0),
+        )
+    }
+
+    pub fn projection(&self) -> XMMATRIX {
+        XMMatrixPerspectiveFovLH(self.fov_y, self.aspect_ratio, self.near_z, self.far_z)
+    }
+
+    pub fn view_projection(&self) -> XMMATRIX {
+        XMMatrixMultiply(self.view(), &self.projection())
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Direct3D 的 feature level，數值與 `D3D_FEATURE_LEVEL` 相同。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum FeatureLevel {
+    Level10_0 = 0xa000,
+    Level10_1 = 0xa100,
+    Level11_0 = 0xb000,
+    Level11_1 = 0xb100,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl FeatureLevel {
+    /// 建立裝置時依序嘗試的 feature level，由高到低。
+    pub const FALLBACK_CHAIN: [FeatureLevel; 4] = [
+        FeatureLevel::Level11_1,
+        FeatureLevel::Level11_0,
+        FeatureLevel::Level10_1,
+        FeatureLevel::Level10_0,
+    ];
+
+    pub fn raw(&self) -> i32 {
+        *self as i32
+    }
+
+    pub fn from_raw(raw: i32) -> Option<FeatureLevel> {
+        FeatureLevel::FALLBACK_CHAIN.into_iter().find(|level| level.raw() == raw)
+    }
+
+    /// 這個 feature level 能執行的最高 shader model。
+    pub fn shader_model(&self) -> ShaderModel {
+        match self {
+            FeatureLevel::Level10_0 => ShaderModel::Sm4_0,
+            FeatureLevel::Level10_1 => ShaderModel::Sm4_1,
+            FeatureLevel::Level11_0 | FeatureLevel::Level11_1 => ShaderModel::Sm5_0,
+        }
+    }
+
+    /// 2D 紋理的最大邊長。
+    pub fn max_texture_dimension(&self) -> u32 {
+        match self {
+            FeatureLevel::Level10_0 | FeatureLevel::Level10_1 => 8192,
+            FeatureLevel::Level11_0 | FeatureLevel::Level11_1 => 16384,
+        }
+    }
+}
+
+impl fmt::Display for FeatureLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = self.raw();
+        write!(f, "{}_{}", raw >> 12, (raw >> 8) & 0xF)
+    }
+}
+
+/// 編譯 shader 時使用的 profile 版本。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShaderModel {
+    Sm4_0,
+    Sm4_1,
+    Sm5_0,
+}
+
+impl ShaderModel {
+    /// 接在 `vs_`、`ps_` 之後的版本字串。
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ShaderModel::Sm4_0 => "4_0",
+            ShaderModel::Sm4_1 => "4_1",
+            ShaderModel::Sm5_0 => "5_0",
+        }
+    }
+}
+
+/// renderer 會用到的 DXGI 格式。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum RenderFormat {
+    Rgba8Unorm,
+    Rgba8UnormSrgb,
+    Rgb10a2Unorm,
+    Rgba16Float,
+    D24UnormS8Uint,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl RenderFormat {
+    pub const ALL: [RenderFormat; 5] = [
+        RenderFormat::Rgba8Unorm,
+        RenderFormat::Rgba8UnormSrgb,
+        RenderFormat::Rgb10a2Unorm,
+        RenderFormat::Rgba16Float,
+        RenderFormat::D24UnormS8Uint,
+    ];
+}
+
+/// `CheckFormatSupport` 結果中 renderer 關心的部分。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FormatSupport {
+    pub render_target: bool,
+    /// 可以當作 swap chain 的 back buffer 格式。
+    pub display: bool,
+    pub multisample_render_target: bool,
+    pub shader_sample: bool,
+}
+
+/// 裝置建立後查詢到的能力，renderer 依此停用或降級不支援的功能。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Capabilities {
+    pub feature_level: Option<FeatureLevel>,
+    pub formats: BTreeMap<RenderFormat, FormatSupport>,
+    /// 場景目標與 depth buffer 都支援的取樣數，由小到大，永遠包含 1。
+    pub msaa_sample_counts: Vec<u32>,
+    /// 驅動程式可以在多個執行緒同時建立資源。
+    pub concurrent_creates: bool,
+    /// 驅動程式原生支援 deferred context 的 command list，否則由 runtime 模擬。
+    pub driver_command_lists: bool,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Capabilities {
+    pub fn feature_level(&self) -> FeatureLevel {
+        self.feature_level.unwrap_or(FeatureLevel::Level10_0)
+    }
+
+    pub fn shader_model(&self) -> ShaderModel {
+        self.feature_level().shader_model()
+    }
+
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.feature_level().max_texture_dimension()
+    }
+
+    pub fn format(&self, format: RenderFormat) -> FormatSupport {
+        self.formats.get(&format).copied().unwrap_or_default()
+    }
+
+    /// 不超過 `requested` 的最大可用取樣數，`requested` 為 0 視為 1。
+    pub fn choose_msaa_samples(&self, requested: u32) -> u32 {
+        self.msaa_sample_counts.iter()
+            .copied()
+            .filter(|count| *count <= requested.max(1))
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// 依序找出第一個可以當 back buffer 的格式，都不支援時回傳 `None`。
+    pub fn choose_display_format(&self, preferred: &[RenderFormat]) -> Option<RenderFormat> {
+        preferred.iter().copied().find(|format| {
+            let support = self.format(*format);
+            support.display && support.render_target
+        })
+    }
+
+    /// 後處理效果需要能取樣的 HDR 中間目標。
+    // 支援的最低 feature level 都能取樣 RGBA16F，目前不需要檢查
+    #[allow(dead_code)]
+    pub fn supports_hdr_scene(&self) -> bool {
+        let support = self.format(RenderFormat::Rgba16Float);
+        support.render_target && support.shader_sample
+    }
+
+    /// 超過最大紋理邊長的視窗大小會被縮小到上限。
+    #[allow(dead_code)]
+    pub fn clamp_texture_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let max = self.max_texture_dimension();
+        (width.min(max), height.min(max))
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "feature level {}, shader model {}", self.feature_level(), self.shader_model().suffix())?;
+        writeln!(f, "max texture size {}", self.max_texture_dimension())?;
+        writeln!(f, "MSAA sample counts {:?}", self.msaa_sample_counts)?;
+        writeln!(f, "concurrent creates {}, driver command lists {}", self.concurrent_creates, self.driver_command_lists)?;
+        for (format, support) in &self.formats {
+            write!(f, "{:?}:", format)?;
+            for (name, supported) in [
+                ("render-target", support.render_target),
+                ("display", support.display),
+                ("msaa", support.multisample_render_target),
+                ("sample", support.shader_sample),
+            ] {
+                if supported {
+                    write!(f, " {}", name)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENDER_TARGET: FormatSupport = FormatSupport { render_target: true, display: false, multisample_render_target: false, shader_sample: false };
+
+    fn capabilities(formats: &[(RenderFormat, FormatSupport)], msaa_sample_counts: &[u32]) -> Capabilities {
+        Capabilities {
+            feature_level: Some(FeatureLevel::Level11_0),
+            formats: formats.iter().copied().collect(),
+            msaa_sample_counts: msaa_sample_counts.to_vec(),
+            ..Capabilities::default()
+        }
+    }
+
+    #[test]
+    fn feature_level_round_trips_through_raw() {
+        for level in FeatureLevel::FALLBACK_CHAIN {
+            assert_eq!(FeatureLevel::from_raw(level.raw()), Some(level));
+        }
+        assert_eq!(FeatureLevel::from_raw(0x9300), None);
+        assert_eq!(FeatureLevel::Level10_1.to_string(), "10_1");
+        assert_eq!(FeatureLevel::Level11_0.shader_model(), ShaderModel::Sm5_0);
+    }
+
+    #[test]
+    fn missing_feature_level_uses_the_lowest_limits() {
+        let capabilities = Capabilities::default();
+        assert_eq!(capabilities.feature_level(), FeatureLevel::Level10_0);
+        assert_eq!(capabilities.shader_model(), ShaderModel::Sm4_0);
+        assert_eq!(capabilities.clamp_texture_size(10000, 100), (8192, 100));
+    }
+
+    #[test]
+    fn msaa_falls_back_to_the_largest_supported_count() {
+        let capabilities = capabilities(&[], &[1, 2, 4]);
+        assert_eq!(capabilities.choose_msaa_samples(4), 4);
+        assert_eq!(capabilities.choose_msaa_samples(8), 4);
+        assert_eq!(capabilities.choose_msaa_samples(3), 2);
+        assert_eq!(capabilities.choose_msaa_samples(1), 1);
+        assert_eq!(capabilities.choose_msaa_samples(0), 1);
+    }
+
+    #[test]
+    fn msaa_without_any_reported_counts_is_off() {
+        assert_eq!(capabilities(&[], &[]).choose_msaa_samples(8), 1);
+    }
+
+    #[test]
+    fn display_format_falls_back_in_preference_order() {
+        let display = FormatSupport { display: true, ..RENDER_TARGET };
+        let capabilities = capabilities(&[
+            (RenderFormat::Rgb10a2Unorm, RENDER_TARGET),
+            (RenderFormat::Rgba8UnormSrgb, display),
+            (RenderFormat::Rgba8Unorm, display),
+        ], &[1]);
+        let preferred = [RenderFormat::Rgba16Float, RenderFormat::Rgb10a2Unorm, RenderFormat::Rgba8UnormSrgb, RenderFormat::Rgba8Unorm];
+        // Rgba16Float 沒有查詢結果，Rgb10a2Unorm 不能當 back buffer
+        assert_eq!(capabilities.choose_display_format(&preferred), Some(RenderFormat::Rgba8UnormSrgb));
+        assert_eq!(capabilities.choose_display_format(&[RenderFormat::Rgba8Unorm]), Some(RenderFormat::Rgba8Unorm));
+        assert_eq!(capabilities.choose_display_format(&preferred[..2]), None);
+        assert_eq!(capabilities.choose_display_format(&[]), None);
+    }
+
+    #[test]
+    fn display_format_must_also_be_a_render_target() {
+        let display_only = FormatSupport { display: true, ..FormatSupport::default() };
+        let capabilities = capabilities(&[(RenderFormat::Rgba8Unorm, display_only)], &[1]);
+        assert_eq!(capabilities.choose_display_format(&[RenderFormat::Rgba8Unorm]), None);
+    }
+
+    #[test]
+    fn hdr_scene_needs_a_sampleable_float_target() {
+        let sampleable = FormatSupport { shader_sample: true, ..RENDER_TARGET };
+        assert!(capabilities(&[(RenderFormat::Rgba16Float, sampleable)], &[1]).supports_hdr_scene());
+        assert!(!capabilities(&[(RenderFormat::Rgba16Float, RENDER_TARGET)], &[1]).supports_hdr_scene());
+        assert!(!capabilities(&[(RenderFormat::Rgba8Unorm, sampleable)], &[1]).supports_hdr_scene());
+    }
+}
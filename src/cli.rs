@@ -0,0 +1,254 @@
+use std::path::PathBuf;
+use crate::adapter::AdapterSelection;
+use crate::config::{ConfigOverride, DriverType};
+use crate::display::WindowMode;
+
+/// 命令列參數。與設定檔重疊的選項轉成 `ConfigOverride`，由 `Config::load` 合併。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    /// `--config`，未指定時讀取 `config::DEFAULT_CONFIG_PATH`。
+    pub config_path: Option<PathBuf>,
+    /// 依出現順序排列，後面的覆寫前面的。
+    pub overrides: Vec<ConfigOverride>,
+    /// 取代內建示範場景的場景檔。
+    pub scene: Option<PathBuf>,
+    /// 畫完這麼多幀後結束，用於自動化的冒煙測試。
+    pub frames: Option<u64>,
+    /// 列出顯示卡後結束。
+    pub list_adapters: bool,
+    /// 啟用 profiler，結束時把最近幾幀寫成 Chrome trace 檔案。
+    pub trace: Option<PathBuf>,
+}
+
+/// `--pack` 的參數。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackArgs {
+    /// 要封裝的目錄，封裝檔中的路徑相對於這個目錄。
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub compress: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    Run(CliArgs),
+    /// 建立封裝檔後結束。
+    Pack(PackArgs),
+    Help,
+}
+
+/// `--help` 的說明文字。
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [OPTIONS]
+
+Options:
+  -c, --config <PATH>      Read settings from PATH instead of app.toml
+      --driver <TYPE>      Direct3D driver: hardware, warp or reference
+      --adapter <ADAPTER>  GPU to use: default, high-performance, minimum-power,
+                           an index from --list-adapters or part of its name
+      --list-adapters      Print the available GPUs and exit
+      --debug-layer        Enable the D3D11 debug layer
+      --no-debug-layer     Disable the D3D11 debug layer
+      --vsync              Wait for vertical sync when presenting
+      --no-vsync           Present immediately, tearing on variable refresh displays
+      --size <WxH>         Client area size in logical pixels, e.g. 1280x720
+      --fullscreen         Start in exclusive fullscreen
+      --borderless         Start in borderless fullscreen
+      --scene <PATH>       Load a scene file instead of the built-in demo scene
+      --frames <N>         Exit after rendering N frames
+      --trace <PATH>       Profile the run and write the last frames as a Chrome trace
+      --set <KEY=VALUE>    Override any config value, e.g. renderer.msaa_samples=8
+  -h, --help               Print this help and exit
+
+Archives:
+      --pack <DIR>         Pack every file under DIR into an archive and exit
+  -o, --output <PATH>      Archive to write, required with --pack
+      --no-compress        Store files in the archive without compression
+
+Command-line options take precedence over the config file."
+    )
+}
+
+/// 解析不含程式名稱的參數。支援 `--name value` 與 `--name=value` 兩種寫法。
+pub fn parse_args<I>(args: I) -> Result<CliCommand, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = CliArgs::default();
+    let (mut pack_source, mut pack_output, mut compress) = (None, None, true);
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        if inline_value.is_some() && matches!(name.as_str(), "--list-adapters" | "--debug-layer" | "--no-debug-layer" | "--vsync" | "--no-vsync" | "--fullscreen" | "--borderless" | "--no-compress") {
+            return Err(format!("{} does not take a value", name));
+        }
+        let mut value = || -> Result<String, String> {
+            inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "-c" | "--config" => parsed.config_path = Some(PathBuf::from(value()?)),
+            "--driver" => {
+                let driver: DriverType = value()?.parse()?;
+                parsed.overrides.push(ConfigOverride::new("renderer.driver", driver.name()));
+            }
+            "--adapter" => {
+                // 先解析一次以便提早回報錯誤，設定檔的反序列化會接受相同的寫法
+                let adapter = value()?;
+                adapter.parse::<AdapterSelection>()?;
+                parsed.overrides.push(ConfigOverride::new("renderer.adapter", adapter));
+            }
+            "--list-adapters" => parsed.list_adapters = true,
+            "--debug-layer" => parsed.overrides.push(ConfigOverride::new("renderer.debug_layer", true)),
+            "--no-debug-layer" => parsed.overrides.push(ConfigOverride::new("renderer.debug_layer", false)),
+            "--vsync" => parsed.overrides.push(ConfigOverride::new("renderer.vsync", true)),
+            "--no-vsync" => parsed.overrides.push(ConfigOverride::new("renderer.vsync", false)),
+            "--size" => {
+                let (width, height) = parse_size(&value()?)?;
+                parsed.overrides.push(ConfigOverride::new("window.width", width));
+                parsed.overrides.push(ConfigOverride::new("window.height", height));
+            }
+            "--fullscreen" => parsed.overrides.push(ConfigOverride::new("window.mode", WindowMode::Fullscreen.name())),
+            "--borderless" => parsed.overrides.push(ConfigOverride::new("window.mode", WindowMode::Borderless.name())),
+            "--scene" => parsed.scene = Some(PathBuf::from(value()?)),
+            "--frames" => {
+                let frames = value()?;
+                let frames = frames.parse::<u64>().ok()
+                    .filter(|frames| *frames > 0)
+                    .ok_or_else(|| format!("--frames expects a positive integer, got \"{}\"", frames))?;
+                parsed.frames = Some(frames);
+            }
+            "--set" => parsed.overrides.push(value()?.parse()?),
+            "--trace" => parsed.trace = Some(PathBuf::from(value()?)),
+            "--pack" => pack_source = Some(PathBuf::from(value()?)),
+            "-o" | "--output" => pack_output = Some(PathBuf::from(value()?)),
+            "--no-compress" => compress = false,
+            _ => return Err(format!("unknown argument \"{}\"", arg)),
+        }
+    }
+    match (pack_source, pack_output) {
+        (Some(source), Some(output)) => Ok(CliCommand::Pack(PackArgs { source, output, compress })),
+        (Some(_), None) => Err("--pack requires --output".to_string()),
+        (None, Some(_)) => Err("--output is only used with --pack".to_string()),
+        (None, None) if !compress => Err("--no-compress is only used with --pack".to_string()),
+        (None, None) => Ok(CliCommand::Run(parsed)),
+    }
+}
+
+fn parse_size(text: &str) -> Result<(f64, f64), String> {
+    let error = || format!("--size expects WIDTHxHEIGHT, e.g. 1280x720, got \"{}\"", text);
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(error)?;
+    let width = width.trim().parse::<f64>().map_err(|_| error())?;
+    let height = height.trim().parse::<f64>().map_err(|_| error())?;
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn run(args: &[&str]) -> CliArgs {
+        match parse(args) {
+            Ok(CliCommand::Run(args)) => args,
+            other => panic!("expected a run command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_arguments_runs_with_defaults() {
+        assert_eq!(run(&[]), CliArgs::default());
+    }
+
+    #[test]
+    fn accepts_inline_and_separate_values() {
+        assert_eq!(run(&["--scene=a.toml"]), run(&["--scene", "a.toml"]));
+        assert_eq!(run(&["--config", "x.toml"]).config_path, Some(PathBuf::from("x.toml")));
+        assert_eq!(run(&["-c", "x.toml"]).config_path, Some(PathBuf::from("x.toml")));
+        // 值中的 = 保留給 --set
+        assert_eq!(run(&["--set=window.title=demo"]).overrides, [ConfigOverride::new("window.title", "demo")]);
+    }
+
+    #[test]
+    fn flags_do_not_take_values() {
+        assert_eq!(parse(&["--vsync=false"]), Err("--vsync does not take a value".to_string()));
+        assert_eq!(parse(&["--no-compress=1"]), Err("--no-compress does not take a value".to_string()));
+        let args = run(&["--vsync", "--no-debug-layer", "--list-adapters"]);
+        assert!(args.list_adapters);
+        assert_eq!(args.overrides, [ConfigOverride::new("renderer.vsync", true), ConfigOverride::new("renderer.debug_layer", false)]);
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        assert_eq!(parse(&["--scene"]), Err("--scene requires a value".to_string()));
+        assert_eq!(parse(&["--vsync", "--frames"]), Err("--frames requires a value".to_string()));
+    }
+
+    #[test]
+    fn frames_must_be_positive() {
+        assert_eq!(run(&["--frames", "120"]).frames, Some(120));
+        for frames in ["0", "-1", "abc", ""] {
+            let error = parse(&["--frames", frames]).unwrap_err();
+            assert_eq!(error, format!("--frames expects a positive integer, got \"{}\"", frames));
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let expected = [ConfigOverride::new("window.width", 1280.0), ConfigOverride::new("window.height", 720.0)];
+        assert_eq!(run(&["--size", "1280x720"]).overrides, expected);
+        assert_eq!(run(&["--size=1280X720"]).overrides, expected);
+        for size in ["1280", "1280x", "x720", "1280*720", "widexhigh"] {
+            assert!(parse(&["--size", size]).unwrap_err().starts_with("--size expects WIDTHxHEIGHT"), "accepted {}", size);
+        }
+    }
+
+    #[test]
+    fn later_options_override_earlier_ones() {
+        let args = run(&["--borderless", "--driver", "WARP", "--fullscreen", "--adapter", "radeon"]);
+        assert_eq!(args.overrides, [
+            ConfigOverride::new("window.mode", "borderless"),
+            ConfigOverride::new("renderer.driver", "warp"),
+            ConfigOverride::new("window.mode", "fullscreen"),
+            ConfigOverride::new("renderer.adapter", "radeon"),
+        ]);
+        assert!(parse(&["--driver", "software"]).is_err());
+        assert!(parse(&["--adapter", ""]).is_err());
+    }
+
+    #[test]
+    fn pack_needs_an_output() {
+        assert_eq!(parse(&["--pack", "assets", "-o", "assets.rpak"]), Ok(CliCommand::Pack(PackArgs {
+            source: PathBuf::from("assets"),
+            output: PathBuf::from("assets.rpak"),
+            compress: true,
+        })));
+        assert!(matches!(parse(&["--no-compress", "--output=a.rpak", "--pack=assets"]), Ok(CliCommand::Pack(PackArgs { compress: false, .. }))));
+        assert_eq!(parse(&["--pack", "assets"]), Err("--pack requires --output".to_string()));
+        assert_eq!(parse(&["--output", "a.rpak"]), Err("--output is only used with --pack".to_string()));
+        assert_eq!(parse(&["--no-compress"]), Err("--no-compress is only used with --pack".to_string()));
+    }
+
+    #[test]
+    fn help_stops_parsing() {
+        assert_eq!(parse(&["-h"]), Ok(CliCommand::Help));
+        assert_eq!(parse(&["--help", "--unknown"]), Ok(CliCommand::Help));
+        assert!(usage("demo").starts_with("Usage: demo [OPTIONS]"));
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert_eq!(parse(&["--verbose"]), Err("unknown argument \"--verbose\"".to_string()));
+        assert_eq!(parse(&["scene.toml"]), Err("unknown argument \"scene.toml\"".to_string()));
+        assert_eq!(parse(&["--unknown=1"]), Err("unknown argument \"--unknown=1\"".to_string()));
+    }
+}
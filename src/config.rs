@@ -0,0 +1,458 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::Deserialize;
+use crate::adapter::AdapterSelection;
+use crate::display::WindowMode;
+use crate::platform::{LogicalSize, Position};
+use crate::present::PresentSettings;
+
+/// 沒有指定設定檔時讀取的檔案，不存在時使用預設值。
+pub const DEFAULT_CONFIG_PATH: &str = "app.toml";
+
+/// back buffer 格式的名稱，與 `OutputFormat::from_name` 相同。
+pub const OUTPUT_FORMAT_NAMES: [&str; 5] = ["sdr8", "sdr8-srgb", "sdr10", "hdr10", "scrgb"];
+
+/// 建立 D3D11 裝置時使用的驅動程式。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriverType {
+    #[default]
+    Hardware,
+    /// 軟體光柵化，沒有顯示卡或在 CI 上執行時使用。
+    Warp,
+    /// 參考實作，非常慢，只用來比對驅動程式的行為。
+    Reference,
+}
+
+impl DriverType {
+    pub const ALL: [DriverType; 3] = [DriverType::Hardware, DriverType::Warp, DriverType::Reference];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DriverType::Hardware => "hardware",
+            DriverType::Warp => "warp",
+            DriverType::Reference => "reference",
+        }
+    }
+}
+
+impl fmt::Display for DriverType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DriverType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DriverType::ALL.into_iter()
+            .find(|driver| driver.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown driver type \"{}\", expected hardware, warp or reference", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub title: String,
+    pub class_name: String,
+    /// 視窗左上角的螢幕座標，未指定時由系統決定。
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// client 區域的邏輯尺寸，未指定時由系統決定。
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub mode: WindowMode,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "test window".to_string(),
+            class_name: "test string".to_string(),
+            x: None,
+            y: None,
+            width: None,
+            height: None,
+            mode: WindowMode::Windowed,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn position(&self) -> Option<Position> {
+        Some(Position { x: self.x?, y: self.y? })
+    }
+
+    pub fn size(&self) -> Option<LogicalSize> {
+        Some(LogicalSize::new(self.width?, self.height?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererConfig {
+    pub driver: DriverType,
+    /// 啟用 D3D11 debug layer，需要安裝 Graphics Tools。
+    pub debug_layer: bool,
+    /// 只在 `driver = "hardware"` 時使用。
+    pub adapter: AdapterSelection,
+    pub vsync: bool,
+    /// swap chain 的 buffer 數量，2 到 16。
+    pub buffer_count: u32,
+    /// 關閉 vsync 時允許畫面撕裂，讓可變更新率顯示器不受更新率限制。
+    pub allow_tearing: bool,
+    /// CPU 最多領先 GPU 幾幀，1 的輸入延遲最低。
+    pub max_frame_latency: u32,
+    pub msaa_samples: u32,
+    /// `OUTPUT_FORMAT_NAMES` 其中之一。
+    pub output_format: String,
+    pub paper_white_nits: f32,
+    /// 放 `.hlsl` 與編譯好的 `.cso` 的目錄，掛載為 `hlsl/`，其中的檔案優先於內建的 shader。
+    /// 目錄不存在時只使用內建的 shader。
+    pub shader_dir: PathBuf,
+}
+
+impl RendererConfig {
+    pub fn present(&self) -> PresentSettings {
+        PresentSettings {
+            buffer_count: self.buffer_count,
+            vsync: self.vsync,
+            allow_tearing: self.allow_tearing,
+            max_frame_latency: self.max_frame_latency,
+        }
+    }
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            driver: DriverType::Hardware,
+            debug_layer: cfg!(debug_assertions),
+            adapter: AdapterSelection::default(),
+            vsync: false,
+            buffer_count: 2,
+            allow_tearing: true,
+            max_frame_latency: 1,
+            msaa_samples: 4,
+            output_format: "sdr8-srgb".to_string(),
+            paper_white_nits: 200.0,
+            shader_dir: PathBuf::from("hlsl"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// 動作對應檔，格式見 `ActionMap::from_toml_str`。
+    pub bindings: PathBuf,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { bindings: PathBuf::from("input.toml") }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    /// 以 `--pack` 建立的封裝檔，掛載在根目錄。後列的封裝檔優先，工作目錄中的同名檔案又優先於封裝檔。
+    pub archives: Vec<PathBuf>,
+}
+
+/// 應用程式設定。依序套用預設值、設定檔與命令列覆寫，最後檢查數值範圍：
+///
+/// ```toml
+/// [window]
+/// title = "demo"
+/// width = 1280
+/// height = 720
+/// mode = "borderless"
+///
+/// [renderer]
+/// driver = "warp"
+/// msaa_samples = 8
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub renderer: RendererConfig,
+    pub input: InputConfig,
+    pub files: FilesConfig,
+}
+
+/// 命令列上的 `key=value` 覆寫，`key` 以點分隔，例如 `renderer.msaa_samples=8`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl ConfigOverride {
+    pub fn new(key: &str, value: impl Into<toml::Value>) -> Self {
+        Self { key: key.to_string(), value: value.into() }
+    }
+}
+
+impl FromStr for ConfigOverride {
+    type Err = String;
+
+    /// 值以 TOML 解析，無法解析時當作字串，所以 `title=demo` 與 `title="demo"` 相同。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s.split_once('=')
+            .ok_or_else(|| format!("invalid override \"{}\", expected key=value", s))?;
+        let key = key.trim();
+        if key.is_empty() || key.split('.').any(str::is_empty) {
+            return Err(format!("invalid override key \"{}\"", key));
+        }
+        let value = value.trim();
+        let value = toml::from_str::<toml::Table>(&format!("value = {}", value))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+        Ok(Self { key: key.to_string(), value })
+    }
+}
+
+impl Config {
+    /// 讀取 `path` 並套用覆寫。`path` 為 `None` 時讀取 `DEFAULT_CONFIG_PATH`，檔案不存在則只用預設值。
+    pub fn load(path: Option<&Path>, overrides: &[ConfigOverride]) -> Result<Self, String> {
+        let text = match path {
+            Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+            None => std::fs::read_to_string(DEFAULT_CONFIG_PATH).unwrap_or_default(),
+        };
+        let name = path.unwrap_or(Path::new(DEFAULT_CONFIG_PATH)).display().to_string();
+        let table = Self::parse(&text).map_err(|e| format!("{}: {}", name, e))?;
+        Self::merge(table, overrides)
+    }
+
+    #[cfg(test)]
+    pub fn from_toml_str(text: &str, overrides: &[ConfigOverride]) -> Result<Self, String> {
+        Self::merge(Self::parse(text)?, overrides)
+    }
+
+    fn parse(text: &str) -> Result<toml::Table, String> {
+        // 先直接解析成 Config，錯誤訊息才會指出設定檔中的行號與欄位
+        toml::from_str::<Config>(text).map_err(|e| e.to_string())?;
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    // 每套用一個覆寫就檢查一次，錯誤才能指出是哪一個參數
+    fn merge(mut table: toml::Table, overrides: &[ConfigOverride]) -> Result<Self, String> {
+        for item in overrides {
+            apply_override(&mut table, item)?;
+            Self::from_table(&table).map_err(|e| format!("invalid override {}={}: {}", item.key, item.value, e))?;
+        }
+        let config = Self::from_table(&table)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_table(table: &toml::Table) -> Result<Self, String> {
+        toml::Value::Table(table.clone()).try_into().map_err(|e: toml::de::Error| e.message().to_string())
+    }
+
+    /// 檢查所有數值範圍，回傳的錯誤一次列出所有問題。
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = vec![];
+        let window = &self.window;
+        for (key, value) in [("window.width", window.width), ("window.height", window.height)] {
+            if let Some(value) = value && !(1.0..=16384.0).contains(&value) {
+                errors.push(format!("{} must be between 1 and 16384, got {}", key, value));
+            }
+        }
+        if window.width.is_some() != window.height.is_some() {
+            errors.push("window.width and window.height must be set together".to_string());
+        }
+        if window.x.is_some() != window.y.is_some() {
+            errors.push("window.x and window.y must be set together".to_string());
+        }
+        if window.title.is_empty() {
+            errors.push("window.title must not be empty".to_string());
+        }
+        if window.class_name.is_empty() {
+            errors.push("window.class_name must not be empty".to_string());
+        }
+
+        let renderer = &self.renderer;
+        let samples = renderer.msaa_samples;
+        if !samples.is_power_of_two() || samples > 32 {
+            errors.push(format!("renderer.msaa_samples must be 1, 2, 4, 8, 16 or 32, got {}", samples));
+        }
+        if !(PresentSettings::MIN_BUFFER_COUNT..=PresentSettings::MAX_BUFFER_COUNT).contains(&renderer.buffer_count) {
+            errors.push(format!(
+                "renderer.buffer_count must be between {} and {}, got {}",
+                PresentSettings::MIN_BUFFER_COUNT,
+                PresentSettings::MAX_BUFFER_COUNT,
+                renderer.buffer_count,
+            ));
+        }
+        if !(1..=PresentSettings::MAX_FRAME_LATENCY).contains(&renderer.max_frame_latency) {
+            errors.push(format!(
+                "renderer.max_frame_latency must be between 1 and {}, got {}",
+                PresentSettings::MAX_FRAME_LATENCY,
+                renderer.max_frame_latency,
+            ));
+        }
+        if !OUTPUT_FORMAT_NAMES.contains(&renderer.output_format.as_str()) {
+            errors.push(format!(
+                "renderer.output_format \"{}\" is not one of {}",
+                renderer.output_format,
+                OUTPUT_FORMAT_NAMES.join(", "),
+            ));
+        }
+        if !(80.0..=10000.0).contains(&renderer.paper_white_nits) {
+            errors.push(format!("renderer.paper_white_nits must be between 80 and 10000, got {}", renderer.paper_white_nits));
+        }
+        for archive in &self.files.archives {
+            if !archive.is_file() {
+                errors.push(format!("files.archives: \"{}\" is not a file", archive.display()));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+// 依點分隔的路徑建立缺少的 table，再寫入值
+fn apply_override(table: &mut toml::Table, item: &ConfigOverride) -> Result<(), String> {
+    let mut parts: Vec<&str> = item.key.split('.').collect();
+    let last = parts.pop().unwrap();
+    let mut current = table;
+    for (depth, part) in parts.iter().enumerate() {
+        let entry = current.entry(part.to_string()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        current = entry.as_table_mut()
+            .ok_or_else(|| format!("cannot override \"{}\": \"{}\" is not a table", item.key, parts[..=depth].join(".")))?;
+    }
+    current.insert(last.to_string(), item.value.clone());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(items: &[&str]) -> Vec<ConfigOverride> {
+        items.iter().map(|item| item.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_override_values_as_toml() {
+        assert_eq!("window.title=demo".parse::<ConfigOverride>(), "window.title=\"demo\"".parse());
+        assert_eq!("window.title = demo".parse(), Ok(ConfigOverride::new("window.title", "demo")));
+        assert_eq!("renderer.msaa_samples=8".parse(), Ok(ConfigOverride::new("renderer.msaa_samples", 8)));
+        assert_eq!("renderer.vsync=true".parse(), Ok(ConfigOverride::new("renderer.vsync", true)));
+        // 只有第一個 = 分隔鍵與值
+        assert_eq!("window.title=a=b".parse(), Ok(ConfigOverride::new("window.title", "a=b")));
+    }
+
+    #[test]
+    fn rejects_malformed_overrides() {
+        for item in ["window.title", "=x", " =x", "window..title=x", ".title=x", "window.=x"] {
+            assert!(item.parse::<ConfigOverride>().is_err(), "accepted {:?}", item);
+        }
+    }
+
+    #[test]
+    fn overrides_replace_file_values() {
+        let config = Config::from_toml_str(
+            "[window]\ntitle = \"file\"\n[renderer]\nmsaa_samples = 2\n",
+            &overrides(&["window.title=demo", "renderer.msaa_samples=8", "renderer.driver=warp"]),
+        ).unwrap();
+        assert_eq!(config.window.title, "demo");
+        assert_eq!(config.renderer.msaa_samples, 8);
+        assert_eq!(config.renderer.driver, DriverType::Warp);
+        assert_eq!(config.renderer.buffer_count, RendererConfig::default().buffer_count);
+    }
+
+    #[test]
+    fn cannot_override_through_a_value() {
+        let error = Config::from_toml_str("[window]\ntitle = \"file\"\n", &overrides(&["window.title.x=1"])).unwrap_err();
+        assert_eq!(error, "cannot override \"window.title.x\": \"window.title\" is not a table");
+        // 設定檔沒有寫的值會建立成 table，型別錯誤在轉換時才發現
+        let error = Config::from_toml_str("", &overrides(&["window.title.x=1"])).unwrap_err();
+        assert!(error.starts_with("invalid override window.title.x=1: "), "{}", error);
+    }
+
+    #[test]
+    fn reports_the_override_with_the_wrong_type() {
+        let error = Config::from_toml_str("", &overrides(&["window.title=demo", "renderer.msaa_samples=many"])).unwrap_err();
+        assert!(error.starts_with("invalid override renderer.msaa_samples=\"many\": "), "{}", error);
+        let error = Config::from_toml_str("", &overrides(&["renderer.unknown=1"])).unwrap_err();
+        assert!(error.starts_with("invalid override renderer.unknown=1: "), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_in_the_file() {
+        assert!(Config::from_toml_str("[window]\ntitel = \"demo\"\n", &[]).is_err());
+        assert!(Config::from_toml_str("[renderers]\n", &[]).is_err());
+    }
+
+    #[test]
+    fn validate_lists_every_error() {
+        let config = Config::from_toml_str(
+            "[window]\ntitle = \"\"\n[renderer]\nmsaa_samples = 3\nbuffer_count = 1\noutput_format = \"hdr12\"\n",
+            &[],
+        ).unwrap_err();
+        let errors: Vec<&str> = config.lines().collect();
+        assert_eq!(errors, [
+            "window.title must not be empty",
+            "renderer.msaa_samples must be 1, 2, 4, 8, 16 or 32, got 3",
+            "renderer.buffer_count must be between 2 and 16, got 1",
+            "renderer.output_format \"hdr12\" is not one of sdr8, sdr8-srgb, sdr10, hdr10, scrgb",
+        ]);
+    }
+
+    #[test]
+    fn size_and_position_must_be_set_together() {
+        let error = Config::from_toml_str("[window]\nwidth = 1280\nx = 10\n", &[]).unwrap_err();
+        assert_eq!(error, "window.width and window.height must be set together\nwindow.x and window.y must be set together");
+
+        let config = Config::from_toml_str("[window]\nwidth = 1280\nheight = 720\nx = 10\ny = -20\n", &[]).unwrap();
+        assert_eq!(config.window.size(), Some(LogicalSize::new(1280.0, 720.0)));
+        assert_eq!(config.window.position(), Some(Position { x: 10, y: -20 }));
+        assert_eq!(Config::default().window.size(), None);
+    }
+
+    #[test]
+    fn checks_value_ranges() {
+        let invalid = [
+            "renderer.msaa_samples=0",
+            "renderer.msaa_samples=64",
+            "renderer.buffer_count=17",
+            "renderer.max_frame_latency=0",
+            "renderer.output_format=sdr16",
+            "renderer.paper_white_nits=10",
+            "window.width=0",
+            "window.height=20000",
+        ];
+        for item in invalid {
+            let key = item.split_once('=').unwrap().0;
+            let error = Config::from_toml_str("[window]\nwidth = 1280\nheight = 720\n", &overrides(&[item]))
+                .expect_err(item);
+            assert!(error.starts_with(key) && !error.contains('\n'), "{}: {}", item, error);
+        }
+        for item in ["renderer.msaa_samples=1", "renderer.msaa_samples=32", "renderer.buffer_count=16", "renderer.output_format=scrgb"] {
+            assert!(Config::from_toml_str("", &overrides(&[item])).is_ok(), "rejected {}", item);
+        }
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn parses_driver_names() {
+        assert_eq!("WARP".parse(), Ok(DriverType::Warp));
+        assert!("software".parse::<DriverType>().is_err());
+        for driver in DriverType::ALL {
+            assert_eq!(driver.to_string().parse(), Ok(driver));
+        }
+    }
+}
@@ -1,163 +1,549 @@
-use std::{mem, slice};
+use std::slice;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
 use directx_math::{XMFLOAT3, XMFLOAT4};
-use windows::core::{s, w, Interface, BOOL};
-use windows::Win32::Foundation::{HMODULE, HWND, SIZE};
+use windows::core::{s, Interface, HRESULT};
+use windows::Win32::Foundation::{E_INVALIDARG, HMODULE, HWND};
 use windows::Win32::Graphics::Direct3D11::*;
-use windows::Win32::Graphics::Direct3D::{ID3DBlob, ID3DInclude, D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST, D3D_DRIVER_TYPE, D3D_FEATURE_LEVEL_11_0, D3D_FEATURE_LEVEL_11_1};
-use windows::Win32::Graphics::Dxgi::{Common, IDXGIAdapter, IDXGIDevice, IDXGIFactory2, IDXGISwapChain, IDXGISwapChain1, DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_CHAIN_FLAG, DXGI_SWAP_CHAIN_FULLSCREEN_DESC, DXGI_SWAP_EFFECT_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT};
-use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_D24_UNORM_S8_UINT, DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32B32_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_MODE_SCALING_UNSPECIFIED, DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED};
-use windows::Win32::UI::WindowsAndMessaging::CW_USEDEFAULT;
-use windows::Win32::Graphics::Direct3D::Fxc;
-use windows::Win32::Graphics::Direct3D::Fxc::D3DCompileFromFile;
-use crate::d3dutil::create_shader_from_file;
+use windows::Win32::Graphics::Direct3D::{D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST, D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL, D3D_FEATURE_LEVEL_11_0};
+use windows::Win32::Graphics::Dxgi::{Common, CreateDXGIFactory1, IDXGIAdapter, IDXGIAdapter1, IDXGIFactory1, DXGI_ADAPTER_FLAG_SOFTWARE};
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_FORMAT_D24_UNORM_S8_UINT, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32B32_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB};
+use crate::adapter::{select_adapter, AdapterInfo, AdapterSelection};
+use crate::camera::Camera;
+use crate::capabilities::{Capabilities, FeatureLevel, FormatSupport, RenderFormat};
+use crate::d3dutil::set_shader_model;
+use crate::debug_draw::DebugDraw;
+use crate::debug_renderer::DebugRenderer;
+use crate::device_lost::{is_device_lost, DeviceLost, DeviceLostReason, RecoveryBudget};
+use crate::display::{DisplayMode, WindowMode};
+use crate::instancing::{InstanceRenderer, Material};
+use crate::mesh::{Mesh, VertexPosColor};
+use crate::output_format::OutputFormat;
+use crate::post_process::{PostProcessChain, HDR_FORMAT};
+use crate::present::PresentSettings;
+use crate::profiler::profile_scope;
+use crate::resources::{BufferHandle, ResourceManager, ResourceStats, ShaderHandle};
+use crate::scene::{InstanceData, MaterialId, MeshId, Scene};
+use crate::surface::{Surface, SurfaceId};
 use crate::window::{Position, Size, Window};
 
+/// 一個 D3D11 裝置與共用的場景資源，可以同時畫到多個視窗，
+/// 每個視窗各自的 swap chain、view 與相機放在 `Surface`。
 pub struct D3d11Renderer{
     device: ID3D11Device,
     context: ID3D11DeviceContext,
-    swap_chain: IDXGISwapChain1,
-    render_target_view: Option<ID3D11RenderTargetView>,
-    depth_stencil_view: Option<ID3D11DepthStencilView>,
+    // 裝置失效時以相同的驅動程式與設定重建
+    driver_type: D3D_DRIVER_TYPE,
+    settings: RendererSettings,
+    recovery_budget: RecoveryBudget,
+    adapter: Option<AdapterInfo>,
+    capabilities: Capabilities,
+    surfaces: Vec<Option<Surface>>,
+    msaa: MsaaMode,
+    supported_msaa_modes: Vec<MsaaMode>,
+    paper_white_nits: f32,
+    present: PresentSettings,
+    resources: ResourceManager,
+    scene_pipeline: Option<ScenePipeline>,
+    meshes: Vec<Mesh>,
+    materials: Vec<Material>,
+    scene: Scene,
+    instancing: InstanceRenderer,
+    debug_draw: Mutex<DebugDraw>,
+    debug_renderer: DebugRenderer,
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug)]
-struct VertexPosColor {
-    position: XMFLOAT3,
-    color: XMFLOAT4,
+/// 建立 renderer 時的選項。
+#[derive(Debug, Clone)]
+pub struct RendererSettings {
+    /// 期望的 MSAA 取樣數，裝置不支援時會退回最接近且較小的取樣數。
+    pub msaa_samples: u32,
+    /// back buffer 格式，HDR 格式在顯示器不支援時會退回對應的 SDR 格式。
+    pub output_format: OutputFormat,
+    /// HDR 輸出時，場景中 1.0 的亮度對應的 nits。
+    pub paper_white_nits: f32,
+    /// 主視窗的顯示方式。
+    pub window_mode: WindowMode,
+    /// 所有視窗的 buffer 數量、垂直同步與撕裂設定。
+    pub present: PresentSettings,
+    /// 建立裝置時啟用 D3D11 debug layer。
+    pub debug_layer: bool,
+    /// 使用硬體驅動程式時選擇哪一張顯示卡。
+    pub adapter: AdapterSelection,
 }
 
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 4,
+            output_format: OutputFormat::Sdr8Srgb,
+            paper_white_nits: 200.0,
+            window_mode: WindowMode::Windowed,
+            present: PresentSettings::default(),
+            debug_layer: cfg!(debug_assertions),
+            adapter: AdapterSelection::default(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MsaaMode {
+    pub sample_count: u32,
+    pub quality_levels: u32,
+}
+
+impl MsaaMode {
+    pub const OFF: MsaaMode = MsaaMode { sample_count: 1, quality_levels: 1 };
+
+    pub fn sample_desc(&self) -> Common::DXGI_SAMPLE_DESC {
+        Common::DXGI_SAMPLE_DESC {
+            Count: self.sample_count,
+            Quality: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sample_count > 1
+    }
+}
+
+struct ScenePipeline {
+    vertex_buffer: BufferHandle,
+    input_layout: ID3D11InputLayout,
+    vertex_shader: ShaderHandle,
+    pixel_shader: ShaderHandle,
+}
+
+
 impl D3d11Renderer {
-    pub fn new(d3d_driver_type : D3D_DRIVER_TYPE, window: &Window) -> D3d11Renderer {
-        let (device, context) = Self::create_device_context(d3d_driver_type);
-        let pos = window.get_position();
-        let size = window.get_size();
-        let swap_chain = Self::create_swap_chain(&device, window.hwnd, size);
-        let (render_target_view, depth_stencil_view) = Self::create_views(&device, &swap_chain, size);
-        Self::bind_render_target(&context, &render_target_view, &depth_stencil_view);
-        Self::set_viewport(&context, pos, size);
+    pub fn with_settings(d3d_driver_type : D3D_DRIVER_TYPE, window: &Window, settings: RendererSettings) -> D3d11Renderer {
+        let (device, context, adapter, supported_msaa_modes, capabilities) = Self::create_device(d3d_driver_type, &settings)
+            .unwrap_or_else(|e| panic!("{}", e));
+        let msaa = Self::choose_msaa_mode(&supported_msaa_modes, &capabilities, settings.msaa_samples);
+        let output_format = Self::supported_output_format(&capabilities, settings.output_format);
+        let mut surface = Surface::new(&device, window, msaa, output_format, settings.paper_white_nits, settings.present);
+        if settings.window_mode != WindowMode::Windowed {
+            surface.set_window_mode(&device, window, settings.window_mode, msaa).unwrap_or_else(|lost| panic!("{}", lost));
+        }
+        let (paper_white_nits, present) = (settings.paper_white_nits, settings.present);
         return Self {
+            instancing: InstanceRenderer::new(&device, InstanceRenderer::DEFAULT_CAPACITY),
+            debug_renderer: DebugRenderer::new(&device, DebugRenderer::DEFAULT_CAPACITY),
             device,
             context,
-            swap_chain,
-            render_target_view : Some(render_target_view),
-            depth_stencil_view : Some(depth_stencil_view)
+            driver_type: d3d_driver_type,
+            settings,
+            recovery_budget: RecoveryBudget::default(),
+            adapter,
+            capabilities,
+            surfaces: vec![Some(surface)],
+            msaa,
+            supported_msaa_modes,
+            paper_white_nits,
+            present,
+            resources: ResourceManager::new(),
+            scene_pipeline: None,
+            meshes: vec![],
+            materials: vec![],
+            scene: Scene::new(),
+            debug_draw: Mutex::new(DebugDraw::new()),
         };
     }
 
-    fn create_device_context(d3d_driver_type : D3D_DRIVER_TYPE) -> (ID3D11Device, ID3D11DeviceContext) {
-        let mut flag = D3D11_CREATE_DEVICE_FLAG::default();
-        flag |= D3D11_CREATE_DEVICE_DEBUG;
-        let mut feature_level = D3D_FEATURE_LEVEL_11_1;
-        let mut device: Option<ID3D11Device> = None;
-        let mut context: Option<ID3D11DeviceContext> = None;
-        unsafe {
-            D3D11CreateDevice(
-                None,
-                d3d_driver_type,
-                HMODULE::default(),
-                flag,
-                Some(&[D3D_FEATURE_LEVEL_11_1]),
-                D3D11_SDK_VERSION,
-                Some(&mut device),
-                Some(&mut feature_level),
-                Some(&mut context),
-            ).unwrap()
-        }
-
-        let device = device.unwrap();
-        let context = context.unwrap();
-        (device, context)
-    }
-
-    fn create_swap_chain(device: &ID3D11Device, hwnd : HWND, size : Size) -> IDXGISwapChain1 {
-        let dxgi_device = device.clone().cast::<IDXGIDevice>().unwrap();
-        let adapter = unsafe {
-            dxgi_device.GetAdapter().unwrap()
-        };
+    /// 在同一個裝置上為另一個視窗建立 swap chain，沿用 renderer 的 MSAA 設定。
+    // 示範程式只開一個視窗，多視窗的 API 目前沒有呼叫端
+    #[allow(dead_code)]
+    pub fn add_window(&mut self, window: &Window, output_format: OutputFormat) -> SurfaceId {
+        self.ensure_device();
+        let output_format = Self::supported_output_format(&self.capabilities, output_format);
+        let surface = Surface::new(&self.device, window, self.msaa, output_format, self.paper_white_nits, self.present);
+        match self.surfaces.iter().position(Option::is_none) {
+            Some(index) => {
+                self.surfaces[index] = Some(surface);
+                SurfaceId(index)
+            }
+            None => {
+                self.surfaces.push(Some(surface));
+                SurfaceId(self.surfaces.len() - 1)
+            }
+        }
+    }
 
-        let factory = unsafe {
-            adapter.GetParent::<IDXGIFactory2>().unwrap()
+    /// 釋放視窗的 swap chain，必須在視窗銷毀前呼叫。
+    #[allow(dead_code)]
+    pub fn remove_window(&mut self, id: SurfaceId) {
+        Self::clear_render_target(&self.context);
+        if let Some(surface) = self.surfaces.get_mut(id.0) {
+            *surface = None;
+        }
+    }
+
+    pub fn surface(&self, id: SurfaceId) -> Option<&Surface> {
+        self.surfaces.get(id.0).and_then(Option::as_ref)
+    }
+
+    pub fn surface_mut(&mut self, id: SurfaceId) -> Option<&mut Surface> {
+        self.surfaces.get_mut(id.0).and_then(Option::as_mut)
+    }
+
+    /// `hwnd` 對應的 surface，用在視窗事件處理函式中。
+    #[allow(dead_code)]
+    pub fn surface_for_window(&self, hwnd: HWND) -> Option<SurfaceId> {
+        self.surfaces.iter().position(|surface| surface.as_ref().is_some_and(|surface| surface.hwnd == hwnd)).map(SurfaceId)
+    }
+
+    #[allow(dead_code)]
+    pub fn surface_ids(&self) -> Vec<SurfaceId> {
+        self.surfaces.iter().enumerate().filter(|(_, surface)| surface.is_some()).map(|(index, _)| SurfaceId(index)).collect()
+    }
+
+    fn primary(&self) -> &Surface {
+        self.surface(SurfaceId::PRIMARY).expect("primary surface has been removed")
+    }
+
+    fn primary_mut(&mut self) -> &mut Surface {
+        self.surface_mut(SurfaceId::PRIMARY).expect("primary surface has been removed")
+    }
+
+    /// 列出系統上所有的 DXGI 顯示卡，包含軟體實作。
+    pub fn enumerate_adapters() -> Vec<AdapterInfo> {
+        Self::enumerate_dxgi_adapters().into_iter().map(|(info, _)| info).collect()
+    }
+
+    fn enumerate_dxgi_adapters() -> Vec<(AdapterInfo, IDXGIAdapter1)> {
+        let Ok(factory) = (unsafe { CreateDXGIFactory1::<IDXGIFactory1>() }) else {
+            return vec![];
         };
+        let mut adapters = vec![];
+        // 編號超出範圍時回傳 DXGI_ERROR_NOT_FOUND
+        while let Ok(adapter) = unsafe { factory.EnumAdapters1(adapters.len() as u32) } {
+            let desc = unsafe { adapter.GetDesc1() }.unwrap();
+            let name_length = desc.Description.iter().position(|c| *c == 0).unwrap_or(desc.Description.len());
+            let info = AdapterInfo {
+                index: adapters.len(),
+                name: String::from_utf16_lossy(&desc.Description[..name_length]),
+                vendor_id: desc.VendorId,
+                device_id: desc.DeviceId,
+                dedicated_video_memory: desc.DedicatedVideoMemory as u64,
+                shared_system_memory: desc.SharedSystemMemory as u64,
+                software: desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32 != 0,
+            };
+            adapters.push((info, adapter));
+        }
+        adapters
+    }
 
-        let swap_chain_desc = DXGI_SWAP_CHAIN_DESC1 {
-            Width: size.width as u32, //binding window size
-            Height: size.height as u32,
-            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
-            Stereo: Default::default(),
-            SampleDesc: Common::DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
-            BufferCount: 1,
-            Scaling: Default::default(),
-            SwapEffect: DXGI_SWAP_EFFECT_DISCARD,
-            AlphaMode: Default::default(),
-            Flags: 0,
+    // 找不到指定的顯示卡時退回系統預設，由 D3D11CreateDevice 自己選擇
+    fn choose_adapter(selection: &AdapterSelection) -> Option<(AdapterInfo, IDXGIAdapter)> {
+        let mut adapters = Self::enumerate_dxgi_adapters();
+        for (info, _) in adapters.iter() {
+            println!("Adapter {}", info);
+        }
+        match select_adapter(&adapters.iter().map(|(info, _)| info.clone()).collect::<Vec<_>>(), selection) {
+            Ok(position) => {
+                let (info, adapter) = adapters.swap_remove(position);
+                println!("Using adapter {} for {}", info, selection);
+                Some((info, adapter.cast::<IDXGIAdapter>().unwrap()))
+            }
+            Err(e) => {
+                println!("Cannot select adapter {}: {}, using the system default", selection, e);
+                None
+            }
+        }
+    }
+
+    /// 建立裝置時選用的顯示卡，使用 WARP 等非硬體驅動程式或退回系統預設時為 `None`。
+    pub fn adapter(&self) -> Option<&AdapterInfo> {
+        self.adapter.as_ref()
+    }
+
+    // 建立裝置並查詢能力，第一次建立與裝置失效後重建共用
+    fn create_device(d3d_driver_type: D3D_DRIVER_TYPE, settings: &RendererSettings) -> Result<(ID3D11Device, ID3D11DeviceContext, Option<AdapterInfo>, Vec<MsaaMode>, Capabilities), String> {
+        let adapter = if d3d_driver_type == D3D_DRIVER_TYPE_HARDWARE {
+            Self::choose_adapter(&settings.adapter)
+        } else {
+            None
         };
+        let (device, context, feature_level) = Self::create_device_context(d3d_driver_type, adapter.as_ref().map(|(_, adapter)| adapter), settings.debug_layer)?;
+        let supported_msaa_modes = Self::query_msaa_modes(&device);
+        let capabilities = Self::query_capabilities(&device, feature_level, &supported_msaa_modes);
+        print!("Device capabilities:\n{}", capabilities);
+        // 之後編譯的 shader 都不能超過裝置支援的 shader model
+        set_shader_model(capabilities.shader_model());
+        Ok((device, context, adapter.map(|(info, _)| info), supported_msaa_modes, capabilities))
+    }
 
-        let fullscreen_desc = DXGI_SWAP_CHAIN_FULLSCREEN_DESC{
-            RefreshRate: Common::DXGI_RATIONAL {
-                Numerator: 60,
-                Denominator: 1
-            },
-            ScanlineOrdering: DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
-            Scaling: DXGI_MODE_SCALING_UNSPECIFIED,
-            Windowed: BOOL::from(true),
+    /// 依 `FeatureLevel::FALLBACK_CHAIN` 由高到低嘗試，回傳實際取得的 feature level。
+    fn create_device_context(d3d_driver_type : D3D_DRIVER_TYPE, adapter: Option<&IDXGIAdapter>, debug_layer: bool) -> Result<(ID3D11Device, ID3D11DeviceContext, FeatureLevel), String> {
+        let mut flag = D3D11_CREATE_DEVICE_FLAG::default();
+        if debug_layer {
+            flag |= D3D11_CREATE_DEVICE_DEBUG;
+        }
+        // 指定顯示卡時驅動程式類型必須是 UNKNOWN
+        let d3d_driver_type = if adapter.is_some() { D3D_DRIVER_TYPE_UNKNOWN } else { d3d_driver_type };
+        let levels = FeatureLevel::FALLBACK_CHAIN.map(|level| D3D_FEATURE_LEVEL(level.raw()));
+        let create = |levels: &[D3D_FEATURE_LEVEL]| {
+            let mut feature_level = D3D_FEATURE_LEVEL_11_0;
+            let mut device: Option<ID3D11Device> = None;
+            let mut context: Option<ID3D11DeviceContext> = None;
+            unsafe {
+                D3D11CreateDevice(
+                    adapter,
+                    d3d_driver_type,
+                    HMODULE::default(),
+                    flag,
+                    Some(levels),
+                    D3D11_SDK_VERSION,
+                    Some(&mut device),
+                    Some(&mut feature_level),
+                    Some(&mut context),
+                )
+            }.map(|_| (device.unwrap(), context.unwrap(), feature_level))
         };
+        // 沒有 D3D 11.1 runtime 的系統不認得 11_1，整個清單會被回傳 E_INVALIDARG，去掉後再試一次
+        let result = match create(&levels) {
+            Err(e) if e.code() == E_INVALIDARG => create(&levels[1..]),
+            result => result,
+        };
+        let (device, context, feature_level) = result.map_err(|e| {
+            let tried = FeatureLevel::FALLBACK_CHAIN.map(|level| level.to_string()).join(", ");
+            format!("cannot create a D3D11 device with feature level {}: {}", tried, e)
+        })?;
+        let feature_level = FeatureLevel::from_raw(feature_level.0).expect("D3D11CreateDevice returned a feature level that was not requested");
+        Ok((device, context, feature_level))
+    }
 
-        let swap_chain = unsafe {
-            factory.CreateSwapChainForHwnd(&*device, hwnd, &swap_chain_desc, Some(&fullscreen_desc), None)
-        }.unwrap();
+    /// 裝置已失效時回傳原因，例如驅動程式更新、GPU 重設或卡住。
+    pub fn device_lost(&self) -> Option<DeviceLost> {
+        let code = unsafe { self.device.GetDeviceRemovedReason() }.err()?.code().0;
+        DeviceLostReason::from_hresult(code).map(|reason| DeviceLost { hresult: code, reason })
+    }
 
-        swap_chain
+    // present 回傳裝置失效時，以裝置回報的原因為準
+    fn check_present(&self, hresult: HRESULT) -> Result<(), DeviceLost> {
+        if !is_device_lost(hresult.0) {
+            return Ok(());
+        }
+        Err(self.device_lost().unwrap_or(DeviceLost {
+            hresult: hresult.0,
+            reason: DeviceLostReason::from_hresult(hresult.0).unwrap_or(DeviceLostReason::Unknown(hresult.0)),
+        }))
     }
 
-    // swap chain 要先初始化完成
-    fn create_views(device : &ID3D11Device, swap_chain : &IDXGISwapChain1, size: Size) -> (ID3D11RenderTargetView, ID3D11DepthStencilView) {
-        let back_buffer = unsafe {
-            swap_chain.GetBuffer::<ID3D11Texture2D>(0).unwrap()
-        };
-        let mut render_target_view: Option<ID3D11RenderTargetView> = None;
+    /// 裝置失效後重建裝置、所有視窗的 swap chain 與所有已註冊的資源。
+    /// mesh、材質、後處理參數、相機與場景都從 CPU 端保留的資料重建，
+    /// 各種 id 維持不變。短時間內失效太多次時放棄並回傳錯誤。
+    pub fn recover(&mut self, lost: DeviceLost) -> Result<(), String> {
+        println!("Direct3D {}, recreating the device", lost);
+        if !self.recovery_budget.try_attempt(Instant::now()) {
+            return Err(format!("{}, giving up after {} recoveries", lost, RecoveryBudget::DEFAULT_MAX_ATTEMPTS));
+        }
         unsafe {
-            device.CreateRenderTargetView(&back_buffer, None, Some(&mut render_target_view)).unwrap();
+            self.context.ClearState();
+            self.context.Flush();
+        }
+        let (device, context, adapter, supported_msaa_modes, capabilities) = Self::create_device(self.driver_type, &self.settings)?;
+        self.msaa = Self::choose_msaa_mode(&supported_msaa_modes, &capabilities, self.msaa.sample_count);
+        self.device = device;
+        self.context = context;
+        self.adapter = adapter;
+        self.supported_msaa_modes = supported_msaa_modes;
+        self.capabilities = capabilities;
+
+        for mesh in self.meshes.iter_mut() {
+            mesh.recreate(&self.device);
+        }
+        for material in self.materials.iter_mut() {
+            material.recreate(&self.device);
         }
+        self.instancing.recreate(&self.device);
+        self.debug_renderer.recreate(&self.device);
+        self.resources.recreate(&self.device);
+        // 場景管線的 buffer 與 shader 已由 resources 重建，handle 不變，
+        // 只剩不受管理的 input layout 要從保留的 VS bytecode 重建
+        let input_layout = self.scene_pipeline.as_ref()
+            .and_then(|pipeline| self.resources.shader(pipeline.vertex_shader))
+            .map(|shader| Self::create_scene_input_layout(&self.device, shader.bytecode()));
+        if let (Some(pipeline), Some(input_layout)) = (&mut self.scene_pipeline, input_layout) {
+            pipeline.input_layout = input_layout;
+        }
+        let (device, msaa) = (self.device.clone(), self.msaa);
+        // 重建期間又失效時從頭再來，次數一樣受 recovery_budget 限制
+        let lost_again = self.surfaces.iter_mut().flatten().find_map(|surface| surface.recreate(&device, msaa).err());
+        if let Some(lost) = lost_again {
+            return self.recover(lost);
+        }
+        println!("Direct3D device recreated");
+        Ok(())
+    }
 
-        let render_target_view = render_target_view.unwrap();
+    // 需要 GPU 的操作之前呼叫，裝置已失效時先重建，無法重建時結束程式
+    fn ensure_device(&mut self) {
+        if let Some(lost) = self.device_lost() {
+            self.recover(lost).unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
 
-        let depth_stencil_desc = D3D11_TEXTURE2D_DESC {
-            Width: size.width as u32,
-            Height: size.height as u32,
-            MipLevels: 1,
-            ArraySize: 1,
-            Format: DXGI_FORMAT_D24_UNORM_S8_UINT,
-            SampleDesc: Common::DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Usage: D3D11_USAGE_DEFAULT,
-            BindFlags: D3D11_BIND_DEPTH_STENCIL.0 as u32,
-            CPUAccessFlags: 0,
-            MiscFlags: 0,
-        };
+    fn dxgi_format(format: RenderFormat) -> DXGI_FORMAT {
+        match format {
+            RenderFormat::Rgba8Unorm => DXGI_FORMAT_R8G8B8A8_UNORM,
+            RenderFormat::Rgba8UnormSrgb => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            RenderFormat::Rgb10a2Unorm => DXGI_FORMAT_R10G10B10A2_UNORM,
+            RenderFormat::Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+            RenderFormat::D24UnormS8Uint => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        }
+    }
 
-        let mut depth_stencil_buffer: Option<ID3D11Texture2D> = None;
+    fn query_capabilities(device: &ID3D11Device, feature_level: FeatureLevel, msaa_modes: &[MsaaMode]) -> Capabilities {
+        let mut formats = BTreeMap::new();
+        for format in RenderFormat::ALL {
+            // 不支援的格式會回傳錯誤，視為全部不支援
+            let flags = unsafe { device.CheckFormatSupport(Self::dxgi_format(format)) }.unwrap_or(0);
+            let has = |flag: D3D11_FORMAT_SUPPORT| flags & flag.0 as u32 != 0;
+            formats.insert(format, FormatSupport {
+                render_target: has(D3D11_FORMAT_SUPPORT_RENDER_TARGET) || has(D3D11_FORMAT_SUPPORT_DEPTH_STENCIL),
+                display: has(D3D11_FORMAT_SUPPORT_DISPLAY),
+                multisample_render_target: has(D3D11_FORMAT_SUPPORT_MULTISAMPLE_RENDERTARGET),
+                shader_sample: has(D3D11_FORMAT_SUPPORT_SHADER_SAMPLE),
+            });
+        }
+        let mut threading = D3D11_FEATURE_DATA_THREADING::default();
+        let threading_supported = unsafe {
+            device.CheckFeatureSupport(
+                D3D11_FEATURE_THREADING,
+                &mut threading as *mut _ as *mut _,
+                size_of::<D3D11_FEATURE_DATA_THREADING>() as u32,
+            )
+        }.is_ok();
+        Capabilities {
+            feature_level: Some(feature_level),
+            formats,
+            msaa_sample_counts: msaa_modes.iter().map(|mode| mode.sample_count).collect(),
+            concurrent_creates: threading_supported && threading.DriverConcurrentCreates.as_bool(),
+            driver_command_lists: threading_supported && threading.DriverCommandLists.as_bool(),
+        }
+    }
 
-        unsafe {
-            device.CreateTexture2D(&depth_stencil_desc, None, Some(&mut depth_stencil_buffer)).unwrap()
+    // 裝置不能把要求的格式當 back buffer 時退回對應的 SDR 格式
+    fn supported_output_format(capabilities: &Capabilities, requested: OutputFormat) -> OutputFormat {
+        let fallback = requested.sdr_fallback();
+        match capabilities.choose_display_format(&[requested.render_format(), fallback.render_format()]) {
+            Some(format) if format == requested.render_format() => requested,
+            _ => {
+                println!("Output format {} is not supported by the device, using {}", requested.name(), fallback.name());
+                fallback
+            }
         }
+    }
 
-        let depth_stencil_buffer = depth_stencil_buffer.unwrap();
+    /// 建立裝置時查詢到的能力。
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
 
-        let mut depth_stencil_view: Option<ID3D11DepthStencilView> = None;
-        unsafe {
-            device.CreateDepthStencilView(&depth_stencil_buffer, None, Some(&mut depth_stencil_view)).unwrap();
+    /// 列出 HDR 場景格式與 depth 格式都支援的取樣數。
+    fn query_msaa_modes(device: &ID3D11Device) -> Vec<MsaaMode> {
+        let mut modes = vec![];
+        let mut sample_count = 1;
+        while sample_count <= D3D11_MAX_MULTISAMPLE_SAMPLE_COUNT {
+            let (color_levels, depth_levels) = unsafe {
+                (
+                    device.CheckMultisampleQualityLevels(HDR_FORMAT, sample_count).unwrap_or(0),
+                    device.CheckMultisampleQualityLevels(DXGI_FORMAT_D24_UNORM_S8_UINT, sample_count).unwrap_or(0),
+                )
+            };
+            let quality_levels = color_levels.min(depth_levels);
+            if quality_levels > 0 {
+                modes.push(MsaaMode { sample_count, quality_levels });
+            }
+            sample_count *= 2;
+        }
+        modes
+    }
+
+    fn choose_msaa_mode(modes: &[MsaaMode], capabilities: &Capabilities, requested_samples: u32) -> MsaaMode {
+        let sample_count = capabilities.choose_msaa_samples(requested_samples);
+        modes.iter().copied().find(|mode| mode.sample_count == sample_count).unwrap_or(MsaaMode::OFF)
+    }
+
+    pub fn msaa(&self) -> MsaaMode {
+        self.msaa
+    }
+
+    pub fn supported_msaa_modes(&self) -> &[MsaaMode] {
+        &self.supported_msaa_modes
+    }
+
+    /// 執行期切換 MSAA，所有視窗一起重建，回傳實際採用的模式。
+    /// 裝置失效時回傳原因，`recover` 會以新的模式重建所有視窗。
+    pub fn set_msaa_samples(&mut self, requested_samples: u32) -> Result<MsaaMode, DeviceLost> {
+        self.ensure_device();
+        let msaa = Self::choose_msaa_mode(&self.supported_msaa_modes, &self.capabilities, requested_samples);
+        if msaa != self.msaa {
+            self.msaa = msaa;
+            Self::clear_render_target(&self.context);
+            for surface in self.surfaces.iter_mut().flatten() {
+                surface.resize(&self.device, surface.position(), surface.size(), msaa)?;
+            }
+        }
+        Ok(msaa)
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.present.vsync
+    }
+
+    /// 切換所有視窗的垂直同步。
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.present.vsync = vsync;
+        for surface in self.surfaces.iter_mut().flatten() {
+            surface.set_vsync(vsync);
         }
+    }
+
+    /// 等到所有視窗的 swap chain 都能接受下一幀，在每幀開始讀取輸入前呼叫。
+    pub fn wait_for_next_frame(&self) {
+        for surface in self.surfaces.iter().flatten() {
+            surface.wait_for_next_frame();
+        }
+    }
+
+    /// 主視窗的輸出格式。
+    pub fn output_format(&self) -> OutputFormat {
+        self.primary().output_format()
+    }
+
+    /// 執行期切換主視窗的 back buffer 格式，回傳實際採用的格式。
+    pub fn set_output_format(&mut self, requested: OutputFormat) -> Result<OutputFormat, DeviceLost> {
+        self.set_surface_output_format(SurfaceId::PRIMARY, requested)
+    }
 
-        let depth_stencil_view = depth_stencil_view.unwrap();
+    pub fn set_surface_output_format(&mut self, id: SurfaceId, requested: OutputFormat) -> Result<OutputFormat, DeviceLost> {
+        self.ensure_device();
+        let requested = Self::supported_output_format(&self.capabilities, requested);
+        Self::clear_render_target(&self.context);
+        let (device, msaa) = (self.device.clone(), self.msaa);
+        let surface = self.surface_mut(id).expect("unknown surface");
+        surface.set_output_format(&device, requested, msaa)
+    }
+
+    pub fn window_mode(&self, id: SurfaceId) -> WindowMode {
+        self.surface(id).map(Surface::window_mode).unwrap_or_default()
+    }
 
-        (render_target_view, depth_stencil_view)
+    /// 切換視窗的顯示方式，回傳實際採用的模式。`window` 必須是 `id` 對應的視窗。
+    pub fn set_window_mode(&mut self, id: SurfaceId, window: &Window, mode: WindowMode) -> Result<WindowMode, DeviceLost> {
+        self.ensure_device();
+        Self::clear_render_target(&self.context);
+        let (device, msaa) = (self.device.clone(), self.msaa);
+        let surface = self.surface_mut(id).expect("unknown surface");
+        surface.set_window_mode(&device, window, mode, msaa)
+    }
+
+    /// 視窗所在顯示器支援的顯示模式，用來選擇獨占全螢幕的解析度。
+    pub fn display_modes(&self, id: SurfaceId) -> Vec<DisplayMode> {
+        self.surface(id).map(Surface::display_modes).unwrap_or_default()
     }
 
     fn bind_render_target(context: &ID3D11DeviceContext, render_target_view: &ID3D11RenderTargetView, depth_stencil_view: &ID3D11DepthStencilView) {
@@ -173,21 +559,6 @@ impl D3d11Renderer {
         }
     }
 
-    fn set_viewport(context: &ID3D11DeviceContext, pos : Position, size: Size) {
-        let viewport = D3D11_VIEWPORT {
-            TopLeftX: pos.x as f32,
-            TopLeftY: pos.y as f32,
-            Width: size.width as f32,
-            Height: size.height as f32,
-            MinDepth: 0.0,
-            MaxDepth: 1.0,
-        };
-
-        unsafe {
-            context.RSSetViewports(Some(&[viewport]));
-        }
-    }
-
     
     pub fn fill_blue(&self) {
         // let blue: [f32; 4] = [0f32, 0f32, 1f32, 0f32];
@@ -197,13 +568,8 @@ impl D3d11Renderer {
         //     self.context.ClearDepthStencilView(&self.depth_stencil_view, flag.0, 1f32, 0);
         // }
     }
-    pub fn present(&self) {
-        unsafe {
-            let _ = self.swap_chain.Present(0, windows::Win32::Graphics::Dxgi::DXGI_PRESENT(0));
-        }
-    }
-
-    fn load_hlsl(&self) -> (ID3D11InputLayout, ID3D11VertexShader, ID3D11PixelShader) {
+    // `VertexPosColor` 的 input layout，必須與 triangle_vs.hlsl 的輸入一致
+    fn create_scene_input_layout(device: &ID3D11Device, vs_bytecode: &[u8]) -> ID3D11InputLayout {
         let input_layout = [
             D3D11_INPUT_ELEMENT_DESC {
                 SemanticName: s!("POSITION"),
@@ -225,30 +591,81 @@ impl D3d11Renderer {
             },
         ];
         let mut vertex_layout: Option<ID3D11InputLayout> = None;
-        let mut vertex_shader: Option<ID3D11VertexShader> = None;
         unsafe {
-            // 頂點著色器
-            let vs_blob = create_shader_from_file(w!("hlsl/triangle_vs.cso"), w!("hlsl/triangle_vs.hlsl"), s!("VS"), s!("vs_5_0"));
+            device.CreateInputLayout(&input_layout, vs_bytecode, Some(&mut vertex_layout)).expect("CreateInputLayout failed");
+        }
+        vertex_layout.unwrap()
+    }
 
-            let vs_buffer = slice::from_raw_parts(vs_blob.GetBufferPointer() as *mut u8, vs_blob.GetBufferSize());
+    /// 以 handle 管理的 GPU 資源。
+    pub fn resources_mut(&mut self) -> &mut ResourceManager {
+        &mut self.resources
+    }
 
-            self.device.CreateVertexShader(vs_buffer, None, Some(&mut vertex_shader)).expect("TODO: panic message");
+    pub fn resource_stats(&self) -> ResourceStats {
+        self.resources.stats()
+    }
 
-            self.device.CreateInputLayout(&input_layout, vs_buffer, Some(&mut vertex_layout)).expect("TODO: panic message");
-        }
-        let mut pixel_shader: Option<ID3D11PixelShader> = None;
-        unsafe {
-            // 像素著色器
-            let ps_blob = create_shader_from_file(w!("hlsl/triangle_ps.cso"), w!("hlsl/triangle_ps.hlsl"), s!("PS"), s!("ps_5_0"));
+    pub fn device(&self) -> &ID3D11Device {
+        &self.device
+    }
 
-            let ps_buffer = slice::from_raw_parts(ps_blob.GetBufferPointer() as *mut u8, ps_blob.GetBufferSize());
-            self.device.CreatePixelShader(ps_buffer, None, Some(&mut pixel_shader)).expect("TODO");
-        }
+    pub fn add_mesh(&mut self, mesh: Mesh) -> MeshId {
+        self.meshes.push(mesh);
+        MeshId(self.meshes.len() - 1)
+    }
+
+    pub fn add_material(&mut self, material: Material) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    /// 主視窗的相機，其他視窗的相機以 `surface_mut` 取得。
+    pub fn camera(&self) -> &Camera {
+        self.primary().camera()
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.primary_mut().camera_mut()
+    }
 
-        return (vertex_layout.unwrap(), vertex_shader.unwrap(), pixel_shader.unwrap())
+    /// 以 hardware instancing 畫出 `instances`，需在 `draw_scene` 綁定場景目標之後呼叫。
+    pub fn draw_instanced(&self, mesh: MeshId, material: MaterialId, instances: &[InstanceData]) -> usize {
+        self.instancing.draw_instanced(&self.context, &self.meshes[mesh.0], &self.materials[material.0], instances)
     }
 
-    pub fn render(&self) {
+    /// 除錯繪圖只需要 `&self`，任何持有 renderer 的地方都能加入線段。
+    pub fn debug_draw(&self) -> MutexGuard<'_, DebugDraw> {
+        self.debug_draw.lock().unwrap()
+    }
+
+    /// 主視窗的後處理鏈。
+    pub fn post_process(&self) -> &PostProcessChain {
+        self.primary().post_process()
+    }
+
+    pub fn post_process_mut(&mut self) -> &mut PostProcessChain {
+        self.primary_mut().post_process_mut()
+    }
+
+    /// 建立畫三角形的場景管線，shader 由 `ResourceManager::create_shader` 從資產建立，
+    /// 之後由管線擁有並在重建或 renderer 釋放時一起釋放。stage 不符時兩個 shader 都會被釋放。
+    pub fn create_scene_pipeline(&mut self, vertex_shader: ShaderHandle, pixel_shader: ShaderHandle) -> Result<(), String> {
+        let vs_bytecode = match self.resources.shader(vertex_shader) {
+            Some(shader) if shader.vertex_shader().is_some() => Some(shader.bytecode()),
+            _ => None,
+        };
+        let is_pixel_shader = self.resources.shader(pixel_shader).is_some_and(|shader| shader.pixel_shader().is_some());
+        let (Some(vs_bytecode), true) = (vs_bytecode, is_pixel_shader) else {
+            let _ = self.resources.destroy_shader(vertex_shader);
+            let _ = self.resources.destroy_shader(pixel_shader);
+            return Err(format!("scene pipeline needs a vertex shader and a pixel shader, got {} and {}", vertex_shader, pixel_shader));
+        };
+        let input_layout = Self::create_scene_input_layout(&self.device, vs_bytecode);
 
         let vertices = [
             VertexPosColor {
@@ -300,70 +717,149 @@ impl D3d11Renderer {
             MiscFlags: 0,
             StructureByteStride: 0,
         };
-        let init_data = D3D11_SUBRESOURCE_DATA {
-            pSysMem: vertices.as_ptr() as _,
-            SysMemPitch: 0,
-            SysMemSlicePitch: 0,
+        let vertex_bytes = unsafe {
+            slice::from_raw_parts(vertices.as_ptr() as *const u8, size_of_val(&vertices))
         };
 
-        let mut buffer: Option<ID3D11Buffer> = None;
-        unsafe {
-            self.device.CreateBuffer(&vbd, Some(&init_data), Some(&mut buffer)).expect("REASON")
+        // 每次重建管線都先釋放上一次建立的資源
+        if let Some(pipeline) = self.scene_pipeline.take() {
+            self.destroy_scene_pipeline(pipeline);
+        }
+        let buffer = self.resources.create_buffer(&self.device, &vbd, Some(vertex_bytes));
+
+        self.scene_pipeline = Some(ScenePipeline {
+            vertex_buffer: buffer,
+            input_layout,
+            vertex_shader,
+            pixel_shader,
+        });
+        Ok(())
+    }
+
+    fn destroy_scene_pipeline(&mut self, pipeline: ScenePipeline) {
+        let results = [
+            self.resources.destroy_buffer(pipeline.vertex_buffer),
+            self.resources.destroy_shader(pipeline.vertex_shader),
+            self.resources.destroy_shader(pipeline.pixel_shader),
+        ];
+        for e in results.into_iter().filter_map(Result::err) {
+            println!("{}", e);
         }
-        let buffer = buffer.unwrap();
+    }
 
+    // 後處理會改掉 IA/VS/PS 狀態，所以每一幀都要重新綁定場景管線。
+    // 任何一個資源已被釋放時回傳 false，這一幀就不畫三角形
+    fn bind_scene_pipeline(&self, pipeline: &ScenePipeline) -> bool {
+        let resources = &self.resources;
+        let (Some(vertex_buffer), Some(vertex_shader), Some(pixel_shader)) = (
+            resources.buffer(pipeline.vertex_buffer),
+            resources.shader(pipeline.vertex_shader).and_then(|shader| shader.vertex_shader()),
+            resources.shader(pipeline.pixel_shader).and_then(|shader| shader.pixel_shader()),
+        ) else {
+            return false;
+        };
         let stride = size_of::<VertexPosColor>() as u32;
         let offset = 0_u32;
-
-        let (vertex_layout, vertex_shader, pixel_shader) = self.load_hlsl();
         unsafe {
-            self.context.IASetVertexBuffers(0, 1, Some(&Some(buffer)), Some(&stride), Some(&offset));
+            self.context.IASetVertexBuffers(0, 1, Some(&Some(vertex_buffer.buffer.clone())), Some(&stride), Some(&offset));
             self.context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
-            self.context.IASetInputLayout(&vertex_layout);
-            self.context.VSSetShader(&vertex_shader, None);
-            self.context.PSSetShader(&pixel_shader, None);
+            self.context.IASetInputLayout(&pipeline.input_layout);
+            self.context.VSSetShader(vertex_shader, None);
+            self.context.PSSetShader(pixel_shader, None);
         }
+        true
     }
 
-    pub fn draw_scene(&self) {
-        let black = [0f32, 0f32, 0f32, 1f32];
-        unsafe {
-            // fill with black
-            self.context.ClearRenderTargetView(&self.render_target_view.clone().unwrap(), &black);
-            self.context.ClearDepthStencilView(&self.depth_stencil_view.clone().unwrap(), (D3D11_CLEAR_DEPTH | D3D11_CLEAR_STENCIL).0, 1.0, 0);
+    /// 依序畫出所有視窗並 present，除錯線段在所有視窗都畫完後才到期。
+    /// 裝置失效時不再繼續畫，回傳的原因交給 `recover` 重建。
+    pub fn draw_scene(&self) -> Result<(), DeviceLost> {
+        profile_scope!("draw_scene");
+        if let Some(lost) = self.device_lost() {
+            return Err(lost);
+        }
+        for surface in self.surfaces.iter().flatten() {
+            self.check_present(self.draw_surface_scene(surface))?;
+        }
+        self.debug_draw().end_frame();
+        Ok(())
+    }
 
-            // draw triangle
-            self.context.Draw(3, 0);
-            let _ = self.swap_chain.Present(0, windows::Win32::Graphics::Dxgi::DXGI_PRESENT(0));
+    /// 只畫出一個視窗，例如只有該視窗需要重繪時。
+    #[allow(dead_code)]
+    pub fn draw_surface(&self, id: SurfaceId) -> Result<(), DeviceLost> {
+        if let Some(lost) = self.device_lost() {
+            return Err(lost);
+        }
+        match self.surface(id) {
+            Some(surface) => self.check_present(self.draw_surface_scene(surface)),
+            None => Ok(()),
         }
     }
 
-    pub fn on_resize(&mut self, pos: Position, size: Size) {
+    fn draw_surface_scene(&self, surface: &Surface) -> HRESULT {
+        profile_scope!("draw_surface");
+        let black = [0f32, 0f32, 0f32, 1f32];
+        let depth_stencil_view = surface.depth_stencil_view();
+        let scene_target = surface.scene_target();
+        let view_projection = surface.camera().view_projection();
 
-        self.render_target_view = None;
-        self.depth_stencil_view = None;
-        
-        
-        let mut size = size;
-        
-        if (size.height == 0) {
-            size.height = 1;
+        // 場景先畫到 HDR 中間目標
+        Self::bind_render_target(&self.context, scene_target, depth_stencil_view);
+        unsafe {
+            self.context.RSSetViewports(Some(&[surface.viewport()]));
+            // fill with black
+            self.context.ClearRenderTargetView(scene_target, &black);
+            self.context.ClearDepthStencilView(depth_stencil_view, (D3D11_CLEAR_DEPTH | D3D11_CLEAR_STENCIL).0, 1.0, 0);
         }
-        if (size.width == 0) {
-            size.width = 1;
+
+        if let Some(pipeline) = &self.scene_pipeline && self.bind_scene_pipeline(pipeline) {
+            unsafe {
+                // draw triangle
+                self.context.Draw(3, 0);
+            }
         }
 
-        unsafe {
-            self.swap_chain.ResizeBuffers(1, size.width as u32, size.height as u32, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SWAP_CHAIN_FLAG(0)).expect("Resize failed");
+        // 相同 mesh + material 的物件合併成一次 instanced draw call
+        self.instancing.begin_frame(&self.context, view_projection);
+        for batch in surface.scene.as_ref().unwrap_or(&self.scene).batches() {
+            self.draw_instanced(batch.mesh, batch.material, &batch.instances);
         }
 
-        let (render_target_view, depth_stencil_view) = Self::create_views(&self.device, &self.swap_chain, size);
-        self.render_target_view = Some(render_target_view);
-        self.depth_stencil_view = Some(depth_stencil_view);
+        // 除錯線段畫在場景之上
+        self.debug_renderer.flush(&self.context, &self.debug_draw(), view_projection);
+
+        surface.finish_frame(&self.context)
+    }
 
-        Self::bind_render_target(&self.context, &self.render_target_view.clone().unwrap(), &self.depth_stencil_view.clone().unwrap());
-        Self::set_viewport(&self.context, pos, size);
+    /// 主視窗大小改變時呼叫。裝置失效時與 `draw_scene` 相同，回傳的原因交給 `recover` 重建。
+    pub fn on_resize(&mut self, pos: Position, size: Size) -> Result<(), DeviceLost> {
+        self.resize_surface(SurfaceId::PRIMARY, pos, size)
+    }
+
+    pub fn resize_surface(&mut self, id: SurfaceId, pos: Position, size: Size) -> Result<(), DeviceLost> {
+        self.ensure_device();
+        // 後處理最後一個 pass 把 back buffer 綁在 context 上，必須先解除才能 ResizeBuffers
+        Self::clear_render_target(&self.context);
+        let (device, msaa) = (self.device.clone(), self.msaa);
+        match self.surface_mut(id) {
+            Some(surface) => surface.resize(&device, pos, size, msaa),
+            None => Ok(()),
+        }
     }
 }
 
 
+
+impl Drop for D3d11Renderer {
+    /// 在視窗銷毀前呼叫：先釋放各視窗的 swap chain（會離開全螢幕），
+    /// 並清除 pipeline 上的綁定，讓之後釋放的資源能立即回收。
+    fn drop(&mut self) {
+        unsafe {
+            self.context.ClearState();
+        }
+        self.surfaces.clear();
+        unsafe {
+            self.context.Flush();
+        }
+    }
+}
@@ -1,18 +1,108 @@
-use std::ffi::c_void;
-use windows::core::{implement, Interface, ScopedInterface, HRESULT, PCSTR, PCWSTR};
-use windows::Win32::Graphics::Direct3D::Fxc::{D3DCompileFromFile, D3DReadFileToBlob, D3DCOMPILE_DEBUG, D3DCOMPILE_ENABLE_STRICTNESS, D3DCOMPILE_SKIP_OPTIMIZATION};
-use windows::Win32::Graphics::Direct3D::{ID3DBlob, ID3DInclude, ID3DInclude_Vtbl};
+use std::ffi::{c_void, CString};
+use std::marker::PhantomData;
+use std::sync::{Mutex, RwLock};
+use windows::core::{PCSTR, PCWSTR};
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Graphics::Direct3D::Fxc::{D3DCompile, D3DCreateBlob, D3DCOMPILE_DEBUG, D3DCOMPILE_ENABLE_STRICTNESS, D3DCOMPILE_SKIP_OPTIMIZATION};
+use windows::Win32::Graphics::Direct3D::{ID3DBlob, ID3DInclude, ID3DInclude_Impl, D3D_INCLUDE_TYPE};
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC};
 use windows_core::*;
+use crate::capabilities::ShaderModel;
+use crate::vfs::{self, Vfs};
 
+// 程式中的 shader 路徑都寫成 `hlsl/<檔名>`，即檔案系統中的虛擬路徑
+fn shader_path(path: PCWSTR) -> String {
+    unsafe { path.to_string() }.expect("shader path is not valid UTF-16")
+}
 
-pub fn create_shader_from_file(cso_file_name_in_out: PCWSTR, hlsl_file_name: PCWSTR, entry_point: PCSTR, shader_model: PCSTR) -> ID3DBlob {
-    unsafe {
-        let blob = D3DReadFileToBlob(cso_file_name_in_out);
-        if blob.is_ok() {
-            return blob.unwrap();
+/// 從檔案系統讀取 `#include` 的檔案，路徑相對於被編譯的 shader 所在的目錄。
+struct VfsInclude<'a> {
+    file_system: &'a Vfs,
+    dir: String,
+    // 交給編譯器的資料，在 Close 時釋放
+    opened: Mutex<Vec<Vec<u8>>>,
+}
+
+impl ID3DInclude_Impl for VfsInclude<'_> {
+    fn Open(&self, _include_type: D3D_INCLUDE_TYPE, file_name: &PCSTR, _parent_data: *const c_void, data: *mut *mut c_void, bytes: *mut u32) -> Result<()> {
+        let file_name = unsafe { file_name.to_string() }.map_err(|_| Error::from(E_FAIL))?;
+        let path = if self.dir.is_empty() { file_name } else { format!("{}/{}", self.dir, file_name) };
+        let contents = self.file_system.read(&path).map_err(|e| Error::new(E_FAIL, e))?;
+        unsafe {
+            *data = contents.as_ptr() as *mut c_void;
+            *bytes = contents.len() as u32;
         }
+        // Vec 的內容在堆積上，移進 opened 後指標仍然有效
+        self.opened.lock().unwrap().push(contents);
+        Ok(())
     }
 
+    fn Close(&self, data: *const c_void) -> Result<()> {
+        self.opened.lock().unwrap().retain(|contents| contents.as_ptr() as *const c_void != data);
+        Ok(())
+    }
+}
+
+fn blob_from_bytes(bytes: &[u8]) -> ID3DBlob {
+    unsafe {
+        let blob = D3DCreateBlob(bytes.len()).expect("D3DCreateBlob failed");
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), blob.GetBufferPointer() as *mut u8, bytes.len());
+        blob
+    }
+}
+
+static SHADER_MODEL: RwLock<ShaderModel> = RwLock::new(ShaderModel::Sm5_0);
+
+/// 設定之後編譯 shader 時使用的 shader model，renderer 依裝置的 feature level 呼叫。
+pub fn set_shader_model(model: ShaderModel) {
+    *SHADER_MODEL.write().unwrap() = model;
+}
+
+/// 目前編譯 shader 使用的 shader model。
+pub fn shader_model() -> ShaderModel {
+    *SHADER_MODEL.read().unwrap()
+}
+
+// 程式中的 profile 都寫成 5_0，裝置不支援時換成較低的版本
+fn shader_profile(profile: PCSTR) -> CString {
+    let profile = unsafe { profile.to_string() }.expect("shader profile is not valid UTF-8");
+    let model = *SHADER_MODEL.read().unwrap();
+    let profile = match profile.strip_suffix("5_0") {
+        Some(stage) => format!("{}{}", stage, model.suffix()),
+        None => profile,
+    };
+    CString::new(profile).unwrap()
+}
+
+/// 經由 `vfs::file_system` 讀取預先編譯的 `.cso`，沒有時編譯 `.hlsl`。
+pub fn create_shader_from_file(cso_file_name_in_out: PCWSTR, hlsl_file_name: PCWSTR, entry_point: PCSTR, shader_model: PCSTR) -> ID3DBlob {
+    let file_system = vfs::file_system();
+    let cso_file_name = shader_path(cso_file_name_in_out);
+    let hlsl_file_name = shader_path(hlsl_file_name);
+    // 預先編譯的 .cso 是 shader model 5.0，較低的 feature level 必須從原始碼重新編譯
+    if *SHADER_MODEL.read().unwrap() == ShaderModel::Sm5_0
+        && let Ok(bytecode) = file_system.read(&cso_file_name)
+    {
+        return blob_from_bytes(&bytecode);
+    }
+    let source = file_system.read(&hlsl_file_name).unwrap_or_else(|e| panic!("failed to load shader: {}", e));
+    compile_shader(&source, &hlsl_file_name, entry_point, shader_model).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// 編譯 HLSL 原始碼。`source_name` 是原始碼在檔案系統中的路徑，`#include` 相對於它所在的目錄，
+/// `shader_model` 的 `5_0` 與 `create_shader_from_file` 相同會換成裝置支援的版本。
+pub fn compile_shader(source: &[u8], source_name: &str, entry_point: PCSTR, shader_model: PCSTR) -> std::result::Result<ID3DBlob, String> {
+    let file_system = vfs::file_system();
+    let shader_model = shader_profile(shader_model);
+    let shader_model = PCSTR(shader_model.as_ptr() as *const u8);
+    let include = VfsInclude {
+        file_system: &file_system,
+        dir: source_name.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default(),
+        opened: Mutex::new(vec![]),
+    };
+    let c_source_name = CString::new(source_name).map_err(|_| format!("{}: invalid shader path", source_name))?;
+
     let mut shader_flag = D3DCOMPILE_ENABLE_STRICTNESS;
 
     // DEBUG
@@ -20,12 +110,140 @@ pub fn create_shader_from_file(cso_file_name_in_out: PCWSTR, hlsl_file_name: PCW
 
     let mut blob: Option<ID3DBlob> = None;
     unsafe {
-        let include_flag = ID3DInclude::from_raw(1 as *mut c_void);
+        let include = ID3DInclude::new(&include);
         let mut err_msg: Option<ID3DBlob> = None;
-        let res = D3DCompileFromFile(hlsl_file_name, None, &include_flag, entry_point, shader_model, shader_flag, 0, &mut blob, Some(&mut err_msg));
-        if res.is_err() {
-            panic!("Compile failed with error: {}", res.unwrap_err());
+        let res = D3DCompile(source.as_ptr() as *const c_void, source.len(), PCSTR(c_source_name.as_ptr() as *const u8), None, &*include, entry_point, shader_model, shader_flag, 0, &mut blob, Some(&mut err_msg));
+        if let Err(e) = res {
+            let message = err_msg.as_ref().map(|message| String::from_utf8_lossy(blob_as_bytes(message)).into_owned()).unwrap_or_default();
+            return Err(format!("failed to compile {}: {}\n{}", source_name, e, message));
+        }
+    }
+    Ok(blob.unwrap())
+}
+
+pub fn blob_as_bytes(blob: &ID3DBlob) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    }
+}
+
+pub fn create_vertex_shader(device: &ID3D11Device, cso_file_name: PCWSTR, hlsl_file_name: PCWSTR, entry_point: PCSTR) -> (ID3D11VertexShader, ID3DBlob) {
+    let blob = create_shader_from_file(cso_file_name, hlsl_file_name, entry_point, s!("vs_5_0"));
+    let mut vertex_shader: Option<ID3D11VertexShader> = None;
+    unsafe {
+        device.CreateVertexShader(blob_as_bytes(&blob), None, Some(&mut vertex_shader)).expect("CreateVertexShader failed");
+    }
+    (vertex_shader.unwrap(), blob)
+}
+
+pub fn create_pixel_shader(device: &ID3D11Device, cso_file_name: PCWSTR, hlsl_file_name: PCWSTR, entry_point: PCSTR) -> ID3D11PixelShader {
+    let blob = create_shader_from_file(cso_file_name, hlsl_file_name, entry_point, s!("ps_5_0"));
+    let mut pixel_shader: Option<ID3D11PixelShader> = None;
+    unsafe {
+        device.CreatePixelShader(blob_as_bytes(&blob), None, Some(&mut pixel_shader)).expect("CreatePixelShader failed");
+    }
+    pixel_shader.unwrap()
+}
+
+pub fn create_sampler(device: &ID3D11Device, filter: D3D11_FILTER, address: D3D11_TEXTURE_ADDRESS_MODE) -> ID3D11SamplerState {
+    let sampler_desc = D3D11_SAMPLER_DESC {
+        Filter: filter,
+        AddressU: address,
+        AddressV: address,
+        AddressW: address,
+        MipLODBias: 0.0,
+        MaxAnisotropy: 1,
+        ComparisonFunc: D3D11_COMPARISON_NEVER,
+        BorderColor: [0.0; 4],
+        MinLOD: 0.0,
+        MaxLOD: D3D11_FLOAT32_MAX,
+    };
+    let mut sampler: Option<ID3D11SamplerState> = None;
+    unsafe {
+        device.CreateSamplerState(&sampler_desc, Some(&mut sampler)).expect("CreateSamplerState failed");
+    }
+    sampler.unwrap()
+}
+
+/// 對應 HLSL `cbuffer` 的常數緩衝區，`T` 必須是 `#[repr(C)]` 且大小為 16 的倍數。
+pub struct ConstantBuffer<T: Copy> {
+    pub buffer: ID3D11Buffer,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> ConstantBuffer<T> {
+    pub fn new(device: &ID3D11Device, initial: &T) -> Self {
+        assert_eq!(size_of::<T>() % 16, 0, "constant buffer size must be a multiple of 16 bytes");
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: size_of::<T>() as u32,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_CONSTANT_BUFFER.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let init_data = D3D11_SUBRESOURCE_DATA {
+            pSysMem: initial as *const T as _,
+            SysMemPitch: 0,
+            SysMemSlicePitch: 0,
+        };
+        let mut buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&desc, Some(&init_data), Some(&mut buffer)).expect("CreateBuffer failed");
+        }
+        Self { buffer: buffer.unwrap(), _marker: PhantomData }
+    }
+
+    pub fn update(&self, context: &ID3D11DeviceContext, value: &T) {
+        unsafe {
+            context.UpdateSubresource(&self.buffer, 0, None, value as *const T as _, 0, 0);
+        }
+    }
+}
+
+/// 可同時作為 render target 與 shader resource 的 2D 紋理。
+pub struct RenderTexture {
+    pub texture: ID3D11Texture2D,
+    pub render_target_view: ID3D11RenderTargetView,
+    pub shader_resource_view: ID3D11ShaderResourceView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenderTexture {
+    pub fn new(device: &ID3D11Device, width: u32, height: u32, format: DXGI_FORMAT) -> Self {
+        Self::multisampled(device, width, height, format, DXGI_SAMPLE_DESC { Count: 1, Quality: 0 })
+    }
+
+    /// 建立多重取樣的 render target，shader resource view 的維度會是 `Texture2DMS`。
+    pub fn multisampled(device: &ID3D11Device, width: u32, height: u32, format: DXGI_FORMAT, sample_desc: DXGI_SAMPLE_DESC) -> Self {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width.max(1),
+            Height: height.max(1),
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: format,
+            SampleDesc: sample_desc,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: (D3D11_BIND_RENDER_TARGET.0 | D3D11_BIND_SHADER_RESOURCE.0) as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        let mut texture: Option<ID3D11Texture2D> = None;
+        let mut render_target_view: Option<ID3D11RenderTargetView> = None;
+        let mut shader_resource_view: Option<ID3D11ShaderResourceView> = None;
+        unsafe {
+            device.CreateTexture2D(&desc, None, Some(&mut texture)).expect("CreateTexture2D failed");
+            let texture = texture.as_ref().unwrap();
+            device.CreateRenderTargetView(texture, None, Some(&mut render_target_view)).expect("CreateRenderTargetView failed");
+            device.CreateShaderResourceView(texture, None, Some(&mut shader_resource_view)).expect("CreateShaderResourceView failed");
+        }
+        Self {
+            texture: texture.unwrap(),
+            render_target_view: render_target_view.unwrap(),
+            shader_resource_view: shader_resource_view.unwrap(),
+            width: desc.Width,
+            height: desc.Height,
         }
     }
-    return blob.unwrap();
-}
\ No newline at end of file
+}
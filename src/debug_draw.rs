@@ -0,0 +1,311 @@
+use std::time::Instant;
+use directx_math::*;
+
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const RED: XMFLOAT4 = XMFLOAT4 { x: 1.0, y: 0.0, z: 0.0, w: 1.0 };
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const GREEN: XMFLOAT4 = XMFLOAT4 { x: 0.0, y: 1.0, z: 0.0, w: 1.0 };
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const BLUE: XMFLOAT4 = XMFLOAT4 { x: 0.0, y: 0.0, z: 1.0, w: 1.0 };
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const YELLOW: XMFLOAT4 = XMFLOAT4 { x: 1.0, y: 1.0, z: 0.0, w: 1.0 };
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const WHITE: XMFLOAT4 = XMFLOAT4 { x: 1.0, y: 1.0, z: 1.0, w: 1.0 };
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const GRAY: XMFLOAT4 = XMFLOAT4 { x: 0.5, y: 0.5, z: 0.5, w: 1.0 };
+
+#[cfg_attr(not(windows), allow(dead_code))]
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// 與 `mesh::VertexPosColor` 相同的配置，可共用同一個 input layout。
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct DebugVertex {
+    pub position: XMFLOAT3,
+    pub color: XMFLOAT4,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct DebugOptions {
+    /// 顯示秒數，0 代表只顯示一幀。
+    pub duration: f32,
+    /// 是否被場景中的物體遮擋。
+    pub depth_test: bool,
+}
+
+impl Default for DebugOptions {
+    fn default() -> Self {
+        Self {
+            duration: 0.0,
+            depth_test: true,
+        }
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl DebugOptions {
+    pub fn for_seconds(duration: f32) -> Self {
+        Self { duration, ..Self::default() }
+    }
+
+    pub fn on_top() -> Self {
+        Self { depth_test: false, ..Self::default() }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+struct DebugLine {
+    from: XMFLOAT3,
+    to: XMFLOAT3,
+    color: XMFLOAT4,
+    depth_test: bool,
+    remaining: f32,
+}
+
+/// Immediate-mode 的除錯繪圖，所有圖形都拆成線段累積起來，每幀由 renderer 一次送出。
+#[derive(Debug, Default)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct DebugDraw {
+    lines: Vec<DebugLine>,
+    last_frame: Option<Instant>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn to_float3(v: XMVECTOR) -> XMFLOAT3 {
+    let mut result = XMFLOAT3::default();
+    XMStoreFloat3(&mut result, v);
+    result
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, from: XMFLOAT3, to: XMFLOAT3, color: XMFLOAT4, options: DebugOptions) {
+        self.lines.push(DebugLine {
+            from,
+            to,
+            color,
+            depth_test: options.depth_test,
+            remaining: options.duration,
+        });
+    }
+
+    fn box_edges(&mut self, corners: &[XMFLOAT3; 8], color: XMFLOAT4, options: DebugOptions) {
+        // corners 依 (x, y, z) 的位元排列：bit0 = x, bit1 = y, bit2 = z
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1), (2, 3), (4, 5), (6, 7),
+            (0, 2), (1, 3), (4, 6), (5, 7),
+            (0, 4), (1, 5), (2, 6), (3, 7),
+        ];
+        for (a, b) in EDGES {
+            self.line(corners[a], corners[b], color, options);
+        }
+    }
+
+    pub fn aabb(&mut self, min: XMFLOAT3, max: XMFLOAT3, color: XMFLOAT4, options: DebugOptions) {
+        let corners = std::array::from_fn(|i| XMFLOAT3 {
+            x: if i & 1 == 0 { min.x } else { max.x },
+            y: if i & 2 == 0 { min.y } else { max.y },
+            z: if i & 4 == 0 { min.z } else { max.z },
+        });
+        self.box_edges(&corners, color, options);
+    }
+
+    /// 以 `transform` 轉換的有向包圍盒，`half_extents` 為局部座標的半邊長。
+    pub fn obb(&mut self, half_extents: XMFLOAT3, transform: XMMATRIX, color: XMFLOAT4, options: DebugOptions) {
+        let corners = std::array::from_fn(|i| {
+            let local = XMVectorSet(
+                if i & 1 == 0 { -half_extents.x } else { half_extents.x },
+                if i & 2 == 0 { -half_extents.y } else { half_extents.y },
+                if i & 4 == 0 { -half_extents.z } else { half_extents.z },
+                1.0,
+            );
+            to_float3(XMVector3TransformCoord(local, transform))
+        });
+        self.box_edges(&corners, color, options);
+    }
+
+    /// 以三個互相垂直的圓近似球體。
+    pub fn sphere(&mut self, center: XMFLOAT3, radius: f32, color: XMFLOAT4, options: DebugOptions) {
+        let point = |axis: usize, angle: f32| {
+            let (s, c) = (angle.sin() * radius, angle.cos() * radius);
+            match axis {
+                0 => XMFLOAT3 { x: center.x + c, y: center.y + s, z: center.z },
+                1 => XMFLOAT3 { x: center.x, y: center.y + c, z: center.z + s },
+                _ => XMFLOAT3 { x: center.x + s, y: center.y, z: center.z + c },
+            }
+        };
+        for axis in 0..3 {
+            for i in 0..CIRCLE_SEGMENTS {
+                let a0 = XM_2PI * i as f32 / CIRCLE_SEGMENTS as f32;
+                let a1 = XM_2PI * (i + 1) as f32 / CIRCLE_SEGMENTS as f32;
+                self.line(point(axis, a0), point(axis, a1), color, options);
+            }
+        }
+    }
+
+    /// 畫出 `view_projection` 所定義的視錐，NDC 深度範圍為 Direct3D 的 [0, 1]。
+    pub fn frustum(&mut self, view_projection: XMMATRIX, color: XMFLOAT4, options: DebugOptions) {
+        let inverse = XMMatrixInverse(None, view_projection);
+        let corners = std::array::from_fn(|i| {
+            let ndc = XMVectorSet(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+                1.0,
+            );
+            to_float3(XMVector3TransformCoord(ndc, inverse))
+        });
+        self.box_edges(&corners, color, options);
+    }
+
+    /// 以紅、綠、藍畫出 `transform` 的 X、Y、Z 軸。
+    pub fn axes(&mut self, transform: XMMATRIX, size: f32, options: DebugOptions) {
+        let origin = to_float3(XMVector3TransformCoord(XMVectorSet(0.0, 0.0, 0.0, 1.0), transform));
+        let axis = |x: f32, y: f32, z: f32| to_float3(XMVector3TransformCoord(XMVectorSet(x * size, y * size, z * size, 1.0), transform));
+        self.line(origin, axis(1.0, 0.0, 0.0), RED, options);
+        self.line(origin, axis(0.0, 1.0, 0.0), GREEN, options);
+        self.line(origin, axis(0.0, 0.0, 1.0), BLUE, options);
+    }
+
+    /// 在 XZ 平面上以 `center` 為中心、每邊 `cells` 格的格線。
+    pub fn grid(&mut self, center: XMFLOAT3, cell_size: f32, cells: u32, color: XMFLOAT4, options: DebugOptions) {
+        let half = cell_size * cells as f32 * 0.5;
+        for i in 0..=cells {
+            let offset = -half + cell_size * i as f32;
+            self.line(
+                XMFLOAT3 { x: center.x + offset, y: center.y, z: center.z - half },
+                XMFLOAT3 { x: center.x + offset, y: center.y, z: center.z + half },
+                color,
+                options,
+            );
+            self.line(
+                XMFLOAT3 { x: center.x - half, y: center.y, z: center.z + offset },
+                XMFLOAT3 { x: center.x + half, y: center.y, z: center.z + offset },
+                color,
+                options,
+            );
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 回傳要以 line list 繪製的頂點，依是否做深度測試分開。
+    pub fn vertices(&self, depth_test: bool) -> Vec<DebugVertex> {
+        let mut vertices = Vec::with_capacity(self.lines.len() * 2);
+        for line in self.lines.iter().filter(|line| line.depth_test == depth_test) {
+            vertices.push(DebugVertex { position: line.from, color: line.color });
+            vertices.push(DebugVertex { position: line.to, color: line.color });
+        }
+        vertices
+    }
+
+    /// 每幀繪製後呼叫，移除已經到期的圖形。
+    pub fn end_frame(&mut self) {
+        let now = Instant::now();
+        let elapsed = match self.last_frame {
+            Some(last) => now.duration_since(last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_frame = Some(now);
+        self.advance(elapsed);
+    }
+
+    /// 以 `elapsed` 秒推進所有圖形的剩餘時間，只顯示一幀的圖形一定會被移除。
+    pub fn advance(&mut self, elapsed: f32) {
+        self.lines.retain_mut(|line| {
+            line.remaining -= elapsed;
+            line.remaining > 0.0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: XMFLOAT3 = XMFLOAT3 { x: 0.0, y: 0.0, z: 0.0 };
+    const ONE: XMFLOAT3 = XMFLOAT3 { x: 1.0, y: 1.0, z: 1.0 };
+
+    // XMFLOAT3 / XMFLOAT4 沒有實作 PartialEq
+    fn xyz(v: XMFLOAT3) -> [f32; 3] {
+        [v.x, v.y, v.z]
+    }
+
+    fn rgba(c: XMFLOAT4) -> [f32; 4] {
+        [c.x, c.y, c.z, c.w]
+    }
+
+    #[test]
+    fn single_frame_lines_are_removed_on_the_next_advance() {
+        let mut draw = DebugDraw::new();
+        draw.line(ORIGIN, ONE, RED, DebugOptions::default());
+        draw.line(ORIGIN, ONE, GREEN, DebugOptions::for_seconds(1.0));
+
+        draw.advance(0.0);
+        assert_eq!(draw.lines.len(), 1);
+        assert_eq!(rgba(draw.lines[0].color), rgba(GREEN));
+        draw.advance(0.5);
+        assert_eq!(draw.lines.len(), 1);
+        draw.advance(0.5);
+        assert!(draw.is_empty());
+    }
+
+    #[test]
+    fn vertices_are_split_by_depth_test() {
+        let mut draw = DebugDraw::new();
+        draw.line(ORIGIN, ONE, RED, DebugOptions::default());
+        draw.line(ONE, ORIGIN, BLUE, DebugOptions::on_top());
+        draw.line(ORIGIN, ONE, GREEN, DebugOptions::default());
+
+        let depth_tested = draw.vertices(true);
+        assert_eq!(depth_tested.len(), 4);
+        assert_eq!(xyz(depth_tested[0].position), xyz(ORIGIN));
+        assert_eq!(xyz(depth_tested[1].position), xyz(ONE));
+        assert_eq!(depth_tested.iter().map(|v| rgba(v.color)).collect::<Vec<_>>(), [RED, RED, GREEN, GREEN].map(rgba));
+
+        let on_top = draw.vertices(false);
+        assert_eq!(on_top.len(), 2);
+        assert_eq!([xyz(on_top[0].position), xyz(on_top[1].position)], [xyz(ONE), xyz(ORIGIN)]);
+        assert_eq!(rgba(on_top[0].color), rgba(BLUE));
+    }
+
+    #[test]
+    fn shapes_emit_the_expected_number_of_lines() {
+        let options = DebugOptions::default();
+        let count = |draw_shape: &dyn Fn(&mut DebugDraw)| {
+            let mut draw = DebugDraw::new();
+            draw_shape(&mut draw);
+            draw.lines.len()
+        };
+
+        assert_eq!(count(&|draw| draw.aabb(ORIGIN, ONE, WHITE, options)), 12);
+        assert_eq!(count(&|draw| draw.obb(ONE, XMMatrixIdentity(), WHITE, options)), 12);
+        assert_eq!(count(&|draw| draw.frustum(XMMatrixIdentity(), WHITE, options)), 12);
+        assert_eq!(count(&|draw| draw.sphere(ORIGIN, 1.0, WHITE, options)), 3 * CIRCLE_SEGMENTS);
+        assert_eq!(count(&|draw| draw.axes(XMMatrixIdentity(), 1.0, options)), 3);
+        assert_eq!(count(&|draw| draw.grid(ORIGIN, 1.0, 4, WHITE, options)), 2 * 5);
+        assert_eq!(count(&|draw| draw.grid(ORIGIN, 1.0, 0, WHITE, options)), 2);
+    }
+
+    #[test]
+    fn aabb_corners_come_from_min_and_max() {
+        let mut draw = DebugDraw::new();
+        let min = XMFLOAT3 { x: -1.0, y: -2.0, z: -3.0 };
+        draw.aabb(min, ONE, WHITE, DebugOptions::default());
+        for vertex in draw.vertices(true) {
+            let p = vertex.position;
+            assert!(p.x == min.x || p.x == ONE.x);
+            assert!(p.y == min.y || p.y == ONE.y);
+            assert!(p.z == min.z || p.z == ONE.z);
+        }
+    }
+}
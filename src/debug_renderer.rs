@@ -0,0 +1,153 @@
+use directx_math::*;
+use windows::core::{s, w, BOOL};
+use windows::Win32::Graphics::Direct3D::D3D11_PRIMITIVE_TOPOLOGY_LINELIST;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32B32_FLOAT};
+use crate::d3dutil::{blob_as_bytes, create_pixel_shader, create_vertex_shader, ConstantBuffer};
+use crate::debug_draw::{DebugDraw, DebugVertex};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugConstants {
+    view_projection: XMFLOAT4X4,
+}
+
+/// 把 `DebugDraw` 累積的線段上傳到 dynamic vertex buffer，
+/// 有深度測試與無深度測試的線段各一次 draw call。
+pub struct DebugRenderer {
+    vertex_buffer: ID3D11Buffer,
+    capacity: usize,
+    input_layout: ID3D11InputLayout,
+    vertex_shader: ID3D11VertexShader,
+    pixel_shader: ID3D11PixelShader,
+    constants: ConstantBuffer<DebugConstants>,
+    depth_test_state: ID3D11DepthStencilState,
+    no_depth_state: ID3D11DepthStencilState,
+}
+
+impl DebugRenderer {
+    pub const DEFAULT_CAPACITY: usize = 65536;
+
+    pub fn new(device: &ID3D11Device, capacity: usize) -> Self {
+        let (vertex_shader, vs_blob) = create_vertex_shader(device, w!("hlsl/debug_line_vs.cso"), w!("hlsl/debug_line_vs.hlsl"), s!("VS"));
+        let pixel_shader = create_pixel_shader(device, w!("hlsl/debug_line_ps.cso"), w!("hlsl/debug_line_ps.hlsl"), s!("PS"));
+
+        let input_layout_desc = [
+            D3D11_INPUT_ELEMENT_DESC {
+                SemanticName: s!("POSITION"),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32B32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: 0,
+                InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+            D3D11_INPUT_ELEMENT_DESC {
+                SemanticName: s!("COLOR"),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: 12,
+                InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+        ];
+        let mut input_layout: Option<ID3D11InputLayout> = None;
+        unsafe {
+            device.CreateInputLayout(&input_layout_desc, blob_as_bytes(&vs_blob), Some(&mut input_layout)).expect("CreateInputLayout failed");
+        }
+
+        // 線段數為偶數個頂點，容量也保持偶數
+        let capacity = capacity & !1;
+        let buffer_desc = D3D11_BUFFER_DESC {
+            ByteWidth: (capacity * size_of::<DebugVertex>()) as u32,
+            Usage: D3D11_USAGE_DYNAMIC,
+            BindFlags: D3D11_BIND_VERTEX_BUFFER.0 as u32,
+            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0 as u32,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let mut vertex_buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&buffer_desc, None, Some(&mut vertex_buffer)).expect("CreateBuffer failed");
+        }
+
+        Self {
+            vertex_buffer: vertex_buffer.unwrap(),
+            capacity,
+            input_layout: input_layout.unwrap(),
+            vertex_shader,
+            pixel_shader,
+            constants: ConstantBuffer::new(device, &DebugConstants { view_projection: XMFLOAT4X4::default() }),
+            depth_test_state: Self::create_depth_state(device, true),
+            no_depth_state: Self::create_depth_state(device, false),
+        }
+    }
+
+    // 除錯線段只讀不寫深度，避免遮住之後畫的東西
+    fn create_depth_state(device: &ID3D11Device, depth_test: bool) -> ID3D11DepthStencilState {
+        let desc = D3D11_DEPTH_STENCIL_DESC {
+            DepthEnable: BOOL::from(depth_test),
+            DepthWriteMask: D3D11_DEPTH_WRITE_MASK_ZERO,
+            DepthFunc: D3D11_COMPARISON_LESS_EQUAL,
+            StencilEnable: BOOL::from(false),
+            ..Default::default()
+        };
+        let mut state: Option<ID3D11DepthStencilState> = None;
+        unsafe {
+            device.CreateDepthStencilState(&desc, Some(&mut state)).expect("CreateDepthStencilState failed");
+        }
+        state.unwrap()
+    }
+
+    /// 在新的裝置上以相同容量重建，累積中的線段放在 `DebugDraw`，不受影響。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        *self = Self::new(device, self.capacity);
+    }
+
+    /// 畫出 `debug_draw` 目前所有的線段，呼叫前場景的 render target 與 depth buffer 必須已綁定。
+    pub fn flush(&self, context: &ID3D11DeviceContext, debug_draw: &DebugDraw, view_projection: XMMATRIX) {
+        if debug_draw.is_empty() {
+            return;
+        }
+
+        let mut constants = DebugConstants { view_projection: XMFLOAT4X4::default() };
+        XMStoreFloat4x4(&mut constants.view_projection, XMMatrixTranspose(view_projection));
+        self.constants.update(context, &constants);
+
+        let stride = size_of::<DebugVertex>() as u32;
+        let offset = 0_u32;
+        unsafe {
+            context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_LINELIST);
+            context.IASetInputLayout(&self.input_layout);
+            context.IASetVertexBuffers(0, 1, Some(&Some(self.vertex_buffer.clone())), Some(&stride), Some(&offset));
+            context.VSSetShader(&self.vertex_shader, None);
+            context.VSSetConstantBuffers(0, Some(&[Some(self.constants.buffer.clone())]));
+            context.PSSetShader(&self.pixel_shader, None);
+        }
+
+        for (depth_test, state) in [(true, &self.depth_test_state), (false, &self.no_depth_state)] {
+            let vertices = debug_draw.vertices(depth_test);
+            if vertices.is_empty() {
+                continue;
+            }
+            unsafe {
+                context.OMSetDepthStencilState(state, 0);
+            }
+            // 超過 buffer 容量時才會分成多次 draw call
+            for chunk in vertices.chunks(self.capacity) {
+                unsafe {
+                    let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+                    context.Map(&self.vertex_buffer, 0, D3D11_MAP_WRITE_DISCARD, 0, Some(&mut mapped)).expect("Map debug vertex buffer failed");
+                    std::ptr::copy_nonoverlapping(chunk.as_ptr(), mapped.pData as *mut DebugVertex, chunk.len());
+                    context.Unmap(&self.vertex_buffer, 0);
+                    context.Draw(chunk.len() as u32, 0);
+                }
+            }
+        }
+
+        unsafe {
+            context.OMSetDepthStencilState(None, 0);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[cfg_attr(not(windows), allow(dead_code))]
+const DXGI_ERROR_INVALID_CALL: u32 = 0x887A0001;
+#[cfg_attr(not(windows), allow(dead_code))]
+const DXGI_ERROR_DEVICE_REMOVED: u32 = 0x887A0005;
+#[cfg_attr(not(windows), allow(dead_code))]
+const DXGI_ERROR_DEVICE_HUNG: u32 = 0x887A0006;
+#[cfg_attr(not(windows), allow(dead_code))]
+const DXGI_ERROR_DEVICE_RESET: u32 = 0x887A0007;
+#[cfg_attr(not(windows), allow(dead_code))]
+const DXGI_ERROR_DRIVER_INTERNAL_ERROR: u32 = 0x887A0020;
+
+/// 判斷 `Present` 等呼叫回傳的 HRESULT 是否表示裝置已經失效，需要重建。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn is_device_lost(hresult: i32) -> bool {
+    matches!(hresult as u32, DXGI_ERROR_DEVICE_REMOVED | DXGI_ERROR_DEVICE_RESET)
+}
+
+/// `GetDeviceRemovedReason` 回報的原因。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum DeviceLostReason {
+    /// GPU 執行的命令太久或格式錯誤，被系統判定為卡住。
+    Hung,
+    /// 顯示卡被移除、停用或驅動程式更新。
+    Removed,
+    /// 其他程式的錯誤命令造成 GPU 重設。
+    Reset,
+    DriverInternalError,
+    /// 應用程式傳入錯誤的參數，重建裝置通常無法解決。
+    InvalidCall,
+    Unknown(i32),
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl DeviceLostReason {
+    /// `S_OK` 表示裝置仍然有效，回傳 `None`。
+    pub fn from_hresult(hresult: i32) -> Option<DeviceLostReason> {
+        Some(match hresult as u32 {
+            0 => return None,
+            DXGI_ERROR_DEVICE_HUNG => DeviceLostReason::Hung,
+            DXGI_ERROR_DEVICE_REMOVED => DeviceLostReason::Removed,
+            DXGI_ERROR_DEVICE_RESET => DeviceLostReason::Reset,
+            DXGI_ERROR_DRIVER_INTERNAL_ERROR => DeviceLostReason::DriverInternalError,
+            DXGI_ERROR_INVALID_CALL => DeviceLostReason::InvalidCall,
+            _ => DeviceLostReason::Unknown(hresult),
+        })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            DeviceLostReason::Hung => "the GPU stopped responding",
+            DeviceLostReason::Removed => "the display adapter was removed or its driver was updated",
+            DeviceLostReason::Reset => "the GPU was reset because of a badly formed command",
+            DeviceLostReason::DriverInternalError => "the driver encountered an internal error",
+            DeviceLostReason::InvalidCall => "the application made an invalid call",
+            DeviceLostReason::Unknown(_) => "unknown reason",
+        }
+    }
+}
+
+impl fmt::Display for DeviceLostReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceLostReason::Unknown(hresult) => write!(f, "{} (0x{:08X})", self.description(), *hresult as u32),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+/// 偵測到裝置失效時的資訊：發現錯誤的呼叫回傳的 HRESULT 與裝置回報的原因。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct DeviceLost {
+    pub hresult: i32,
+    pub reason: DeviceLostReason,
+}
+
+impl fmt::Display for DeviceLost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "device lost (0x{:08X}): {}", self.hresult as u32, self.reason)
+    }
+}
+
+/// 限制短時間內重建裝置的次數。驅動程式一直無法恢復時，
+/// 不斷重建只會讓程式卡在迴圈裡，超過次數就應該放棄並回報錯誤。
+#[derive(Debug, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct RecoveryBudget {
+    max_attempts: usize,
+    window: Duration,
+    attempts: VecDeque<Instant>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl RecoveryBudget {
+    pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+
+    pub fn new(max_attempts: usize, window: Duration) -> Self {
+        Self { max_attempts, window, attempts: VecDeque::new() }
+    }
+
+    /// 記錄一次重建，`window` 內的次數已達上限時回傳 `false`。
+    pub fn try_attempt(&mut self, now: Instant) -> bool {
+        while let Some(first) = self.attempts.front() && now.duration_since(*first) >= self.window {
+            self.attempts.pop_front();
+        }
+        if self.attempts.len() >= self.max_attempts {
+            return false;
+        }
+        self.attempts.push_back(now);
+        true
+    }
+}
+
+impl Default for RecoveryBudget {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ATTEMPTS, Self::DEFAULT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_dxgi_errors_to_reasons() {
+        let codes = [
+            (DXGI_ERROR_DEVICE_HUNG, DeviceLostReason::Hung),
+            (DXGI_ERROR_DEVICE_REMOVED, DeviceLostReason::Removed),
+            (DXGI_ERROR_DEVICE_RESET, DeviceLostReason::Reset),
+            (DXGI_ERROR_DRIVER_INTERNAL_ERROR, DeviceLostReason::DriverInternalError),
+            (DXGI_ERROR_INVALID_CALL, DeviceLostReason::InvalidCall),
+        ];
+        for (code, reason) in codes {
+            assert_eq!(DeviceLostReason::from_hresult(code as i32), Some(reason));
+        }
+        assert_eq!(DeviceLostReason::from_hresult(0), None);
+        // E_OUTOFMEMORY
+        assert_eq!(DeviceLostReason::from_hresult(0x8007000E_u32 as i32), Some(DeviceLostReason::Unknown(0x8007000E_u32 as i32)));
+        assert_eq!(DeviceLostReason::from_hresult(1), Some(DeviceLostReason::Unknown(1)));
+    }
+
+    #[test]
+    fn only_removed_and_reset_are_device_loss() {
+        assert!(is_device_lost(DXGI_ERROR_DEVICE_REMOVED as i32));
+        assert!(is_device_lost(DXGI_ERROR_DEVICE_RESET as i32));
+        for code in [0, DXGI_ERROR_DEVICE_HUNG, DXGI_ERROR_DRIVER_INTERNAL_ERROR, DXGI_ERROR_INVALID_CALL, 0x80004005] {
+            assert!(!is_device_lost(code as i32), "0x{:08X}", code);
+        }
+    }
+
+    #[test]
+    fn formats_unknown_reasons_with_the_code() {
+        let lost = DeviceLost { hresult: DXGI_ERROR_DEVICE_REMOVED as i32, reason: DeviceLostReason::Unknown(0x80004005_u32 as i32) };
+        assert_eq!(lost.to_string(), "device lost (0x887A0005): unknown reason (0x80004005)");
+        assert_eq!(DeviceLostReason::Hung.to_string(), "the GPU stopped responding");
+    }
+
+    #[test]
+    fn budget_is_spent_within_the_window() {
+        let window = Duration::from_secs(30);
+        let mut budget = RecoveryBudget::new(3, window);
+        let start = Instant::now();
+        assert!(budget.try_attempt(start));
+        assert!(budget.try_attempt(start + Duration::from_secs(10)));
+        assert!(budget.try_attempt(start + Duration::from_secs(20)));
+        assert!(!budget.try_attempt(start + Duration::from_secs(29)));
+        // 失敗的嘗試不會佔用次數，第一次嘗試滿 window 後空出一次
+        assert!(budget.try_attempt(start + window));
+        assert!(!budget.try_attempt(start + Duration::from_secs(35)));
+        assert!(budget.try_attempt(start + Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn budget_refills_after_the_window() {
+        let window = Duration::from_secs(30);
+        let mut budget = RecoveryBudget::new(2, window);
+        let start = Instant::now();
+        assert!(budget.try_attempt(start));
+        assert!(budget.try_attempt(start));
+        assert!(!budget.try_attempt(start + Duration::from_secs(1)));
+
+        let later = start + window * 2;
+        assert!(budget.try_attempt(later));
+        assert!(budget.try_attempt(later));
+        assert!(!budget.try_attempt(later));
+    }
+
+    #[test]
+    fn zero_attempts_never_recovers() {
+        let mut budget = RecoveryBudget::new(0, Duration::from_secs(1));
+        assert!(!budget.try_attempt(Instant::now()));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::Deserialize;
+
+/// 視窗的顯示方式。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// 沒有邊框、覆蓋整個螢幕的視窗，切換快且不影響其他程式。
+    Borderless,
+    /// 獨占全螢幕，可以切換顯示器的解析度與更新率。
+    Fullscreen,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [WindowMode::Windowed, WindowMode::Borderless, WindowMode::Fullscreen];
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn is_fullscreen(&self) -> bool {
+        *self != WindowMode::Windowed
+    }
+
+    /// Alt+Enter 的切換：視窗模式切到 `fullscreen_mode`，其他模式回到視窗模式。
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn toggled(&self, fullscreen_mode: WindowMode) -> WindowMode {
+        if self.is_fullscreen() { WindowMode::Windowed } else { fullscreen_mode }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Fullscreen => "fullscreen",
+        }
+    }
+}
+
+impl fmt::Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WindowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WindowMode::ALL.into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown window mode \"{}\", expected windowed, borderless or fullscreen", s))
+    }
+}
+
+/// 顯示器支援的一種解析度與更新率。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_numerator: u32,
+    pub refresh_denominator: u32,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl DisplayMode {
+    pub fn refresh_rate(&self) -> f64 {
+        if self.refresh_denominator == 0 {
+            return 0.0;
+        }
+        self.refresh_numerator as f64 / self.refresh_denominator as f64
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} @ {:.2}Hz", self.width, self.height, self.refresh_rate())
+    }
+}
+
+/// 找出最接近 `width` x `height` 的模式：先比解析度差距，相同時取更新率最高的。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn closest_mode(modes: &[DisplayMode], width: u32, height: u32) -> Option<DisplayMode> {
+    let distance = |mode: &DisplayMode| mode.width.abs_diff(width) as u64 + mode.height.abs_diff(height) as u64;
+    modes.iter()
+        .min_by(|a, b| distance(a).cmp(&distance(b)).then(b.refresh_rate().total_cmp(&a.refresh_rate())))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(width: u32, height: u32, refresh_rate: u32) -> DisplayMode {
+        DisplayMode { width, height, refresh_numerator: refresh_rate * 1000, refresh_denominator: 1000 }
+    }
+
+    #[test]
+    fn finds_the_closest_resolution() {
+        let modes = [mode(1280, 720, 60), mode(1920, 1080, 60), mode(2560, 1440, 144)];
+        assert_eq!(closest_mode(&modes, 1920, 1080), Some(modes[1]));
+        assert_eq!(closest_mode(&modes, 1900, 1000), Some(modes[1]));
+        assert_eq!(closest_mode(&modes, 800, 600), Some(modes[0]));
+        assert_eq!(closest_mode(&modes, 3840, 2160), Some(modes[2]));
+    }
+
+    #[test]
+    fn ties_prefer_the_highest_refresh_rate() {
+        let modes = [mode(1920, 1080, 60), mode(1920, 1080, 144), mode(1920, 1080, 120), mode(2560, 1440, 240)];
+        assert_eq!(closest_mode(&modes, 1920, 1080), Some(modes[1]));
+        // 解析度差距相同時也比較更新率
+        let modes = [mode(1900, 1080, 60), mode(1940, 1080, 75)];
+        assert_eq!(closest_mode(&modes, 1920, 1080), Some(modes[1]));
+    }
+
+    #[test]
+    fn no_modes_gives_none() {
+        assert_eq!(closest_mode(&[], 1920, 1080), None);
+    }
+
+    #[test]
+    fn refresh_rate_handles_a_zero_denominator() {
+        assert_eq!(DisplayMode { width: 1920, height: 1080, refresh_numerator: 60, refresh_denominator: 0 }.refresh_rate(), 0.0);
+        let ntsc = DisplayMode { width: 1920, height: 1080, refresh_numerator: 60000, refresh_denominator: 1001 };
+        assert!((ntsc.refresh_rate() - 59.94).abs() < 0.01);
+        assert_eq!(ntsc.to_string(), "1920x1080 @ 59.94Hz");
+    }
+
+    #[test]
+    fn toggles_between_windowed_and_fullscreen() {
+        assert_eq!(WindowMode::Windowed.toggled(WindowMode::Borderless), WindowMode::Borderless);
+        assert_eq!(WindowMode::Windowed.toggled(WindowMode::Fullscreen), WindowMode::Fullscreen);
+        for fullscreen_mode in [WindowMode::Borderless, WindowMode::Fullscreen] {
+            assert_eq!(WindowMode::Borderless.toggled(fullscreen_mode), WindowMode::Windowed);
+            assert_eq!(WindowMode::Fullscreen.toggled(fullscreen_mode), WindowMode::Windowed);
+        }
+    }
+
+    #[test]
+    fn parses_window_modes() {
+        for mode in WindowMode::ALL {
+            assert_eq!(mode.name().parse(), Ok(mode));
+        }
+        assert_eq!("Borderless".parse(), Ok(WindowMode::Borderless));
+        assert!("maximized".parse::<WindowMode>().is_err());
+        assert!(!WindowMode::Windowed.is_fullscreen());
+        assert!(WindowMode::Borderless.is_fullscreen());
+    }
+}
@@ -0,0 +1,225 @@
+/// 可以放進 `EffectChain` 的效果，以名稱查詢與開關。
+pub trait ChainEffect {
+    fn name(&self) -> &'static str;
+}
+
+/// 一個 pass 讀取的紋理。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum PassInput {
+    /// 場景渲染出的原始目標
+    Scene,
+    /// 第 n 張輪流使用的中間目標
+    Target(usize),
+}
+
+/// 一個啟用效果的輸入與輸出，`output` 是中間目標的索引。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Pass<'a, T> {
+    pub effect: &'a T,
+    pub input: PassInput,
+    pub output: usize,
+}
+
+/// 中間目標的數量，效果之間輪流讀寫這兩張紋理。
+pub const TARGET_COUNT: usize = 2;
+
+struct EffectSlot<T> {
+    effect: T,
+    enabled: bool,
+}
+
+/// 後處理效果的順序與開關，不涉及任何 GPU 資源。
+pub struct EffectChain<T> {
+    slots: Vec<EffectSlot<T>>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl<T: ChainEffect> EffectChain<T> {
+    pub fn new() -> Self {
+        Self { slots: vec![] }
+    }
+
+    pub fn push(&mut self, effect: T) {
+        self.slots.push(EffectSlot { effect, enabled: true });
+    }
+
+    // 示範程式只用數字鍵開關效果，插入、移除與調整順序目前只有測試使用
+    #[allow(dead_code)]
+    pub fn insert(&mut self, index: usize, effect: T) {
+        let index = index.min(self.slots.len());
+        self.slots.insert(index, EffectSlot { effect, enabled: true });
+    }
+
+    #[allow(dead_code)]
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        let index = self.index_of(name)?;
+        Some(self.slots.remove(index).effect)
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.effect.name() == name)
+    }
+
+    /// 依目前順序回傳 (效果名稱, 是否啟用)。
+    pub fn entries(&self) -> Vec<(&'static str, bool)> {
+        self.slots.iter().map(|slot| (slot.effect.name(), slot.enabled)).collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.index_of(name) {
+            Some(index) => {
+                self.slots[index].enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let index = self.index_of(name)?;
+        let slot = &mut self.slots[index];
+        slot.enabled = !slot.enabled;
+        Some(slot.enabled)
+    }
+
+    /// 把效果移到 `new_index`，其餘效果保持相對順序；超出範圍時移到最後。
+    #[allow(dead_code)]
+    pub fn move_effect(&mut self, name: &str, new_index: usize) -> bool {
+        match self.index_of(name) {
+            Some(index) => {
+                let slot = self.slots.remove(index);
+                let new_index = new_index.min(self.slots.len());
+                self.slots.insert(new_index, slot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 依順序走訪所有效果，不論是否啟用。
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().map(|slot| &mut slot.effect)
+    }
+
+    /// 啟用的效果依序從場景目標開始，輪流寫入兩張中間目標，每個 pass 讀取上一個 pass 的輸出。
+    pub fn passes(&self) -> Vec<Pass<'_, T>> {
+        let mut input = PassInput::Scene;
+        let mut passes = vec![];
+        for (i, slot) in self.slots.iter().filter(|slot| slot.enabled).enumerate() {
+            let output = i % TARGET_COUNT;
+            passes.push(Pass { effect: &slot.effect, input, output });
+            input = PassInput::Target(output);
+        }
+        passes
+    }
+
+    /// 輸出 pass 讀取的紋理：最後一個啟用效果的輸出，沒有啟用的效果時直接讀場景目標。
+    pub fn output_input(&self) -> PassInput {
+        match self.slots.iter().filter(|slot| slot.enabled).count() {
+            0 => PassInput::Scene,
+            count => PassInput::Target((count - 1) % TARGET_COUNT),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    impl ChainEffect for Named {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+    }
+
+    fn chain(names: &[&'static str]) -> EffectChain<Named> {
+        let mut chain = EffectChain::new();
+        for name in names {
+            chain.push(Named(name));
+        }
+        chain
+    }
+
+    fn names(chain: &EffectChain<Named>) -> Vec<&'static str> {
+        chain.entries().into_iter().map(|(name, _)| name).collect()
+    }
+
+    fn schedule(chain: &EffectChain<Named>) -> Vec<(&'static str, PassInput, usize)> {
+        chain.passes().into_iter().map(|pass| (pass.effect.name(), pass.input, pass.output)).collect()
+    }
+
+    #[test]
+    fn insert_clamps_the_index_to_the_end() {
+        let mut chain = chain(&["bloom", "fxaa"]);
+        chain.insert(1, Named("vignette"));
+        chain.insert(99, Named("grain"));
+        assert_eq!(names(&chain), ["bloom", "vignette", "fxaa", "grain"]);
+        assert!(chain.entries().iter().all(|(_, enabled)| *enabled));
+    }
+
+    #[test]
+    fn remove_returns_the_effect_by_name() {
+        let mut chain = chain(&["bloom", "fxaa"]);
+        assert_eq!(chain.remove("bloom").map(|effect| effect.name()), Some("bloom"));
+        assert!(chain.remove("bloom").is_none());
+        assert_eq!(names(&chain), ["fxaa"]);
+    }
+
+    #[test]
+    fn toggle_and_set_enabled_report_unknown_names() {
+        let mut chain = chain(&["bloom", "fxaa"]);
+        assert_eq!(chain.toggle("bloom"), Some(false));
+        assert_eq!(chain.toggle("bloom"), Some(true));
+        assert_eq!(chain.toggle("missing"), None);
+        assert!(chain.set_enabled("fxaa", false));
+        assert!(!chain.set_enabled("missing", false));
+        assert_eq!(chain.entries(), [("bloom", true), ("fxaa", false)]);
+    }
+
+    #[test]
+    fn move_effect_keeps_the_relative_order_of_the_rest() {
+        let mut chain = chain(&["a", "b", "c", "d"]);
+        assert!(chain.move_effect("d", 1));
+        assert_eq!(names(&chain), ["a", "d", "b", "c"]);
+        assert!(chain.move_effect("a", 2));
+        assert_eq!(names(&chain), ["d", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn move_effect_out_of_range_moves_to_the_end() {
+        let mut chain = chain(&["a", "b", "c"]);
+        assert!(chain.move_effect("a", 99));
+        assert_eq!(names(&chain), ["b", "c", "a"]);
+        assert!(!chain.move_effect("missing", 0));
+        assert_eq!(names(&chain), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn passes_alternate_between_the_two_targets() {
+        let mut chain = chain(&["a", "b", "c", "d"]);
+        chain.toggle("b");
+        assert_eq!(schedule(&chain), [
+            ("a", PassInput::Scene, 0),
+            ("c", PassInput::Target(0), 1),
+            ("d", PassInput::Target(1), 0),
+        ]);
+        assert_eq!(chain.output_input(), PassInput::Target(0));
+    }
+
+    #[test]
+    fn output_reads_the_scene_when_no_effect_is_enabled() {
+        let mut chain = chain(&["a", "b"]);
+        chain.toggle("a");
+        assert_eq!(chain.output_input(), PassInput::Target(0));
+        chain.remove("b");
+        assert!(chain.passes().is_empty());
+        assert_eq!(chain.output_input(), PassInput::Scene);
+
+        let empty = EffectChain::<Named>::new();
+        assert_eq!(empty.output_input(), PassInput::Scene);
+    }
+}
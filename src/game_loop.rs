@@ -0,0 +1,283 @@
+use std::time::{Duration, Instant};
+
+/// 單調遞增的時間來源，排程邏輯只透過它取得時間，測試時可以換成手動推進的時鐘。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub trait Clock {
+    /// 從某個固定起點開始經過的時間。
+    fn now(&self) -> Duration;
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct SystemClock {
+    start: Instant,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// 傳給 `Game::update` 的固定步長時間。
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct UpdateTime {
+    pub step: Duration,
+    /// 這次 update 結束時的模擬時間。
+    pub total: Duration,
+    pub update_index: u64,
+}
+
+/// 傳給 `Game::render` 的每幀時間。
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct FrameTime {
+    /// 與上一幀之間的實際時間（已套用 `max_frame_time` 上限）。
+    pub delta: Duration,
+    /// 從迴圈開始到這一幀經過的實際時間。
+    pub total: Duration,
+    pub frame_index: u64,
+    /// 介於上一個與下一個 update 狀態之間的插值比例，範圍 [0, 1)。
+    pub interpolation: f32,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+pub trait Game {
+    fn update(&mut self, time: &UpdateTime);
+    fn render(&mut self, time: &FrameTime);
+
+    /// 使用者要求關閉視窗時呼叫，回傳 `false` 可以取消關閉。
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+}
+
+/// `FixedTimestep::advance` 的結果：本幀要跑幾次 update，以及 render 用的時間。
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Tick {
+    pub updates: u32,
+    pub frame: FrameTime,
+}
+
+/// 固定步長排程：累積實際經過的時間，每滿一個 `step` 就跑一次 update，
+/// 剩下不足一步的部分換算成 render 的插值比例。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct FixedTimestep<C: Clock> {
+    clock: C,
+    step: Duration,
+    max_frame_time: Duration,
+    accumulator: Duration,
+    last: Option<Duration>,
+    total: Duration,
+    simulated: Duration,
+    frame_index: u64,
+    update_index: u64,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl<C: Clock> FixedTimestep<C> {
+    pub fn new(clock: C, step: Duration) -> Self {
+        assert!(!step.is_zero(), "fixed timestep must be greater than zero");
+        Self {
+            clock,
+            step,
+            // 卡頓（例如拖曳視窗）之後最多補 250ms，避免 update 追不上而越積越多
+            max_frame_time: Duration::from_millis(250),
+            accumulator: Duration::ZERO,
+            last: None,
+            total: Duration::ZERO,
+            simulated: Duration::ZERO,
+            frame_index: 0,
+            update_index: 0,
+        }
+    }
+
+    // 示範程式沿用預設的 250ms，目前只有測試調整
+    #[allow(dead_code)]
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> Self {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    /// 讀取時鐘並計算本幀的排程，第一次呼叫的 delta 為 0。
+    pub fn advance(&mut self) -> Tick {
+        let now = self.clock.now();
+        let delta = match self.last {
+            Some(last) => now.saturating_sub(last).min(self.max_frame_time),
+            None => Duration::ZERO,
+        };
+        self.last = Some(now);
+        self.total += delta;
+        self.accumulator += delta;
+
+        let mut updates = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            updates += 1;
+        }
+
+        let frame = FrameTime {
+            delta,
+            total: self.total,
+            frame_index: self.frame_index,
+            interpolation: self.accumulator.as_secs_f32() / self.step.as_secs_f32(),
+        };
+        self.frame_index += 1;
+        Tick { updates, frame }
+    }
+
+    /// 跑一幀：依排程呼叫 `update` 零到多次，再呼叫一次 `render`。
+    pub fn run_frame<G: Game>(&mut self, game: &mut G) -> Tick {
+        let tick = self.advance();
+        for _ in 0..tick.updates {
+            self.simulated += self.step;
+            let time = UpdateTime {
+                step: self.step,
+                total: self.simulated,
+                update_index: self.update_index,
+            };
+            self.update_index += 1;
+            game.update(&time);
+        }
+        game.render(&tick.frame);
+        tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// 手動推進的時鐘，clone 出來的實例共用同一個時間。
+    #[derive(Clone, Default)]
+    struct ManualClock {
+        now: Rc<Cell<Duration>>,
+    }
+
+    impl ManualClock {
+        fn new() -> Self {
+            Self::default()
+        }
+
+        fn advance(&self, elapsed: Duration) {
+            self.now.set(self.now.get() + elapsed);
+        }
+
+        fn set(&self, now: Duration) {
+            self.now.set(now);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+    }
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[derive(Default)]
+    struct RecordingGame {
+        updates: Vec<UpdateTime>,
+        frames: Vec<FrameTime>,
+    }
+
+    impl Game for RecordingGame {
+        fn update(&mut self, time: &UpdateTime) {
+            self.updates.push(*time);
+        }
+
+        fn render(&mut self, time: &FrameTime) {
+            self.frames.push(*time);
+        }
+    }
+
+    fn timestep() -> (ManualClock, FixedTimestep<ManualClock>) {
+        let clock = ManualClock::new();
+        (clock.clone(), FixedTimestep::new(clock, STEP))
+    }
+
+    #[test]
+    fn first_frame_has_no_updates() {
+        let (clock, mut timestep) = timestep();
+        clock.advance(Duration::from_secs(1));
+        let tick = timestep.advance();
+        assert_eq!(tick.updates, 0);
+        assert_eq!(tick.frame.delta, Duration::ZERO);
+        assert_eq!(tick.frame.frame_index, 0);
+    }
+
+    #[test]
+    fn runs_one_update_per_elapsed_step() {
+        let (clock, mut timestep) = timestep();
+        timestep.advance();
+        for (elapsed_ms, expected) in [(10, 1), (35, 3), (4, 0), (1, 1), (0, 0)] {
+            clock.advance(Duration::from_millis(elapsed_ms));
+            assert_eq!(timestep.advance().updates, expected, "after {} ms", elapsed_ms);
+        }
+    }
+
+    #[test]
+    fn run_frame_calls_update_then_render() {
+        let (clock, mut timestep) = timestep();
+        let mut game = RecordingGame::default();
+        timestep.run_frame(&mut game);
+        clock.advance(Duration::from_millis(30));
+        timestep.run_frame(&mut game);
+
+        assert_eq!(game.frames.len(), 2);
+        let indices: Vec<u64> = game.updates.iter().map(|time| time.update_index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(game.updates[2].total, Duration::from_millis(30));
+        assert!(game.updates.iter().all(|time| time.step == STEP));
+    }
+
+    #[test]
+    fn clamps_long_frames_to_max_frame_time() {
+        let clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(clock.clone(), STEP).with_max_frame_time(Duration::from_millis(50));
+        timestep.advance();
+        clock.advance(Duration::from_secs(5));
+        let tick = timestep.advance();
+        assert_eq!(tick.frame.delta, Duration::from_millis(50));
+        assert_eq!(tick.frame.total, Duration::from_millis(50));
+        assert_eq!(tick.updates, 5);
+    }
+
+    #[test]
+    fn interpolation_is_the_fraction_of_a_pending_step() {
+        let (clock, mut timestep) = timestep();
+        timestep.advance();
+        clock.advance(Duration::from_millis(25));
+        let tick = timestep.advance();
+        assert_eq!(tick.updates, 2);
+        assert!((tick.frame.interpolation - 0.5).abs() < 1e-6);
+
+        clock.advance(Duration::from_millis(2));
+        let tick = timestep.advance();
+        assert_eq!(tick.updates, 0);
+        assert!((tick.frame.interpolation - 0.7).abs() < 1e-6);
+        assert!((0.0..1.0).contains(&tick.frame.interpolation));
+    }
+
+    #[test]
+    fn clock_going_backwards_is_treated_as_no_time() {
+        let (clock, mut timestep) = timestep();
+        clock.set(Duration::from_secs(1));
+        timestep.advance();
+        clock.set(Duration::from_millis(500));
+        let tick = timestep.advance();
+        assert_eq!(tick.frame.delta, Duration::ZERO);
+        assert_eq!(tick.updates, 0);
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 指向 `SlotMap<T>` 中一個元素的 handle。
+/// 元素被移除後 slot 的世代會增加，舊的 handle 就不會再對應到之後放進同一個 slot 的元素。
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    // 除錯輸出直接讀欄位，目前只有測試透過這兩個方法檢查
+    #[allow(dead_code)]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    #[allow(dead_code)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    // 只取型別名稱的最後一段，例如 `GpuBuffer`
+    fn type_name() -> &'static str {
+        let name = std::any::type_name::<T>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+// derive 會要求 T 也實作這些 trait，handle 只是兩個整數，所以手動實作
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({}v{})", Self::type_name(), self.index, self.generation)
+    }
+}
+
+impl<T> fmt::Display for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} #{} (generation {})", Self::type_name(), self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// `SlotMap` 的使用統計。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct SlotStats {
+    /// 目前存在的元素數量。
+    pub live: usize,
+    /// 已配置的 slot 數量，包含空的 slot。
+    pub slots: usize,
+    pub created: u64,
+    pub destroyed: u64,
+    /// 以已移除元素的 handle 查詢或移除的次數，不為 0 表示有地方保留了過期的 handle。
+    pub stale_lookups: u64,
+}
+
+impl fmt::Display for SlotStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} live / {} slots, {} created, {} destroyed, {} stale lookups",
+            self.live, self.slots, self.created, self.destroyed, self.stale_lookups,
+        )
+    }
+}
+
+/// 以世代 handle 存取的容器。插入、移除與查詢都是 O(1)，移除後的 slot 會重複使用。
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+    created: u64,
+    destroyed: u64,
+    // 查詢只需要 `&self`，所以用 atomic 計數
+    stale_lookups: AtomicU64,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl<T> SlotMap<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free: vec![],
+            len: 0,
+            created: 0,
+            destroyed: 0,
+            stale_lookups: AtomicU64::new(0),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        self.created += 1;
+        self.len += 1;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize].value = Some(value);
+                index
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, value: Some(value) });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        Handle { index, generation: self.slots[index as usize].generation, _marker: PhantomData }
+    }
+
+    /// 移除並回傳元素，handle 已過期時回傳 `None`。
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let Some(slot) = self.slots.get_mut(handle.index as usize).filter(|slot| slot.generation == handle.generation && slot.value.is_some()) else {
+            self.stale_lookups.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        let value = slot.value.take();
+        slot.generation = slot.generation.wrapping_add(1);
+        // 世代繞回 0 的 slot 不再使用，避免很久以前的 handle 又變成有效
+        if slot.generation != 0 {
+            self.free.push(handle.index);
+        }
+        self.len -= 1;
+        self.destroyed += 1;
+        value
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        let value = self.slots.get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref());
+        if value.is_none() {
+            self.stale_lookups.fetch_add(1, Ordering::Relaxed);
+        }
+        value
+    }
+
+    // 以下是通用的容器操作，renderer 目前只用到其中一部分
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        let value = self.slots.get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut());
+        if value.is_none() {
+            self.stale_lookups.fetch_add(1, Ordering::Relaxed);
+        }
+        value
+    }
+
+    /// 與 `get` 不同，不會計入過期查詢。
+    #[allow(dead_code)]
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.slots.get(handle.index as usize).is_some_and(|slot| slot.generation == handle.generation && slot.value.is_some())
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = Handle { index: index as u32, generation: slot.generation, _marker: PhantomData };
+            slot.value.as_ref().map(|value| (handle, value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            let handle = Handle { index: index as u32, generation: slot.generation, _marker: PhantomData };
+            slot.value.as_mut().map(|value| (handle, value))
+        })
+    }
+
+    pub fn stats(&self) -> SlotStats {
+        SlotStats {
+            live: self.len,
+            slots: self.slots.len(),
+            created: self.created,
+            destroyed: self.destroyed,
+            stale_lookups: self.stale_lookups.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_handles_are_stale() {
+        let mut map = SlotMap::new();
+        let handle = map.insert("a");
+        assert_eq!(map.get(handle), Some(&"a"));
+        assert_eq!(map.remove(handle), Some("a"));
+        assert_eq!(map.get(handle), None);
+        assert_eq!(map.remove(handle), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn reinsert_reuses_the_slot_with_a_new_generation() {
+        let mut map = SlotMap::new();
+        let old = map.insert(1);
+        let other = map.insert(2);
+        map.remove(old);
+        let new = map.insert(3);
+
+        assert_eq!(new.index(), old.index());
+        assert_eq!(new.generation(), old.generation() + 1);
+        assert_ne!(new, old);
+        assert_eq!(map.get(old), None);
+        assert_eq!(map.get(new), Some(&3));
+        assert_eq!(map.get(other), Some(&2));
+        assert_eq!(map.stats().slots, 2);
+    }
+
+    #[test]
+    fn get_mut_respects_generations() {
+        let mut map = SlotMap::new();
+        let old = map.insert(1);
+        map.remove(old);
+        let new = map.insert(2);
+        assert!(map.get_mut(old).is_none());
+        *map.get_mut(new).unwrap() += 10;
+        assert_eq!(map.get(new), Some(&12));
+    }
+
+    #[test]
+    fn slot_is_retired_when_its_generation_wraps() {
+        let mut map = SlotMap::new();
+        let handle = map.insert("old");
+        map.slots[0].generation = u32::MAX;
+        let last = Handle { index: 0, generation: u32::MAX, _marker: PhantomData };
+        assert_eq!(map.remove(last), Some("old"));
+        assert_eq!(map.slots[0].generation, 0);
+
+        // 繞回 0 後不再使用這個 slot，第一個 handle 不會又變成有效
+        let next = map.insert("new");
+        assert_eq!(next.index(), 1);
+        assert!(!map.contains(handle));
+        assert_eq!(map.get(handle), None);
+        assert_eq!(map.stats().slots, 2);
+    }
+
+    #[test]
+    fn stale_lookups_count_get_but_not_contains() {
+        let mut map = SlotMap::new();
+        let handle = map.insert(1);
+        map.remove(handle);
+        assert_eq!(map.stats().stale_lookups, 0);
+
+        assert!(!map.contains(handle));
+        assert_eq!(map.stats().stale_lookups, 0);
+        map.get(handle);
+        map.get(Handle { index: 99, generation: 0, _marker: PhantomData });
+        assert_eq!(map.stats().stale_lookups, 2);
+        map.get_mut(handle);
+        map.remove(handle);
+        assert_eq!(map.stats().stale_lookups, 4);
+    }
+
+    #[test]
+    fn iter_skips_empty_slots() {
+        let mut map = SlotMap::new();
+        let handles: Vec<_> = (0..4).map(|value| map.insert(value)).collect();
+        map.remove(handles[0]);
+        map.remove(handles[2]);
+
+        let live: Vec<_> = map.iter().map(|(handle, value)| (handle, *value)).collect();
+        assert_eq!(live, [(handles[1], 1), (handles[3], 3)]);
+        for (_, value) in map.iter_mut() {
+            *value *= 10;
+        }
+        assert_eq!(map.get(handles[3]), Some(&30));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn stats_track_creation_and_destruction() {
+        let mut map = SlotMap::new();
+        let a = map.insert(());
+        map.insert(());
+        map.remove(a);
+        map.insert(());
+        assert_eq!(map.stats(), SlotStats { live: 2, slots: 2, created: 3, destroyed: 1, stale_lookups: 0 });
+    }
+
+    #[test]
+    fn handle_formatting_uses_the_short_type_name() {
+        let mut map: SlotMap<String> = SlotMap::new();
+        let handle = map.insert(String::new());
+        assert_eq!(format!("{:?}", handle), "Handle<String>(0v0)");
+        assert_eq!(handle.to_string(), "String #0 (generation 0)");
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use serde::Deserialize;
+use crate::platform::event::{ButtonState, Event, MouseButton};
+use crate::platform::Position;
+use crate::vfs;
+
+/// Win32 虛擬鍵碼，與 `KeyInput::key` 相同。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key(pub u32);
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Key {
+    pub const BACKSPACE: Key = Key(0x08);
+    pub const TAB: Key = Key(0x09);
+    pub const ENTER: Key = Key(0x0D);
+    pub const SHIFT: Key = Key(0x10);
+    pub const CONTROL: Key = Key(0x11);
+    pub const ALT: Key = Key(0x12);
+    pub const ESCAPE: Key = Key(0x1B);
+    pub const SPACE: Key = Key(0x20);
+    pub const LEFT: Key = Key(0x25);
+    pub const UP: Key = Key(0x26);
+    pub const RIGHT: Key = Key(0x27);
+    pub const DOWN: Key = Key(0x28);
+
+    /// 以設定檔中的名稱取得按鍵，例如 `"W"`、`"7"`、`"Space"`、`"F5"`，不分大小寫。
+    pub fn from_name(name: &str) -> Option<Key> {
+        let upper = name.to_ascii_uppercase();
+        // 英文字母與數字的虛擬鍵碼就是對應的 ASCII 大寫字元
+        if let [c] = upper.as_bytes() && c.is_ascii_alphanumeric() {
+            return Some(Key(*c as u32));
+        }
+        if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u32>().ok()) && (1..=24).contains(&n) {
+            return Some(Key(0x70 + n - 1));
+        }
+        let key = match upper.as_str() {
+            "BACKSPACE" => Key::BACKSPACE,
+            "TAB" => Key::TAB,
+            "ENTER" | "RETURN" => Key::ENTER,
+            "SHIFT" => Key::SHIFT,
+            "CONTROL" | "CTRL" => Key::CONTROL,
+            "ALT" => Key::ALT,
+            "ESCAPE" | "ESC" => Key::ESCAPE,
+            "SPACE" => Key::SPACE,
+            "LEFT" => Key::LEFT,
+            "UP" => Key::UP,
+            "RIGHT" => Key::RIGHT,
+            "DOWN" => Key::DOWN,
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
+/// 動作可以綁定到按鍵或滑鼠按鈕。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Binding {
+    /// 滑鼠按鈕寫成 `"MouseLeft"`、`"MouseRight"`、`"MouseMiddle"`、`"MouseX1"`、`"MouseX2"`，其餘視為按鍵名稱。
+    pub fn from_name(name: &str) -> Option<Binding> {
+        let button = match name.to_ascii_lowercase().as_str() {
+            "mouseleft" => MouseButton::Left,
+            "mouseright" => MouseButton::Right,
+            "mousemiddle" => MouseButton::Middle,
+            "mousex1" => MouseButton::X1,
+            "mousex2" => MouseButton::X2,
+            _ => return Key::from_name(name).map(Binding::Key),
+        };
+        Some(Binding::Mouse(button))
+    }
+}
+
+/// 由視窗事件更新的鍵盤與滑鼠狀態。
+/// 「按下」與「放開」的邊緣只在同一幀內有效，每幀結束時呼叫 `end_frame` 清除。
+#[derive(Debug, Default)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct InputState {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: Option<Position>,
+    mouse_delta: (i32, i32),
+    wheel_delta: f32,
+    horizontal_wheel_delta: f32,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::KeyDown(input) => {
+                let key = Key(input.key);
+                if self.keys_down.insert(key) {
+                    self.keys_pressed.insert(key);
+                }
+            }
+            Event::KeyUp(input) => {
+                let key = Key(input.key);
+                if self.keys_down.remove(&key) {
+                    self.keys_released.insert(key);
+                }
+            }
+            Event::MouseButton { button, state, position } => {
+                self.move_mouse(position);
+                match state {
+                    ButtonState::Pressed => {
+                        if self.buttons_down.insert(button) {
+                            self.buttons_pressed.insert(button);
+                        }
+                    }
+                    ButtonState::Released => {
+                        if self.buttons_down.remove(&button) {
+                            self.buttons_released.insert(button);
+                        }
+                    }
+                }
+            }
+            Event::MouseMove(position) => self.move_mouse(position),
+            Event::Wheel { delta, horizontal } => {
+                if horizontal {
+                    self.horizontal_wheel_delta += delta;
+                } else {
+                    self.wheel_delta += delta;
+                }
+            }
+            // 失去焦點後收不到 KeyUp，把所有按住的按鍵當作放開
+            Event::FocusLost => self.release_all(),
+            _ => {}
+        }
+    }
+
+    fn move_mouse(&mut self, position: Position) {
+        if let Some(last) = self.mouse_position {
+            self.mouse_delta.0 += position.x - last.x;
+            self.mouse_delta.1 += position.y - last.y;
+        }
+        self.mouse_position = Some(position);
+    }
+
+    fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.buttons_released.extend(self.buttons_down.drain());
+    }
+
+    /// 每幀使用完輸入狀態後呼叫，清除這一幀的邊緣、滑鼠位移與滾輪量。
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0, 0);
+        self.wheel_delta = 0.0;
+        self.horizontal_wheel_delta = 0.0;
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    pub fn is_down(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.is_key_down(key),
+            Binding::Mouse(button) => self.is_button_down(button),
+        }
+    }
+
+    pub fn was_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.was_key_pressed(key),
+            Binding::Mouse(button) => self.was_button_pressed(button),
+        }
+    }
+
+    pub fn was_released(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.was_key_released(key),
+            Binding::Mouse(button) => self.was_button_released(button),
+        }
+    }
+
+    /// 最後一次收到的滑鼠 client 座標，還沒收到任何滑鼠事件時為 `None`。
+    // 示範程式只用滾輪，滑鼠位置與移動量目前只有測試讀取
+    #[allow(dead_code)]
+    pub fn mouse_position(&self) -> Option<Position> {
+        self.mouse_position
+    }
+
+    /// 這一幀滑鼠移動的像素量 (x, y)。
+    #[allow(dead_code)]
+    pub fn mouse_delta(&self) -> (i32, i32) {
+        self.mouse_delta
+    }
+
+    /// 這一幀垂直滾輪轉動的格數。
+    pub fn wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
+
+    #[allow(dead_code)]
+    pub fn horizontal_wheel_delta(&self) -> f32 {
+        self.horizontal_wheel_delta
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(windows), allow(dead_code))]
+struct ActionMapFile {
+    #[serde(default)]
+    actions: HashMap<String, Vec<String>>,
+}
+
+/// 動作名稱到一組綁定的對應，任一綁定成立即視為動作成立。
+#[derive(Debug, Default, Clone)]
+pub struct ActionMap {
+    bindings: HashMap<String, Vec<Binding>>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 從 TOML 文字讀取，格式為：
+    ///
+    /// ```toml
+    /// [actions]
+    /// move_forward = ["W", "Up"]
+    /// fire = ["MouseLeft", "Space"]
+    /// ```
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let file: ActionMapFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut map = Self::new();
+        for (action, names) in file.actions {
+            for name in names {
+                let binding = Binding::from_name(&name)
+                    .ok_or_else(|| format!("unknown binding \"{}\" for action \"{}\"", name, action))?;
+                map.bind(&action, binding);
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = vfs::file_system().read_to_string(path)?;
+        Self::from_toml_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    // 還沒有在執行期重新綁定按鍵的介面
+    #[allow(dead_code)]
+    pub fn unbind_all(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// 輸入狀態加上動作對應，遊戲邏輯只需要以動作名稱查詢。
+#[derive(Debug, Default)]
+pub struct Input {
+    pub state: InputState,
+    pub actions: ActionMap,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Input {
+    pub fn new(actions: ActionMap) -> Self {
+        Self { state: InputState::new(), actions }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        self.state.handle_event(event);
+    }
+
+    pub fn end_frame(&mut self) {
+        self.state.end_frame();
+    }
+
+    /// 任一綁定目前按著。
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.actions.bindings(action).iter().any(|b| self.state.is_down(*b))
+    }
+
+    /// 任一綁定在這一幀被按下。
+    pub fn was_action_pressed(&self, action: &str) -> bool {
+        self.actions.bindings(action).iter().any(|b| self.state.was_pressed(*b))
+    }
+
+    /// 任一綁定在這一幀被放開。
+    // 示範程式的動作都在按下時觸發
+    #[allow(dead_code)]
+    pub fn was_action_released(&self, action: &str) -> bool {
+        self.actions.bindings(action).iter().any(|b| self.state.was_released(*b))
+    }
+
+    /// 以兩個動作組成 -1 ~ 1 的軸，例如 `axis("move_left", "move_right")`。
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        let mut value = 0.0;
+        if self.is_action_down(negative) {
+            value -= 1.0;
+        }
+        if self.is_action_down(positive) {
+            value += 1.0;
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::event::KeyInput;
+
+    fn key_input(key: Key, repeat: bool) -> KeyInput {
+        KeyInput { key: key.0, scan_code: 0, extended: false, repeat, repeat_count: 1, system: false }
+    }
+
+    fn key_down(key: Key) -> Event {
+        Event::KeyDown(key_input(key, false))
+    }
+
+    fn key_repeat(key: Key) -> Event {
+        Event::KeyDown(key_input(key, true))
+    }
+
+    fn key_up(key: Key) -> Event {
+        Event::KeyUp(key_input(key, false))
+    }
+
+    fn button(button: MouseButton, state: ButtonState, x: i32, y: i32) -> Event {
+        Event::MouseButton { button, state, position: Position { x, y } }
+    }
+
+    fn mouse_move(x: i32, y: i32) -> Event {
+        Event::MouseMove(Position { x, y })
+    }
+
+    fn wheel(delta: f32, horizontal: bool) -> Event {
+        Event::Wheel { delta, horizontal }
+    }
+
+    #[test]
+    fn key_edges_last_one_frame() {
+        let mut state = InputState::new();
+        state.handle_event(&key_down(Key::SPACE));
+        assert!(state.is_key_down(Key::SPACE));
+        assert!(state.was_key_pressed(Key::SPACE));
+        assert!(!state.was_key_released(Key::SPACE));
+
+        state.end_frame();
+        assert!(state.is_key_down(Key::SPACE));
+        assert!(!state.was_key_pressed(Key::SPACE));
+
+        // 按住不放的重複 KeyDown 不是新的按下
+        state.handle_event(&key_repeat(Key::SPACE));
+        assert!(!state.was_key_pressed(Key::SPACE));
+
+        state.handle_event(&key_up(Key::SPACE));
+        assert!(!state.is_key_down(Key::SPACE));
+        assert!(state.was_key_released(Key::SPACE));
+        state.end_frame();
+        assert!(!state.was_key_released(Key::SPACE));
+
+        // 沒有按下過的按鍵放開不算
+        state.handle_event(&key_up(Key::ENTER));
+        assert!(!state.was_key_released(Key::ENTER));
+    }
+
+    #[test]
+    fn press_and_release_in_the_same_frame() {
+        let mut state = InputState::new();
+        state.handle_event(&key_down(Key::ESCAPE));
+        state.handle_event(&key_up(Key::ESCAPE));
+        assert!(state.was_key_pressed(Key::ESCAPE));
+        assert!(state.was_key_released(Key::ESCAPE));
+        assert!(!state.is_key_down(Key::ESCAPE));
+    }
+
+    #[test]
+    fn button_edges() {
+        let mut state = InputState::new();
+        state.handle_event(&button(MouseButton::Left, ButtonState::Pressed, 10, 20));
+        state.handle_event(&button(MouseButton::Left, ButtonState::Pressed, 10, 20));
+        assert!(state.is_button_down(MouseButton::Left));
+        assert!(state.was_button_pressed(MouseButton::Left));
+        assert_eq!(state.mouse_position(), Some(Position { x: 10, y: 20 }));
+
+        state.end_frame();
+        assert!(!state.was_button_pressed(MouseButton::Left));
+        state.handle_event(&button(MouseButton::Left, ButtonState::Released, 10, 20));
+        assert!(state.was_button_released(MouseButton::Left));
+        assert!(!state.is_button_down(MouseButton::Left));
+        assert!(!state.was_button_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn focus_lost_releases_everything() {
+        let mut state = InputState::new();
+        state.handle_event(&key_down(Key::SHIFT));
+        state.handle_event(&key_down(Key::from_name("W").unwrap()));
+        state.handle_event(&button(MouseButton::Right, ButtonState::Pressed, 0, 0));
+        state.end_frame();
+
+        state.handle_event(&Event::FocusLost);
+        for key in [Key::SHIFT, Key::from_name("W").unwrap()] {
+            assert!(!state.is_key_down(key));
+            assert!(state.was_key_released(key));
+        }
+        assert!(!state.is_button_down(MouseButton::Right));
+        assert!(state.was_button_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn mouse_delta_accumulates_within_a_frame() {
+        let mut state = InputState::new();
+        assert_eq!(state.mouse_position(), None);
+        // 第一次移動只記下位置，不會從原點跳過來
+        state.handle_event(&mouse_move(100, 100));
+        assert_eq!(state.mouse_delta(), (0, 0));
+
+        state.handle_event(&mouse_move(110, 95));
+        state.handle_event(&button(MouseButton::Middle, ButtonState::Pressed, 112, 90));
+        assert_eq!(state.mouse_delta(), (12, -10));
+        assert_eq!(state.mouse_position(), Some(Position { x: 112, y: 90 }));
+
+        state.end_frame();
+        assert_eq!(state.mouse_delta(), (0, 0));
+        state.handle_event(&mouse_move(100, 100));
+        assert_eq!(state.mouse_delta(), (-12, 10));
+    }
+
+    #[test]
+    fn wheel_directions_are_kept_apart() {
+        let mut state = InputState::new();
+        state.handle_event(&wheel(1.0, false));
+        state.handle_event(&wheel(0.5, false));
+        state.handle_event(&wheel(-2.0, true));
+        assert_eq!(state.wheel_delta(), 1.5);
+        assert_eq!(state.horizontal_wheel_delta(), -2.0);
+
+        state.end_frame();
+        assert_eq!(state.wheel_delta(), 0.0);
+        assert_eq!(state.horizontal_wheel_delta(), 0.0);
+    }
+
+    #[test]
+    fn parses_key_and_binding_names() {
+        assert_eq!(Key::from_name("w"), Some(Key(b'W' as u32)));
+        assert_eq!(Key::from_name("7"), Some(Key(b'7' as u32)));
+        assert_eq!(Key::from_name("F1"), Some(Key(0x70)));
+        assert_eq!(Key::from_name("f24"), Some(Key(0x87)));
+        assert_eq!(Key::from_name("Esc"), Some(Key::ESCAPE));
+        assert_eq!(Key::from_name("F25"), None);
+        assert_eq!(Key::from_name("F0"), None);
+        assert_eq!(Key::from_name("?"), None);
+        assert_eq!(Binding::from_name("MouseX2"), Some(Binding::Mouse(MouseButton::X2)));
+        assert_eq!(Binding::from_name("space"), Some(Binding::Key(Key::SPACE)));
+    }
+
+    #[test]
+    fn loads_action_maps() {
+        let map = ActionMap::from_toml_str("[actions]\nfire = [\"MouseLeft\", \"Space\", \"space\"]\njump = []\n").unwrap();
+        assert_eq!(map.bindings("fire"), [Binding::Mouse(MouseButton::Left), Binding::Key(Key::SPACE)]);
+        assert!(map.bindings("jump").is_empty());
+        assert!(map.bindings("missing").is_empty());
+        assert!(ActionMap::from_toml_str("").unwrap().bindings("fire").is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_binding_names() {
+        let error = ActionMap::from_toml_str("[actions]\nfire = [\"MouseLeft\", \"Trigger\"]\n").unwrap_err();
+        assert_eq!(error, "unknown binding \"Trigger\" for action \"fire\"");
+    }
+
+    #[test]
+    fn rejects_unknown_sections() {
+        // 寫錯成 [action] 時不能默默讀成空的對應
+        assert!(ActionMap::from_toml_str("[action]\nfire = [\"Space\"]\n").is_err());
+    }
+
+    #[test]
+    fn axis_combines_two_actions() {
+        let mut actions = ActionMap::new();
+        actions.bind("left", Binding::Key(Key::LEFT));
+        actions.bind("right", Binding::Key(Key::RIGHT));
+        actions.bind("right", Binding::Mouse(MouseButton::Right));
+        let mut input = Input::new(actions);
+        assert_eq!(input.axis("left", "right"), 0.0);
+
+        input.handle_event(&key_down(Key::LEFT));
+        assert_eq!(input.axis("left", "right"), -1.0);
+        assert!(input.was_action_pressed("left"));
+
+        input.handle_event(&button(MouseButton::Right, ButtonState::Pressed, 0, 0));
+        assert_eq!(input.axis("left", "right"), 0.0);
+
+        input.handle_event(&key_up(Key::LEFT));
+        assert_eq!(input.axis("left", "right"), 1.0);
+        assert!(input.was_action_released("left"));
+
+        input.end_frame();
+        assert!(!input.was_action_released("left"));
+        assert!(input.is_action_down("right"));
+    }
+}
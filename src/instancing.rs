@@ -0,0 +1,177 @@
+use directx_math::*;
+use windows::core::{s, w, PCSTR};
+use windows::Win32::Graphics::Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32B32_FLOAT};
+use crate::d3dutil::{blob_as_bytes, create_pixel_shader, create_vertex_shader, ConstantBuffer};
+use crate::mesh::Mesh;
+use crate::scene::InstanceData;
+
+/// slot 0 為 per-vertex 資料，slot 1 為 per-instance 的 world 矩陣與顏色。
+fn instanced_input_layout() -> [D3D11_INPUT_ELEMENT_DESC; 7] {
+    let per_instance = |semantic_name: PCSTR, semantic_index: u32, offset: u32| D3D11_INPUT_ELEMENT_DESC {
+        SemanticName: semantic_name,
+        SemanticIndex: semantic_index,
+        Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+        InputSlot: 1,
+        AlignedByteOffset: offset,
+        InputSlotClass: D3D11_INPUT_PER_INSTANCE_DATA,
+        InstanceDataStepRate: 1,
+    };
+    [
+        D3D11_INPUT_ELEMENT_DESC {
+            SemanticName: s!("POSITION"),
+            SemanticIndex: 0,
+            Format: DXGI_FORMAT_R32G32B32_FLOAT,
+            InputSlot: 0,
+            AlignedByteOffset: 0,
+            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+            InstanceDataStepRate: 0,
+        },
+        D3D11_INPUT_ELEMENT_DESC {
+            SemanticName: s!("COLOR"),
+            SemanticIndex: 0,
+            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+            InputSlot: 0,
+            AlignedByteOffset: 12,
+            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+            InstanceDataStepRate: 0,
+        },
+        per_instance(s!("WORLD"), 0, 0),
+        per_instance(s!("WORLD"), 1, 16),
+        per_instance(s!("WORLD"), 2, 32),
+        per_instance(s!("WORLD"), 3, 48),
+        per_instance(s!("INSTANCE_COLOR"), 0, 64),
+    ]
+}
+
+/// 一組著色器與對應的 input layout。
+pub struct Material {
+    pub name: String,
+    pub input_layout: ID3D11InputLayout,
+    pub vertex_shader: ID3D11VertexShader,
+    pub pixel_shader: ID3D11PixelShader,
+    // 建立這個材質的函式，裝置失效後用來重建
+    create: fn(&ID3D11Device) -> Material,
+}
+
+impl Material {
+    /// 以 instance 顏色調變頂點顏色的預設 instanced 材質。
+    pub fn instanced_color(device: &ID3D11Device) -> Self {
+        let (vertex_shader, vs_blob) = create_vertex_shader(device, w!("hlsl/instanced_vs.cso"), w!("hlsl/instanced_vs.hlsl"), s!("VS"));
+        let pixel_shader = create_pixel_shader(device, w!("hlsl/instanced_ps.cso"), w!("hlsl/instanced_ps.hlsl"), s!("PS"));
+        let mut input_layout: Option<ID3D11InputLayout> = None;
+        unsafe {
+            device.CreateInputLayout(&instanced_input_layout(), blob_as_bytes(&vs_blob), Some(&mut input_layout)).expect("CreateInputLayout failed");
+        }
+        Self {
+            name: "instanced_color".to_string(),
+            input_layout: input_layout.unwrap(),
+            vertex_shader,
+            pixel_shader,
+            create: Self::instanced_color,
+        }
+    }
+
+    /// 在新的裝置上重新編譯著色器並建立 input layout。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        let name = std::mem::take(&mut self.name);
+        *self = (self.create)(device);
+        self.name = name;
+    }
+
+    pub fn bind(&self, context: &ID3D11DeviceContext) {
+        unsafe {
+            context.IASetInputLayout(&self.input_layout);
+            context.VSSetShader(&self.vertex_shader, None);
+            context.PSSetShader(&self.pixel_shader, None);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct PerFrameConstants {
+    view_projection: XMFLOAT4X4,
+}
+
+/// 以 dynamic instance buffer 畫大量相同 mesh 的複本，超過容量時分段上傳。
+pub struct InstanceRenderer {
+    instance_buffer: ID3D11Buffer,
+    capacity: usize,
+    per_frame: ConstantBuffer<PerFrameConstants>,
+}
+
+impl InstanceRenderer {
+    pub const DEFAULT_CAPACITY: usize = 4096;
+
+    pub fn new(device: &ID3D11Device, capacity: usize) -> Self {
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: (capacity * size_of::<InstanceData>()) as u32,
+            Usage: D3D11_USAGE_DYNAMIC,
+            BindFlags: D3D11_BIND_VERTEX_BUFFER.0 as u32,
+            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0 as u32,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let mut instance_buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&desc, None, Some(&mut instance_buffer)).expect("CreateBuffer failed");
+        }
+        Self {
+            instance_buffer: instance_buffer.unwrap(),
+            capacity,
+            per_frame: ConstantBuffer::new(device, &PerFrameConstants { view_projection: XMFLOAT4X4::default() }),
+        }
+    }
+
+    /// 在新的裝置上以相同容量重建。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        *self = Self::new(device, self.capacity);
+    }
+
+    /// 每幀開始 instanced 繪製前呼叫，更新並綁定相機矩陣到 VS 的 b0。
+    pub fn begin_frame(&self, context: &ID3D11DeviceContext, view_projection: XMMATRIX) {
+        let mut constants = PerFrameConstants { view_projection: XMFLOAT4X4::default() };
+        // HLSL 預設 column-major，上傳前先轉置
+        XMStoreFloat4x4(&mut constants.view_projection, XMMatrixTranspose(view_projection));
+        self.per_frame.update(context, &constants);
+        unsafe {
+            context.VSSetConstantBuffers(0, Some(&[Some(self.per_frame.buffer.clone())]));
+        }
+    }
+
+    /// 以最少的 draw call 畫出 `instances` 中的每一個複本，回傳實際發出的 draw call 數。
+    pub fn draw_instanced(&self, context: &ID3D11DeviceContext, mesh: &Mesh, material: &Material, instances: &[InstanceData]) -> usize {
+        if instances.is_empty() {
+            return 0;
+        }
+
+        mesh.bind(context);
+        material.bind(context);
+        let stride = size_of::<InstanceData>() as u32;
+        let offset = 0_u32;
+        unsafe {
+            context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            context.IASetVertexBuffers(1, 1, Some(&Some(self.instance_buffer.clone())), Some(&stride), Some(&offset));
+        }
+
+        let mut draw_calls = 0;
+        for chunk in instances.chunks(self.capacity) {
+            unsafe {
+                let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+                context.Map(&self.instance_buffer, 0, D3D11_MAP_WRITE_DISCARD, 0, Some(&mut mapped)).expect("Map instance buffer failed");
+                std::ptr::copy_nonoverlapping(chunk.as_ptr(), mapped.pData as *mut InstanceData, chunk.len());
+                context.Unmap(&self.instance_buffer, 0);
+
+                if mesh.index_buffer.is_some() {
+                    context.DrawIndexedInstanced(mesh.index_count, chunk.len() as u32, 0, 0, 0);
+                } else {
+                    context.DrawInstanced(mesh.vertex_count, chunk.len() as u32, 0, 0);
+                }
+            }
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+}
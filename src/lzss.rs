@@ -0,0 +1,206 @@
+//! 封裝檔使用的 LZSS 壓縮。每 8 個項目前有一個旗標 byte，位元為 1 表示該項目是
+//! 回頭複製（2 byte：12 位元距離、4 位元長度），為 0 表示一個原樣的 byte。
+
+const WINDOW_SIZE: usize = 4096;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 18;
+const HASH_BITS: u32 = 12;
+// 每個位置最多比對幾個候選，越大壓縮率越好但越慢
+const MAX_CANDIDATES: usize = 32;
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+fn insert(input: &[u8], head: &mut [usize], previous: &mut [usize], position: usize) {
+    if position + MIN_MATCH <= input.len() {
+        let hash = hash(&input[position..]);
+        previous[position] = head[hash];
+        head[hash] = position;
+    }
+}
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    // 每個雜湊值最後出現的位置，以及同一個雜湊值的前一個位置
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; input.len()];
+    let mut flag_position = 0;
+    let mut item = 8;
+    let mut position = 0;
+    while position < input.len() {
+        if item == 8 {
+            flag_position = output.len();
+            output.push(0);
+            item = 0;
+        }
+        let (mut best_length, mut best_distance) = (0, 0);
+        if position + MIN_MATCH <= input.len() {
+            let max_length = MAX_MATCH.min(input.len() - position);
+            let mut candidate = head[hash(&input[position..])];
+            for _ in 0..MAX_CANDIDATES {
+                if candidate == usize::MAX || position - candidate > WINDOW_SIZE {
+                    break;
+                }
+                let length = input[candidate..].iter().zip(&input[position..position + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    (best_length, best_distance) = (length, position - candidate);
+                    if length == max_length {
+                        break;
+                    }
+                }
+                candidate = previous[candidate];
+            }
+        }
+        if best_length >= MIN_MATCH {
+            let distance = best_distance - 1;
+            output[flag_position] |= 1 << item;
+            output.push(distance as u8);
+            output.push(((distance >> 8) << 4) as u8 | (best_length - MIN_MATCH) as u8);
+            for offset in 0..best_length {
+                insert(input, &mut head, &mut previous, position + offset);
+            }
+            position += best_length;
+        } else {
+            output.push(input[position]);
+            insert(input, &mut head, &mut previous, position);
+            position += 1;
+        }
+        item += 1;
+    }
+    output
+}
+
+/// `compressed_size` byte 的壓縮資料最多能解出的大小：每個回頭複製用 2 byte 產生 `MAX_MATCH` byte。
+pub fn max_decompressed_size(compressed_size: usize) -> usize {
+    compressed_size.saturating_mul(MAX_MATCH / 2)
+}
+
+/// `size` 是原始資料的大小，解壓縮後的長度不符時回傳錯誤。
+/// `size` 來自檔案內容，超過 `input` 可能解出的大小時直接回傳錯誤，不會先配置記憶體。
+pub fn decompress(input: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    if size > max_decompressed_size(input.len()) {
+        return Err(format!("{} bytes of compressed data cannot expand to {} bytes", input.len(), size));
+    }
+    let mut output = Vec::with_capacity(size);
+    let mut bytes = input.iter().copied();
+    let truncated = || "compressed data is truncated".to_string();
+    while output.len() < size {
+        let flags = bytes.next().ok_or_else(truncated)?;
+        for item in 0..8 {
+            if output.len() >= size {
+                break;
+            }
+            if flags & (1 << item) == 0 {
+                output.push(bytes.next().ok_or_else(truncated)?);
+                continue;
+            }
+            let low = bytes.next().ok_or_else(truncated)? as usize;
+            let high = bytes.next().ok_or_else(truncated)? as usize;
+            let distance = (low | (high >> 4) << 8) + 1;
+            let length = (high & 0x0F) + MIN_MATCH;
+            if distance > output.len() {
+                return Err(format!("compressed data refers {} bytes back at offset {}", distance, output.len()));
+            }
+            // 距離可以小於長度，複製的內容會重複剛寫出的資料，所以逐 byte 複製
+            let start = output.len() - distance;
+            for offset in 0..length {
+                output.push(output[start + offset]);
+            }
+        }
+    }
+    if output.len() != size || bytes.next().is_some() {
+        return Err(format!("decompressed size does not match, expected {} bytes", size));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = compress(input);
+        assert_eq!(decompress(&compressed, input.len()).unwrap(), input);
+        compressed
+    }
+
+    /// 固定種子的 xorshift，產生無法壓縮的資料。
+    fn noise(length: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_u32;
+        (0..length).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }).collect()
+    }
+
+    #[test]
+    fn round_trips_empty_input() {
+        assert!(round_trip(&[]).is_empty());
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let text = "float4 PS(float4 color : COLOR) : SV_Target { return color; }\n".repeat(100);
+        let compressed = round_trip(text.as_bytes());
+        assert!(compressed.len() < text.len() / 4, "{} bytes compressed to {}", text.len(), compressed.len());
+        // 距離小於長度的回頭複製
+        let run = vec![7_u8; 1000];
+        assert!(round_trip(&run).len() < 200);
+    }
+
+    #[test]
+    fn round_trips_incompressible_data() {
+        for length in [1, 2, 3, 7, 8, 9, 17, 5000] {
+            let input = noise(length);
+            let compressed = round_trip(&input);
+            assert!(compressed.len() <= length + length.div_ceil(8));
+        }
+    }
+
+    #[test]
+    fn round_trips_matches_beyond_the_window() {
+        // 重複的區塊相隔超過 WINDOW_SIZE，只能用較近的資料
+        let block = noise(300);
+        let mut input = block.clone();
+        input.extend(noise(WINDOW_SIZE + 500).iter().rev());
+        input.extend(&block);
+        input.extend(&block);
+        round_trip(&input);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let input = "abcabcabcabc hello hello hello".repeat(10);
+        let compressed = compress(input.as_bytes());
+        for length in [0, 1, compressed.len() / 2, compressed.len() - 1] {
+            assert!(decompress(&compressed[..length], input.len()).is_err(), "accepted {} bytes", length);
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        // 第一個項目就回頭複製
+        assert!(decompress(&[0x01, 0x00, 0x00], 3).is_err());
+        // 多出來的資料
+        assert!(decompress(&[0x00, b'a', b'b'], 1).is_err());
+        let compressed = compress(b"hello world");
+        assert!(decompress(&compressed, 10).is_err());
+        assert!(decompress(&compressed, 12).is_err());
+    }
+
+    #[test]
+    fn rejects_sizes_larger_than_the_input_can_expand_to() {
+        let compressed = compress(&[0_u8; 100]);
+        assert!(decompress(&compressed, usize::MAX).is_err());
+        assert!(decompress(&compressed, max_decompressed_size(compressed.len()) + 1).is_err());
+        assert!(decompress(&[], 1).is_err());
+        // 上限不會小於實際解出的大小
+        assert!(max_decompressed_size(compressed.len()) >= 100);
+    }
+}
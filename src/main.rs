@@ -1,42 +1,668 @@
+
+#[cfg(windows)]
 mod window;
+#[cfg(windows)]
 mod d3d11;
+#[cfg(windows)]
 mod d3dutil;
+#[cfg(windows)]
+mod post_process;
+mod effect_chain;
+#[cfg(windows)]
+mod output_format;
+mod camera;
+#[cfg(windows)]
+mod mesh;
+mod scene;
+#[cfg(windows)]
+mod instancing;
+mod debug_draw;
+#[cfg(windows)]
+mod debug_renderer;
+#[cfg(windows)]
+mod surface;
+mod game_loop;
+mod input;
+mod platform;
+mod display;
+mod adapter;
+mod config;
+mod cli;
+mod scene_file;
+mod capabilities;
+mod device_lost;
+mod present;
+mod handle;
+#[cfg(windows)]
+mod resources;
+mod thread_pool;
+mod assets;
+mod lzss;
+mod archive;
+mod vfs;
+mod profiler;
 
+#[cfg(windows)]
+use std::cell::Cell;
+#[cfg(windows)]
+use std::rc::Rc;
+#[cfg(windows)]
 use std::sync::{Arc, RwLock};
-use windows::core::{s, w};
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::*;
-use window::*;
-use widestring::{u16str, U16Str};
-use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
-use crate::d3d11::D3d11Renderer;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(windows)]
+use widestring::U16CString;
+#[cfg(windows)]
+use windows::core::PCWSTR;
+#[cfg(windows)]
+use windows::Win32::Graphics::Direct3D::{D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_REFERENCE, D3D_DRIVER_TYPE_WARP};
+#[cfg(windows)]
+use crate::config::DriverType;
+#[cfg(windows)]
+use crate::d3d11::{D3d11Renderer, RendererSettings};
+#[cfg(windows)]
+use crate::output_format::OutputFormat;
+#[cfg(windows)]
+use crate::post_process::{ToneMapOperator, ToneMapping};
+#[cfg(windows)]
+use crate::surface::{Surface, SurfaceId};
+#[cfg(windows)]
+use crate::display::WindowMode;
+#[cfg(windows)]
+use crate::instancing::Material;
+#[cfg(windows)]
+use crate::mesh::Mesh;
+#[cfg(windows)]
+use crate::camera::Camera;
+#[cfg(windows)]
+use crate::scene::MeshId;
+#[cfg(windows)]
+use crate::scene_file::MeshKind;
+#[cfg(windows)]
+use crate::profiler::profile_scope;
+#[cfg(windows)]
+use crate::debug_draw::{DebugOptions, GRAY, WHITE, YELLOW};
+#[cfg(windows)]
+use crate::game_loop::{FixedTimestep, FrameTime, Game, SystemClock, UpdateTime};
+#[cfg(windows)]
+use crate::input::{ActionMap, Input};
+#[cfg(windows)]
+use crate::platform::dispatch::{EventHandler, HandlerResult};
+#[cfg(windows)]
+use crate::platform::win32::Win32Platform;
+use crate::cli::{CliArgs, CliCommand, PackArgs};
+use crate::archive::{Archive, ArchiveBuilder};
+use crate::vfs::{DirectoryMount, Vfs};
+use crate::profiler::ProfileSummary;
+use crate::config::Config;
+use crate::scene_file::SceneDesc;
+use crate::assets::{AssetData, AssetEvent, AssetRegistry, AssetState, ShaderData};
+use crate::thread_pool::ThreadPool;
+use crate::platform::event::Event;
+use crate::platform::{Platform, WindowDesc};
+use crate::platform::WindowId;
+#[cfg(windows)]
+use directx_math::*;
+
+/// 解析命令列並讀取設定檔，`--help` 印出說明後結束，參數或設定有誤時印出錯誤並以代碼 2 結束。
+fn parse_command_line() -> (CliArgs, Config, Option<SceneDesc>) {
+    let mut args = std::env::args();
+    let program = args.next().unwrap_or_else(|| "rust_learning".to_string());
+    let cli = match cli::parse_args(args) {
+        Ok(CliCommand::Run(cli)) => cli,
+        Ok(CliCommand::Help) => {
+            println!("{}", cli::usage(&program));
+            std::process::exit(0);
+        }
+        Ok(CliCommand::Pack(args)) => {
+            pack(&args).unwrap_or_else(|e| {
+                eprintln!("failed to build archive:\n{}", e);
+                std::process::exit(1);
+            });
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}\nRun with --help for usage.", e);
+            std::process::exit(2);
+        }
+    };
+    profiler::set_enabled(cli.trace.is_some());
+    let config = Config::load(cli.config_path.as_deref(), &cli.overrides).unwrap_or_else(|e| {
+        eprintln!("invalid configuration:\n{}", e);
+        std::process::exit(2);
+    });
+    let file_system = build_file_system(&config).unwrap_or_else(|e| {
+        eprintln!("failed to mount files:\n{}", e);
+        std::process::exit(2);
+    });
+    vfs::install(file_system);
+    let scene = cli.scene.as_ref().map(|path| SceneDesc::load(path).unwrap_or_else(|e| {
+        eprintln!("failed to load scene:\n{}", e);
+        std::process::exit(2);
+    }));
+    (cli, config, scene)
+}
+
+/// 由低到高的優先順序掛載內建 shader、封裝檔、工作目錄與 shader 目錄。
+fn build_file_system(config: &Config) -> Result<Vfs, String> {
+    let mut vfs = Vfs::new();
+    vfs.mount("hlsl", vfs::EMBEDDED_SHADERS)?;
+    for path in &config.files.archives {
+        vfs.mount("", Archive::open(path)?)?;
+    }
+    vfs.mount("", DirectoryMount::new("."))?;
+    if config.renderer.shader_dir.is_dir() {
+        vfs.mount("hlsl", DirectoryMount::new(&config.renderer.shader_dir))?;
+    }
+    Ok(vfs)
+}
+
+/// 視窗的實體大小、位置與縮放比例，視窗不存在時為 `None`。
+fn describe_window(platform: &impl Platform, window: WindowId) -> Option<String> {
+    let size = platform.window_size(window)?;
+    let position = platform.window_position(window)?;
+    let scale_factor = platform.scale_factor(window)?;
+    Some(format!("{}x{} at {},{} (scale factor {})", size.width, size.height, position.x, position.y, scale_factor))
+}
 
+/// `--pack`：把目錄下的所有檔案寫成封裝檔。
+fn pack(args: &PackArgs) -> Result<(), String> {
+    let mut builder = ArchiveBuilder::new(args.compress);
+    builder.add_dir(&args.source, "")?;
+    if builder.is_empty() {
+        return Err(format!("{}: no files to pack", args.source.display()));
+    }
+    let summary = builder.write_to_file(&args.output)?;
+    println!("{}: {}", args.output.display(), summary);
+    Ok(())
+}
+
+/// `--trace`：寫出最近幾幀的 Chrome trace 並印出各 scope 的平均時間。
+fn finish_trace(cli: &CliArgs) {
+    let Some(path) = &cli.trace else {
+        return;
+    };
+    println!("profile: {}", ProfileSummary::new(&profiler::frames()));
+    match profiler::write_chrome_trace(path) {
+        Ok(()) => println!("trace written to {}", path.display()),
+        Err(e) => eprintln!("failed to write trace: {}", e),
+    }
+}
+
+fn window_desc(config: &Config) -> WindowDesc {
+    let mut desc = WindowDesc::new(&config.window.title);
+    if let Some(position) = config.window.position() {
+        desc = desc.position(position.x, position.y);
+    }
+    if let Some(size) = config.window.size() {
+        desc = desc.size(size.width, size.height);
+    }
+    desc
+}
+
+#[cfg(windows)]
 fn main() {
-    WndClass::init(w!("test string"));
-    let global_wndclass = WndClass::get_instance();
-    let mut window = WindowBuilder::new()
-        .window_name(w!("test window"))
-        .class_name(w!("test string"))
-        .hinstance(global_wndclass.h_instance)
-    .build().unwrap();
-
-    let d3d11 = D3d11Renderer::new(D3D_DRIVER_TYPE_HARDWARE, &window);
+    let (cli, config, scene) = parse_command_line();
+    if cli.list_adapters {
+        for adapter in D3d11Renderer::enumerate_adapters() {
+            println!("{}", adapter);
+        }
+        return;
+    }
+    // Win32Platform 只保存指標，類別名稱必須比 platform 活得久
+    let class_name = U16CString::from_str(&config.window.class_name).unwrap();
+    let mut platform = Win32Platform::new(PCWSTR(class_name.as_ptr()));
+    let window_id = platform.create_window(&window_desc(&config)).unwrap();
+
+    // renderer 與所有參考它的處理函式都在 run 內，回傳時已經釋放，之後才銷毀視窗
+    run(&mut platform, window_id, &config, &cli, scene.as_ref());
+    platform.close_window(window_id);
+    while platform.pump_events() {}
+    std::process::exit(platform.exit_code().unwrap_or(0));
+}
+
+#[cfg(windows)]
+fn run(platform: &mut Win32Platform, window_id: WindowId, config: &Config, cli: &CliArgs, scene: Option<&SceneDesc>) {
+    let window = platform.window(window_id).unwrap().clone();
+
+    if let Some(description) = describe_window(platform, window_id) {
+        println!("window: {}", description);
+    }
+    let renderer_config = &config.renderer;
+    let settings = RendererSettings {
+        msaa_samples: renderer_config.msaa_samples,
+        // 名稱已經由 Config::validate 檢查過
+        output_format: OutputFormat::from_name(&renderer_config.output_format).unwrap(),
+        paper_white_nits: renderer_config.paper_white_nits,
+        window_mode: config.window.mode,
+        present: renderer_config.present(),
+        debug_layer: renderer_config.debug_layer,
+        adapter: renderer_config.adapter.clone(),
+    };
+    let d3d11 = D3d11Renderer::with_settings(driver_type(renderer_config.driver), &window, settings);
+    match d3d11.adapter() {
+        Some(adapter) => println!("Rendering on {}", adapter),
+        None => println!("Rendering on the default adapter"),
+    }
+    print!("Device capabilities:\n{}", d3d11.capabilities());
     
     let pos = window.get_position();
     
     let d3d11 = Arc::new(RwLock::new(d3d11));
 
-    window.show(SHOW_WINDOW_CMD(1));
-    d3d11.read().unwrap().render();
-    d3d11.read().unwrap().draw_scene();
+    create_scene_pipeline(&mut d3d11.write().unwrap()).unwrap_or_else(|e| panic!("failed to create the scene pipeline: {}", e));
+    match scene {
+        Some(scene) => {
+            let assets = load_scene_assets(scene, cli);
+            load_scene(&mut d3d11.write().unwrap(), scene, &assets);
+        }
+        None => populate_demo_scene(&mut d3d11.write().unwrap()),
+    }
+    draw_scene(&d3d11);
+    for mode in d3d11.read().unwrap().display_modes(SurfaceId::PRIMARY) {
+        println!("display mode: {}", mode);
+    }
     
     let d3d11_clone = d3d11.clone();
-    window.add_handler(EventHandler::new(WM_SIZE, Box::new(move |wparam: WPARAM, lparam: LPARAM| {
-        let width = LOWORD(lparam.0 as u32);
-        let height = LOWORD(lparam.0 as u32);
-        d3d11_clone.write().unwrap().on_resize(pos, Size{width: width as i32, height: height as i32});
-        d3d11_clone.read().unwrap().render();
-        d3d11_clone.read().unwrap().draw_scene();
-    })));
-    WndClass::msg_loop();
+    let _resize_subscription = platform.add_handler(window_id, EventHandler::typed(Box::new(move |event: &Event| {
+        // 切換顯示方式時 renderer 已被鎖住，期間同步送來的 WM_SIZE 由切換本身處理大小
+        if let Event::Resized(size) = *event && let Ok(mut renderer) = d3d11_clone.try_write() {
+            let result = renderer.on_resize(pos, size).and_then(|()| renderer.draw_scene());
+            if let Err(lost) = result {
+                renderer.recover(lost).unwrap_or_else(|e| panic!("{}", e));
+            }
+        }
+        HandlerResult::Continue
+    }))).unwrap();
+
+    // 數字鍵 1~9 依鏈上順序開關後處理效果
+    let d3d11_clone = d3d11.clone();
+    let window_clone = window.clone();
+    let _key_subscription = platform.add_handler(window_id, EventHandler::typed(Box::new(move |event: &Event| {
+        let Event::KeyDown(input) = *event else {
+            return HandlerResult::Continue;
+        };
+        let key = input.key;
+        // Alt+Enter 在視窗與無邊框全螢幕之間切換，F 鍵切換獨占全螢幕
+        if (input.system && key == 0x0D) || key == 0x46 {
+            let fullscreen_mode = if key == 0x46 { WindowMode::Fullscreen } else { WindowMode::Borderless };
+            let mut renderer = d3d11_clone.write().unwrap();
+            let next = renderer.window_mode(SurfaceId::PRIMARY).toggled(fullscreen_mode);
+            match renderer.set_window_mode(SurfaceId::PRIMARY, &window_clone, next) {
+                Ok(mode) => println!("Window mode: {}", mode),
+                Err(lost) => renderer.recover(lost).unwrap_or_else(|e| panic!("{}", e)),
+            }
+            return HandlerResult::Handled(None);
+        }
+        // M 鍵循環切換裝置支援的 MSAA 取樣數
+        if key == 0x4D {
+            let mut renderer = d3d11_clone.write().unwrap();
+            let modes = renderer.supported_msaa_modes().to_vec();
+            let current = modes.iter().position(|mode| *mode == renderer.msaa()).unwrap_or(0);
+            let next = modes[(current + 1) % modes.len()];
+            match renderer.set_msaa_samples(next.sample_count) {
+                Ok(msaa) => println!("MSAA: {}x", msaa.sample_count),
+                Err(lost) => renderer.recover(lost).unwrap_or_else(|e| panic!("{}", e)),
+            }
+            return HandlerResult::Handled(None);
+        }
+        // V 鍵切換垂直同步
+        if key == 0x56 {
+            let mut renderer = d3d11_clone.write().unwrap();
+            let vsync = !renderer.vsync();
+            renderer.set_vsync(vsync);
+            let tearing = renderer.surface(SurfaceId::PRIMARY).is_some_and(Surface::tearing_supported);
+            println!("VSync: {} (tearing {})", vsync, if tearing { "supported" } else { "not supported" });
+            return HandlerResult::Handled(None);
+        }
+        // O 鍵循環切換 back buffer 輸出格式
+        if key == 0x4F {
+            let mut renderer = d3d11_clone.write().unwrap();
+            let current = OutputFormat::ALL.iter().position(|format| *format == renderer.output_format()).unwrap_or(0);
+            let next = OutputFormat::ALL[(current + 1) % OutputFormat::ALL.len()];
+            match renderer.set_output_format(next) {
+                Ok(format) => println!("Output format: {:?}", format),
+                Err(lost) => renderer.recover(lost).unwrap_or_else(|e| panic!("{}", e)),
+            }
+            return HandlerResult::Handled(None);
+        }
+        // T 鍵切換 tone mapping 曲線
+        if key == 0x54 {
+            let mut renderer = d3d11_clone.write().unwrap();
+            if let Some(tone_mapping) = renderer.post_process_mut().effect_mut::<ToneMapping>() {
+                tone_mapping.operator = match tone_mapping.operator {
+                    ToneMapOperator::Reinhard => ToneMapOperator::AcesFilmic,
+                    ToneMapOperator::AcesFilmic => ToneMapOperator::Reinhard,
+                };
+                println!("Tone mapping: {:?}", tone_mapping.operator);
+            }
+            return HandlerResult::Handled(None);
+        }
+        if !(0x31..=0x39).contains(&key) {
+            return HandlerResult::Continue;
+        }
+        let index = (key - 0x31) as usize;
+        let mut renderer = d3d11_clone.write().unwrap();
+        let effects = renderer.post_process().effects().entries();
+        if let Some((name, _)) = effects.get(index) {
+            let enabled = renderer.post_process_mut().effects_mut().toggle(name);
+            println!("post effect {}: {:?}", name, enabled);
+        }
+        HandlerResult::Handled(None)
+    }))).unwrap();
+
+    let actions = ActionMap::load(&config.input.bindings).unwrap_or_else(|e| {
+        println!("failed to load input bindings: {}", e);
+        ActionMap::new()
+    });
+    let input = Arc::new(RwLock::new(Input::new(actions)));
+    let input_clone = input.clone();
+    // 輸入狀態需要看到所有事件，優先於其他可能攔下事件的處理函式
+    let _input_subscription = platform.add_handler(window_id, EventHandler::typed(Box::new(move |event: &Event| {
+        input_clone.write().unwrap().handle_event(event);
+        HandlerResult::Continue
+    })).with_priority(100)).unwrap();
+
+    // 攔下 WM_CLOSE，由主迴圈詢問 DemoApp 後再依序釋放 renderer 與視窗
+    let close_requested = Rc::new(Cell::new(false));
+    let close_requested_clone = close_requested.clone();
+    let _close_subscription = platform.add_handler(window_id, EventHandler::typed(Box::new(move |event: &Event| {
+        if *event != Event::CloseRequested {
+            return HandlerResult::Continue;
+        }
+        close_requested_clone.set(true);
+        HandlerResult::Handled(None)
+    }))).unwrap();
+
+    let mut app = DemoApp::new(d3d11.clone(), input.clone());
+    let mut timestep = FixedTimestep::new(SystemClock::new(), Duration::from_secs_f64(1.0 / 60.0));
+    let mut frames = 0_u64;
+    loop {
+        // 先等 swap chain 可以接受下一幀再處理輸入，畫面上看到的結果才是最新的輸入
+        {
+            profile_scope!("wait_for_next_frame");
+            d3d11.read().unwrap().wait_for_next_frame();
+        }
+        {
+            profile_scope!("pump_events");
+            if !platform.pump_events() {
+                break;
+            }
+        }
+        if close_requested.take() && app.on_close_requested() {
+            break;
+        }
+        timestep.run_frame(&mut app);
+        input.write().unwrap().end_frame();
+        profiler::end_frame();
+        frames += 1;
+        if cli.frames.is_some_and(|limit| frames >= limit) {
+            println!("Rendered {} frames, exiting", frames);
+            platform.quit(0);
+        }
+    }
+    println!("GPU resources:\n{}", d3d11.read().unwrap().resource_stats());
+    finish_trace(cli);
+}
+
+#[cfg(windows)]
+fn driver_type(driver: DriverType) -> D3D_DRIVER_TYPE {
+    match driver {
+        DriverType::Hardware => D3D_DRIVER_TYPE_HARDWARE,
+        DriverType::Warp => D3D_DRIVER_TYPE_WARP,
+        DriverType::Reference => D3D_DRIVER_TYPE_REFERENCE,
+    }
+}
+
+/// renderer 需要 Direct3D 11，其他平台以 mock 平台跑一次視窗事件流程。
+#[cfg(not(windows))]
+fn main() {
+    use crate::platform::dispatch::{EventHandler, HandlerResult};
+    use crate::platform::mock::MockPlatform;
+    use crate::platform::{Position, Size};
+
+    let (cli, config, scene) = parse_command_line();
+    if cli.list_adapters {
+        println!("Adapter enumeration requires DXGI, which is only available on Windows.");
+        return;
+    }
+    println!("Direct3D 11 is only available on Windows, running the headless window backend.");
+    println!("renderer: {} driver, {}x MSAA, {}", config.renderer.driver, config.renderer.msaa_samples, config.renderer.output_format);
+    let present = config.renderer.present();
+    println!("mounts:\n{}", vfs::file_system());
+    println!("present: {} buffers, vsync {}, tearing {}, max frame latency {}", present.buffer_count, present.vsync, present.allow_tearing, present.max_frame_latency);
+    if let Some(scene) = &scene {
+        println!("scene: {} objects", scene.objects.len());
+        // 網格不能上傳，但仍然載入並檢查檔案內容
+        load_scene_assets(scene, &cli);
+    }
+    match load_scene_shaders() {
+        Ok(shaders) => {
+            for (path, data) in &shaders {
+                println!("shader: {} ({:?})", path.display(), data.stage);
+            }
+        }
+        Err(e) => eprintln!("failed to load the scene shaders: {}", e),
+    }
+    if let Some(frames) = cli.frames {
+        println!("frame limit: {}", frames);
+    }
+    let mut platform = MockPlatform::new();
+    let window_id = platform.create_window(&window_desc(&config)).unwrap();
+    // 第一次關閉要求會被否決，第二次才真的銷毀視窗
+    let mut vetoes_left = 1;
+    let _subscription = platform.add_handler(window_id, EventHandler::typed(Box::new(move |event: &Event| {
+        match event {
+            Event::Resized(size) => println!("resized to {}x{}", size.width, size.height),
+            Event::Moved(position) => println!("moved to {},{}", position.x, position.y),
+            Event::DpiChanged { dpi, scale_factor } => println!("dpi changed to {} ({}x)", dpi, scale_factor),
+            Event::CloseRequested if vetoes_left > 0 => {
+                vetoes_left -= 1;
+                println!("close vetoed");
+                return HandlerResult::Handled(None);
+            }
+            Event::CloseRequested => println!("close accepted"),
+            Event::Destroyed => println!("window destroyed"),
+            _ => {}
+        }
+        HandlerResult::Continue
+    }))).unwrap();
+
+    platform.inject_resize(window_id, Size { width: 1280, height: 720 });
+    platform.inject_move(window_id, Position { x: 40, y: 30 });
+    platform.inject_dpi_change(window_id, 144).unwrap();
+    platform.pump_events();
+    profiler::end_frame();
+    if let (Some(title), Some(description)) = (platform.window_title(window_id), describe_window(&platform, window_id)) {
+        println!("window \"{}\": {}", title, description);
+    }
+    platform.inject_close(window_id);
+    platform.inject_close(window_id);
+    // 最後一次處理事件後視窗已經銷毀，仍然要結束這一幀才會記錄
+    loop {
+        let running = platform.pump_events();
+        profiler::end_frame();
+        if !running {
+            break;
+        }
+    }
+    finish_trace(&cli);
+    let exit_code = platform.exit_code().unwrap_or(0);
+    println!("exit code: {}", exit_code);
+    std::process::exit(exit_code);
+}
+
+#[cfg(windows)]
+/// 以固定步長讓相機繞場景旋轉，render 時在前後兩個 update 狀態間插值。
+/// A/D 調整旋轉方向，W/S 與滾輪調整距離，R 回到初始位置（見 input.toml）。
+struct DemoApp {
+    renderer: Arc<RwLock<D3d11Renderer>>,
+    input: Arc<RwLock<Input>>,
+    previous_angle: f32,
+    angle: f32,
+    radius: f32,
+}
+
+#[cfg(windows)]
+impl DemoApp {
+    const ORBIT_SPEED: f32 = 0.25;
+    const ORBIT_RADIUS: f32 = 24.0;
+    const ZOOM_SPEED: f32 = 12.0;
+    const MIN_RADIUS: f32 = 4.0;
+    const MAX_RADIUS: f32 = 64.0;
+
+    fn new(renderer: Arc<RwLock<D3d11Renderer>>, input: Arc<RwLock<Input>>) -> Self {
+        Self { renderer, input, previous_angle: 0.0, angle: 0.0, radius: Self::ORBIT_RADIUS }
+    }
+}
+
+#[cfg(windows)]
+impl Game for DemoApp {
+    fn update(&mut self, time: &UpdateTime) {
+        profile_scope!("update");
+        let input = self.input.read().unwrap();
+        let step = time.step.as_secs_f32();
+        // 沒有按鍵時維持自動旋轉
+        let orbit = 1.0 + 3.0 * input.axis("orbit_left", "orbit_right");
+        let zoom = input.axis("zoom_in", "zoom_out");
+
+        self.previous_angle = self.angle;
+        self.angle += Self::ORBIT_SPEED * orbit * step;
+        self.radius = (self.radius + Self::ZOOM_SPEED * zoom * step).clamp(Self::MIN_RADIUS, Self::MAX_RADIUS);
+    }
+
+    fn render(&mut self, time: &FrameTime) {
+        profile_scope!("render");
+        // 單次觸發的輸入每幀都會被清除，而一幀不一定有 update，所以在 render 處理
+        {
+            let input = self.input.read().unwrap();
+            if input.was_action_pressed("reset_camera") {
+                self.previous_angle = 0.0;
+                self.angle = 0.0;
+                self.radius = Self::ORBIT_RADIUS;
+            }
+            self.radius = (self.radius - input.state.wheel_delta() * 2.0).clamp(Self::MIN_RADIUS, Self::MAX_RADIUS);
+        }
+
+        let angle = self.previous_angle + (self.angle - self.previous_angle) * time.interpolation;
+        let mut renderer = self.renderer.write().unwrap();
+        let camera = renderer.camera_mut();
+        camera.position = XMFLOAT3 { x: angle.sin() * self.radius, y: 10.0, z: -angle.cos() * self.radius };
+        drop(renderer);
+        draw_scene(&self.renderer);
+    }
+}
+
+/// 在背景執行緒平行讀取場景用到的網格檔案，等全部完成後回傳，載入失敗的檔案印出錯誤。
+/// 網格路徑相對於場景檔所在的目錄。
+fn load_scene_assets(scene: &SceneDesc, cli: &CliArgs) -> AssetRegistry {
+    let scene_dir = cli.scene.as_ref().and_then(|path| path.parent()).unwrap_or(Path::new(""));
+    let mut assets = AssetRegistry::new(scene_dir, ThreadPool::default_thread_count());
+    for file in scene.mesh_files() {
+        if let Err(e) = assets.load(file) {
+            eprintln!("failed to load mesh: {}", e);
+        }
+    }
+    for event in assets.wait(Duration::from_secs(30)) {
+        if let AssetEvent::Failed(_, e) = event {
+            eprintln!("failed to load mesh: {}", e);
+        }
+    }
+    let progress = assets.progress();
+    println!("assets: {}", progress);
+    if !progress.is_done() {
+        eprintln!("gave up waiting for {} assets", progress.pending());
+    }
+    assets
+}
+
+/// 透過資產登錄表讀取三角形場景的頂點與像素著色器，回傳 (路徑, 內容)。
+fn load_scene_shaders() -> Result<[(PathBuf, ShaderData); 2], String> {
+    let mut assets = AssetRegistry::new("", 2);
+    let ids = [assets.load("hlsl/triangle_vs.hlsl")?, assets.load("hlsl/triangle_ps.hlsl")?];
+    assets.wait(Duration::from_secs(30));
+    let shader = |id| match assets.state(id) {
+        Some(AssetState::Loaded(AssetData::Shader(data))) => Ok((assets.path(id).unwrap().to_path_buf(), data.clone())),
+        Some(AssetState::Failed(e)) => Err(e.clone()),
+        _ => Err(format!("{} did not finish loading", assets.path(id).unwrap().display())),
+    };
+    Ok([shader(ids[0])?, shader(ids[1])?])
+}
+
+#[cfg(windows)]
+/// 建立三角形場景的著色器與管線。
+fn create_scene_pipeline(renderer: &mut D3d11Renderer) -> Result<(), String> {
+    let [(vs_path, vs_data), (ps_path, ps_data)] = load_scene_shaders()?;
+    let device = renderer.device().clone();
+    let vertex_shader = renderer.resources_mut().create_shader(&device, &vs_path, &vs_data)?;
+    let pixel_shader = renderer.resources_mut().create_shader(&device, &ps_path, &ps_data)?;
+    renderer.create_scene_pipeline(vertex_shader, pixel_shader)
+}
+
+#[cfg(windows)]
+/// 畫出所有視窗，裝置失效時重建後等下一幀再畫，無法重建時結束程式。
+fn draw_scene(renderer: &RwLock<D3d11Renderer>) {
+    let result = renderer.read().unwrap().draw_scene();
+    if let Err(lost) = result {
+        renderer.write().unwrap().recover(lost).unwrap_or_else(|e| panic!("{}", e));
+    }
+}
+
+#[cfg(windows)]
+/// 把場景檔的物件加進 renderer，相同種類與大小的網格只建立一次。
+/// 網格檔案沒有載入成功的物件會略過。
+fn load_scene(renderer: &mut D3d11Renderer, scene: &SceneDesc, assets: &AssetRegistry) {
+    let material = Material::instanced_color(renderer.device());
+    let material = renderer.add_material(material);
+    let mut meshes: Vec<(MeshKind, f32, MeshId)> = vec![];
+    for object in &scene.objects {
+        let cached = meshes.iter().find(|(kind, size, _)| *kind == object.mesh && *size == object.size);
+        let mesh = match cached {
+            Some((_, _, mesh)) => *mesh,
+            None => {
+                let mesh = match &object.mesh {
+                    MeshKind::Cube => Mesh::cube(renderer.device(), object.size),
+                    MeshKind::File(path) => match assets.id(path).and_then(|id| assets.get(id)) {
+                        Some(AssetData::Mesh(data)) => Mesh::from_data(renderer.device(), data),
+                        _ => continue,
+                    },
+                };
+                let mesh = renderer.add_mesh(mesh);
+                meshes.push((object.mesh.clone(), object.size, mesh));
+                mesh
+            }
+        };
+        renderer.scene_mut().add(mesh, material, object.world(), object.color());
+    }
+}
+
+#[cfg(windows)]
+/// 32 x 32 個立方體，全部共用同一組 mesh + material，只需要一次 instanced draw call。
+fn populate_demo_scene(renderer: &mut D3d11Renderer) {
+    let cube = Mesh::cube(renderer.device(), 0.3);
+    let cube = renderer.add_mesh(cube);
+    let material = Material::instanced_color(renderer.device());
+    let material = renderer.add_material(material);
+
+    const GRID: i32 = 32;
+    for z in 0..GRID {
+        for x in 0..GRID {
+            let (fx, fz) = ((x - GRID / 2) as f32, (z - GRID / 2) as f32);
+            let world = XMMatrixMultiply(XMMatrixRotationY((fx + fz) * 0.2), &XMMatrixTranslation(fx, 0.0, fz));
+            let color = XMFLOAT4 { x: x as f32 / GRID as f32, y: 0.5, z: z as f32 / GRID as f32, w: 1.0 };
+            renderer.scene_mut().add(cube, material, world, color);
+        }
+    }
+
+    let forever = DebugOptions::for_seconds(f32::INFINITY);
+    // 只畫到近處的初始視錐，相機開始繞行後才看得到
+    let initial_view = Camera { far_z: 4.0, ..*renderer.camera() }.view_projection();
+    let mut debug_draw = renderer.debug_draw();
+    debug_draw.grid(XMFLOAT3 { x: -0.5, y: -0.5, z: -0.5 }, 1.0, GRID as u32, GRAY, forever);
+    debug_draw.axes(XMMatrixIdentity(), 3.0, DebugOptions { duration: f32::INFINITY, ..DebugOptions::on_top() });
+    debug_draw.aabb(XMFLOAT3 { x: -16.5, y: -0.5, z: -16.5 }, XMFLOAT3 { x: 15.5, y: 0.5, z: 15.5 }, YELLOW, forever);
+    // 中央的方塊沒有旋轉，標出它的有向包圍盒與外接球
+    debug_draw.obb(XMFLOAT3 { x: 0.3, y: 0.3, z: 0.3 }, XMMatrixIdentity(), WHITE, forever);
+    debug_draw.sphere(XMFLOAT3 { x: 0.0, y: 0.0, z: 0.0 }, 0.3 * 3.0_f32.sqrt(), WHITE, forever);
+    debug_draw.frustum(initial_view, WHITE, forever);
 }
@@ -0,0 +1,120 @@
+use directx_math::{XMFLOAT3, XMFLOAT4};
+use crate::assets::MeshData;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_R16_UINT;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct VertexPosColor {
+    pub position: XMFLOAT3,
+    pub color: XMFLOAT4,
+}
+
+/// 不可變的頂點 / 索引緩衝區。保留一份 CPU 端的資料，裝置失效後才能重建。
+pub struct Mesh {
+    pub vertex_buffer: ID3D11Buffer,
+    pub index_buffer: Option<ID3D11Buffer>,
+    pub vertex_count: u32,
+    pub index_count: u32,
+    vertices: Vec<VertexPosColor>,
+    indices: Vec<u16>,
+}
+
+impl Mesh {
+    pub fn new(device: &ID3D11Device, vertices: &[VertexPosColor], indices: &[u16]) -> Self {
+        let vertex_buffer = Self::create_buffer(device, vertices, D3D11_BIND_VERTEX_BUFFER);
+        let index_buffer = if indices.is_empty() {
+            None
+        } else {
+            Some(Self::create_buffer(device, indices, D3D11_BIND_INDEX_BUFFER))
+        };
+        Self {
+            vertex_buffer,
+            index_buffer,
+            vertex_count: vertices.len() as u32,
+            index_count: indices.len() as u32,
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        }
+    }
+
+    /// 在新的裝置上重新建立緩衝區。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        *self = Self::new(device, &self.vertices, &self.indices);
+    }
+
+    fn create_buffer<T: Copy>(device: &ID3D11Device, data: &[T], bind_flag: D3D11_BIND_FLAG) -> ID3D11Buffer {
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: size_of_val(data) as u32,
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: bind_flag.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+        let init_data = D3D11_SUBRESOURCE_DATA {
+            pSysMem: data.as_ptr() as _,
+            SysMemPitch: 0,
+            SysMemSlicePitch: 0,
+        };
+        let mut buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(&desc, Some(&init_data), Some(&mut buffer)).expect("CreateBuffer failed");
+        }
+        buffer.unwrap()
+    }
+
+    /// 以原點為中心、邊長 `2 * half_extent` 的立方體，每個角落不同顏色。
+    pub fn cube(device: &ID3D11Device, half_extent: f32) -> Self {
+        let h = half_extent;
+        let vertex = |x: f32, y: f32, z: f32, r: f32, g: f32, b: f32| VertexPosColor {
+            position: XMFLOAT3 { x: x * h, y: y * h, z: z * h },
+            color: XMFLOAT4 { x: r, y: g, z: b, w: 1.0 },
+        };
+        let vertices = [
+            vertex(-1.0, -1.0, -1.0, 0.0, 0.0, 0.0),
+            vertex(-1.0, 1.0, -1.0, 0.0, 1.0, 0.0),
+            vertex(1.0, 1.0, -1.0, 1.0, 1.0, 0.0),
+            vertex(1.0, -1.0, -1.0, 1.0, 0.0, 0.0),
+            vertex(-1.0, -1.0, 1.0, 0.0, 0.0, 1.0),
+            vertex(-1.0, 1.0, 1.0, 0.0, 1.0, 1.0),
+            vertex(1.0, 1.0, 1.0, 1.0, 1.0, 1.0),
+            vertex(1.0, -1.0, 1.0, 1.0, 0.0, 1.0),
+        ];
+        // 順時針為正面
+        let indices: [u16; 36] = [
+            0, 1, 2, 0, 2, 3, // front
+            4, 6, 5, 4, 7, 6, // back
+            4, 5, 1, 4, 1, 0, // left
+            3, 2, 6, 3, 6, 7, // right
+            1, 5, 6, 1, 6, 2, // top
+            4, 0, 3, 4, 3, 7, // bottom
+        ];
+        Self::new(device, &vertices, &indices)
+    }
+
+    /// 上傳 OBJ 載入的網格。OBJ 是右手座標系、逆時針為正面，轉成左手座標系與順時針正面。
+    pub fn from_data(device: &ID3D11Device, data: &MeshData) -> Self {
+        let vertices: Vec<VertexPosColor> = data.vertices.iter().map(|vertex| {
+            let [x, y, z] = vertex.position;
+            let [r, g, b, a] = vertex.color;
+            VertexPosColor {
+                position: XMFLOAT3 { x, y, z: -z },
+                color: XMFLOAT4 { x: r, y: g, z: b, w: a },
+            }
+        }).collect();
+        let indices: Vec<u16> = data.indices.chunks_exact(3).flat_map(|triangle| [triangle[0], triangle[2], triangle[1]]).collect();
+        Self::new(device, &vertices, &indices)
+    }
+
+    pub fn bind(&self, context: &ID3D11DeviceContext) {
+        let stride = size_of::<VertexPosColor>() as u32;
+        let offset = 0_u32;
+        unsafe {
+            context.IASetVertexBuffers(0, 1, Some(&Some(self.vertex_buffer.clone())), Some(&stride), Some(&offset));
+            if let Some(index_buffer) = &self.index_buffer {
+                context.IASetIndexBuffer(index_buffer, DXGI_FORMAT_R16_UINT, 0);
+            }
+        }
+    }
+}
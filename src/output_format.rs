@@ -0,0 +1,108 @@
+use windows::Win32::Graphics::Dxgi::Common::*;
+use crate::capabilities::RenderFormat;
+
+/// back buffer 的輸出格式。場景與後處理全程在線性空間計算，
+/// 只有最後寫入 back buffer 時才依這裡的設定做編碼。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 8 位元 UNORM，由 shader 做 sRGB 編碼。
+    Sdr8,
+    /// 8 位元 UNORM buffer 搭配 `_SRGB` render target view，由硬體做 sRGB 編碼。
+    Sdr8Srgb,
+    /// R10G10B10A2 的 SDR 輸出，由 shader 做 sRGB 編碼。
+    Sdr10,
+    /// R10G10B10A2 + Rec.2020 / ST.2084 (PQ) 色彩空間。
+    Hdr10,
+    /// R16G16B16A16_FLOAT + 線性 Rec.709 (scRGB) 色彩空間。
+    ScRgb,
+}
+
+/// 最後輸出 pass 在 shader 內要做的轉換，數值需與 `output_ps.hlsl` 一致。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum OutputEncoding {
+    Linear = 0,
+    Srgb = 1,
+    Pq = 2,
+    ScRgb = 3,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 5] = [
+        OutputFormat::Sdr8,
+        OutputFormat::Sdr8Srgb,
+        OutputFormat::Sdr10,
+        OutputFormat::Hdr10,
+        OutputFormat::ScRgb,
+    ];
+
+    pub fn swap_chain_format(&self) -> DXGI_FORMAT {
+        match self {
+            OutputFormat::Sdr8 | OutputFormat::Sdr8Srgb => DXGI_FORMAT_R8G8B8A8_UNORM,
+            OutputFormat::Sdr10 | OutputFormat::Hdr10 => DXGI_FORMAT_R10G10B10A2_UNORM,
+            OutputFormat::ScRgb => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        }
+    }
+
+    /// 對應 `swap_chain_format` 的平台無關格式，用來查詢裝置能力。
+    pub fn render_format(&self) -> RenderFormat {
+        match self {
+            OutputFormat::Sdr8 | OutputFormat::Sdr8Srgb => RenderFormat::Rgba8Unorm,
+            OutputFormat::Sdr10 | OutputFormat::Hdr10 => RenderFormat::Rgb10a2Unorm,
+            OutputFormat::ScRgb => RenderFormat::Rgba16Float,
+        }
+    }
+
+    /// back buffer render target view 的格式，sRGB 模式會與 swap chain 格式不同。
+    pub fn render_target_format(&self) -> DXGI_FORMAT {
+        match self {
+            OutputFormat::Sdr8Srgb => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            _ => self.swap_chain_format(),
+        }
+    }
+
+    pub fn color_space(&self) -> DXGI_COLOR_SPACE_TYPE {
+        match self {
+            OutputFormat::Hdr10 => DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+            OutputFormat::ScRgb => DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+            _ => DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709,
+        }
+    }
+
+    /// 設定檔與命令列使用的名稱，見 `config::OUTPUT_FORMAT_NAMES`。
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Sdr8 => "sdr8",
+            OutputFormat::Sdr8Srgb => "sdr8-srgb",
+            OutputFormat::Sdr10 => "sdr10",
+            OutputFormat::Hdr10 => "hdr10",
+            OutputFormat::ScRgb => "scrgb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        OutputFormat::ALL.into_iter().find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn encoding(&self) -> OutputEncoding {
+        match self {
+            OutputFormat::Sdr8Srgb => OutputEncoding::Linear,
+            OutputFormat::Sdr8 | OutputFormat::Sdr10 => OutputEncoding::Srgb,
+            OutputFormat::Hdr10 => OutputEncoding::Pq,
+            OutputFormat::ScRgb => OutputEncoding::ScRgb,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, OutputFormat::Hdr10 | OutputFormat::ScRgb)
+    }
+
+    /// 顯示器不支援 HDR 色彩空間時退回的格式。
+    pub fn sdr_fallback(&self) -> OutputFormat {
+        match self {
+            OutputFormat::Hdr10 => OutputFormat::Sdr10,
+            OutputFormat::ScRgb => OutputFormat::Sdr8Srgb,
+            other => *other,
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use super::event::{Event, EventDecoder};
+use super::{LPARAM, WPARAM};
+use crate::profiler::profile_scope;
+
+/// 處理函式的回傳值。`Handled` 會停止分派，並讓視窗程序不再交給 `DefWindowProcW`。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HandlerResult {
+    /// 繼續交給下一個處理函式，最後由預設行為處理。
+    #[default]
+    Continue,
+    /// 訊息已處理，`Some` 時以該值作為視窗程序的 `LRESULT`，`None` 時為 0。
+    Handled(Option<isize>),
+}
+
+enum Callback {
+    Message {
+        msg: u32,
+        handler: Box<dyn FnMut(WPARAM, LPARAM) -> HandlerResult>,
+    },
+    Event(Box<dyn FnMut(&Event) -> HandlerResult>),
+}
+
+pub struct EventHandler {
+    /// 數值大的先被呼叫，相同優先權依註冊順序。
+    pub priority: i32,
+    callback: Callback,
+}
+
+impl EventHandler {
+    /// 只接收編號為 `msg` 的原始訊息。
+    // 目前視窗都改用 `typed`，保留給解碼器還不認得的訊息
+    #[allow(dead_code)]
+    pub fn new(msg: u32, handler: Box<dyn FnMut(WPARAM, LPARAM) -> HandlerResult>) -> EventHandler {
+        EventHandler {
+            priority: 0,
+            callback: Callback::Message { msg, handler },
+        }
+    }
+
+    /// 接收所有能解碼成 `Event` 的訊息。
+    pub fn typed(handler: Box<dyn FnMut(&Event) -> HandlerResult>) -> EventHandler {
+        EventHandler {
+            priority: 0,
+            callback: Callback::Event(handler),
+        }
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn with_priority(mut self, priority: i32) -> EventHandler {
+        self.priority = priority;
+        self
+    }
+}
+
+struct Entry {
+    id: u64,
+    priority: i32,
+    callback: Rc<RefCell<Callback>>,
+}
+
+enum Pending {
+    Add(Entry),
+    Remove(u64),
+}
+
+#[derive(Default)]
+struct Handlers {
+    entries: Vec<Entry>,
+    next_id: u64,
+    /// 目前巢狀分派的層數，大於 0 時新增與移除先放進 `pending`。
+    depth: usize,
+    pending: Vec<Pending>,
+}
+
+impl Handlers {
+    fn insert(&mut self, entry: Entry) {
+        // 插在第一個優先權較低的處理函式之前，相同優先權維持註冊順序
+        let index = self.entries.iter()
+            .position(|e| e.priority < entry.priority)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.entries.retain(|entry| entry.id != id);
+        // 也可能是同一次分派中剛加入、還沒生效的處理函式
+        self.pending.retain(|pending| !matches!(pending, Pending::Add(entry) if entry.id == id));
+    }
+
+    fn is_pending_removal(&self, id: u64) -> bool {
+        self.pending.iter().any(|pending| matches!(pending, Pending::Remove(removed) if *removed == id))
+    }
+
+    fn apply_pending(&mut self) {
+        for pending in std::mem::take(&mut self.pending) {
+            match pending {
+                Pending::Add(entry) => self.insert(entry),
+                Pending::Remove(id) => self.remove(id),
+            }
+        }
+    }
+}
+
+/// `add_handler` 回傳的訂閱，drop 時移除對應的處理函式。
+#[must_use = "dropping the subscription removes the handler immediately"]
+pub struct Subscription {
+    handlers: Weak<RefCell<Handlers>>,
+    id: u64,
+}
+
+impl Subscription {
+    /// 放棄訂閱的所有權，處理函式會一直保留到視窗銷毀。
+    // 目前所有訂閱都跟著擁有者一起 drop，只有測試呼叫
+    #[allow(dead_code)]
+    pub fn detach(mut self) {
+        self.handlers = Weak::new();
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(handlers) = self.handlers.upgrade() {
+            let mut handlers = handlers.borrow_mut();
+            if handlers.depth > 0 {
+                handlers.pending.push(Pending::Remove(self.id));
+            } else {
+                handlers.remove(self.id);
+            }
+        }
+    }
+}
+
+/// 依優先權把訊息分派給已註冊的處理函式，Win32 與 mock 平台共用。
+/// 認得的訊息另外解碼成 `Event` 交給 typed 處理函式。
+///
+/// 呼叫處理函式時不持有任何借用，所以處理函式可以新增或移除處理函式，
+/// 也可以觸發巢狀分派（例如 `SetWindowPos` 同步送出 `WM_SIZE`）：
+/// - 分派期間的新增與移除會排進佇列，在最外層分派結束時套用；
+///   但已被移除的處理函式在同一次分派中不會再被呼叫。
+/// - 巢狀分派會跳過目前正在執行中的處理函式，避免 `FnMut` 被重複借用。
+#[derive(Default)]
+pub struct Dispatcher {
+    handlers: Rc<RefCell<Handlers>>,
+    decoder: RefCell<EventDecoder>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_handler(&self, handler: EventHandler) -> Subscription {
+        let mut handlers = self.handlers.borrow_mut();
+        let id = handlers.next_id;
+        handlers.next_id += 1;
+        let entry = Entry {
+            id,
+            priority: handler.priority,
+            callback: Rc::new(RefCell::new(handler.callback)),
+        };
+        if handlers.depth > 0 {
+            handlers.pending.push(Pending::Add(entry));
+        } else {
+            handlers.insert(entry);
+        }
+        Subscription {
+            handlers: Rc::downgrade(&self.handlers),
+            id,
+        }
+    }
+
+    /// 已生效的處理函式數量，不含分派期間排進佇列的新增。
+    #[cfg(test)]
+    pub fn handler_count(&self) -> usize {
+        self.handlers.borrow().entries.len()
+    }
+
+    /// 是否正在分派中，巢狀分派時也回傳 `true`。
+    #[cfg(test)]
+    pub fn is_dispatching(&self) -> bool {
+        self.handlers.borrow().depth > 0
+    }
+
+    /// 依優先權呼叫符合的處理函式，直到其中一個回傳 `Handled`。
+    /// 回傳 `Some(lresult)` 代表訊息已被處理，`None` 代表應交給預設行為。
+    pub fn dispatch(&self, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<isize> {
+        profile_scope!("dispatch_message");
+        let event = self.decoder.borrow_mut().decode(msg, wparam, lparam);
+        let snapshot: Vec<(u64, Rc<RefCell<Callback>>)> = {
+            let mut handlers = self.handlers.borrow_mut();
+            handlers.depth += 1;
+            handlers.entries.iter().map(|entry| (entry.id, entry.callback.clone())).collect()
+        };
+
+        let mut result = None;
+        for (id, callback) in snapshot {
+            if self.handlers.borrow().is_pending_removal(id) {
+                continue;
+            }
+            // 借用失敗代表這個處理函式正在外層分派中執行
+            let Ok(mut callback) = callback.try_borrow_mut() else {
+                continue;
+            };
+            let handler_result = match &mut *callback {
+                Callback::Message { msg: filter, handler } if *filter == msg => handler(wparam, lparam),
+                Callback::Event(handler) => match &event {
+                    Some(event) => handler(event),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if let HandlerResult::Handled(lresult) = handler_result {
+                result = Some(lresult.unwrap_or(0));
+                break;
+            }
+        }
+
+        let mut handlers = self.handlers.borrow_mut();
+        handlers.depth -= 1;
+        if handlers.depth == 0 {
+            handlers.apply_pending();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use super::*;
+    use crate::platform::messages::*;
+    use crate::platform::mock::MockPlatform;
+    use crate::platform::{Platform, Size, WindowDesc};
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    fn logging(log: &Log, name: &'static str, result: HandlerResult) -> EventHandler {
+        let log = log.clone();
+        EventHandler::typed(Box::new(move |_| {
+            log.borrow_mut().push(name);
+            result
+        }))
+    }
+
+    fn dispatch_close(dispatcher: &Dispatcher) -> Option<isize> {
+        dispatcher.dispatch(WM_CLOSE, WPARAM(0), LPARAM(0))
+    }
+
+    #[test]
+    fn higher_priority_runs_first_and_can_stop_dispatch() {
+        let dispatcher = Dispatcher::new();
+        let log = Log::default();
+        let _low = dispatcher.add_handler(logging(&log, "low", HandlerResult::Continue).with_priority(-1));
+        let _first = dispatcher.add_handler(logging(&log, "first", HandlerResult::Continue));
+        let _high = dispatcher.add_handler(logging(&log, "high", HandlerResult::Continue).with_priority(5));
+        let _second = dispatcher.add_handler(logging(&log, "second", HandlerResult::Handled(Some(7))));
+
+        assert_eq!(dispatch_close(&dispatcher), Some(7));
+        assert_eq!(*log.borrow(), ["high", "first", "second"]);
+    }
+
+    #[test]
+    fn message_handlers_only_see_their_message() {
+        let dispatcher = Dispatcher::new();
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        let _subscription = dispatcher.add_handler(EventHandler::new(WM_SIZE, Box::new(move |_, _| {
+            counted.set(counted.get() + 1);
+            HandlerResult::Handled(None)
+        })));
+        assert_eq!(dispatch_close(&dispatcher), None);
+        assert_eq!(dispatcher.dispatch(WM_SIZE, WPARAM(0), LPARAM(0)), Some(0));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn dropping_a_subscription_removes_the_handler() {
+        let dispatcher = Dispatcher::new();
+        let log = Log::default();
+        let subscription = dispatcher.add_handler(logging(&log, "a", HandlerResult::Continue));
+        dispatcher.add_handler(logging(&log, "b", HandlerResult::Continue)).detach();
+        drop(subscription);
+        assert_eq!(dispatcher.handler_count(), 1);
+        dispatch_close(&dispatcher);
+        assert_eq!(*log.borrow(), ["b"]);
+    }
+
+    #[test]
+    fn handlers_added_during_dispatch_start_with_the_next_message() {
+        let dispatcher = Rc::new(Dispatcher::new());
+        let log = Log::default();
+        let added = Rc::new(RefCell::new(vec![]));
+        let (weak, inner_log, added_in_handler) = (Rc::downgrade(&dispatcher), log.clone(), added.clone());
+        let _adder = dispatcher.add_handler(EventHandler::typed(Box::new(move |_| {
+            inner_log.borrow_mut().push("adder");
+            let dispatcher = weak.upgrade().unwrap();
+            assert!(dispatcher.is_dispatching());
+            // 優先權較高也不會在這一次分派中被呼叫
+            let before = dispatcher.handler_count();
+            added_in_handler.borrow_mut().push(dispatcher.add_handler(logging(&inner_log, "added", HandlerResult::Continue).with_priority(10)));
+            assert_eq!(dispatcher.handler_count(), before);
+            HandlerResult::Continue
+        })));
+
+        dispatch_close(&dispatcher);
+        assert_eq!(*log.borrow(), ["adder"]);
+        assert_eq!(dispatcher.handler_count(), 2);
+
+        log.borrow_mut().clear();
+        dispatch_close(&dispatcher);
+        assert_eq!(*log.borrow(), ["added", "adder"]);
+        assert_eq!(dispatcher.handler_count(), 3);
+    }
+
+    #[test]
+    fn handler_added_and_dropped_in_the_same_dispatch_never_runs() {
+        let dispatcher = Rc::new(Dispatcher::new());
+        let log = Log::default();
+        let (weak, inner_log) = (Rc::downgrade(&dispatcher), log.clone());
+        let _subscription = dispatcher.add_handler(EventHandler::typed(Box::new(move |_| {
+            let dispatcher = weak.upgrade().unwrap();
+            drop(dispatcher.add_handler(logging(&inner_log, "short-lived", HandlerResult::Continue)));
+            HandlerResult::Continue
+        })));
+        dispatch_close(&dispatcher);
+        dispatch_close(&dispatcher);
+        assert!(log.borrow().is_empty());
+        assert_eq!(dispatcher.handler_count(), 1);
+    }
+
+    #[test]
+    fn subscription_dropped_inside_its_own_handler() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        let log = Log::default();
+        // 只處理一次的處理函式：第一次被呼叫時 drop 自己的訂閱
+        let own: Rc<RefCell<Option<Subscription>>> = Rc::default();
+        let (inner_own, inner_log) = (own.clone(), log.clone());
+        let subscription = platform.add_handler(window, EventHandler::typed(Box::new(move |event| {
+            if let Event::Resized(_) = event {
+                inner_log.borrow_mut().push("once");
+                inner_own.borrow_mut().take();
+            }
+            HandlerResult::Continue
+        }))).unwrap();
+        *own.borrow_mut() = Some(subscription);
+        let _after = platform.add_handler(window, logging(&log, "after", HandlerResult::Continue)).unwrap();
+
+        platform.inject_resize(window, Size { width: 10, height: 10 });
+        platform.inject_resize(window, Size { width: 20, height: 20 });
+        platform.pump_events();
+        assert!(own.borrow().is_none());
+        assert_eq!(*log.borrow(), ["once", "after", "after"]);
+    }
+
+    #[test]
+    fn removed_handlers_are_skipped_in_the_same_dispatch() {
+        let dispatcher = Dispatcher::new();
+        let log = Log::default();
+        let victim: Rc<RefCell<Option<Subscription>>> = Rc::default();
+        let (inner_victim, inner_log) = (victim.clone(), log.clone());
+        let _remover = dispatcher.add_handler(EventHandler::typed(Box::new(move |_| {
+            inner_log.borrow_mut().push("remover");
+            inner_victim.borrow_mut().take();
+            HandlerResult::Continue
+        })));
+        *victim.borrow_mut() = Some(dispatcher.add_handler(logging(&log, "victim", HandlerResult::Continue)));
+
+        dispatch_close(&dispatcher);
+        assert_eq!(*log.borrow(), ["remover"]);
+        assert_eq!(dispatcher.handler_count(), 1);
+    }
+
+    #[test]
+    fn nested_dispatch_skips_running_handlers() {
+        let dispatcher = Rc::new(Dispatcher::new());
+        let log = Log::default();
+        let (weak, inner_log) = (Rc::downgrade(&dispatcher), log.clone());
+        // 與 SetWindowPos 相同：處理 WM_CLOSE 時同步送出 WM_SIZE
+        let _outer = dispatcher.add_handler(EventHandler::new(WM_CLOSE, Box::new(move |_, _| {
+            inner_log.borrow_mut().push("outer start");
+            let nested = weak.upgrade().unwrap().dispatch(WM_SIZE, WPARAM(0), LPARAM(0));
+            assert_eq!(nested, Some(3));
+            inner_log.borrow_mut().push("outer end");
+            HandlerResult::Continue
+        })).with_priority(1));
+        let inner_log = log.clone();
+        let _typed = dispatcher.add_handler(EventHandler::typed(Box::new(move |event| {
+            inner_log.borrow_mut().push(match event {
+                Event::Resized(_) => "typed resized",
+                _ => "typed other",
+            });
+            HandlerResult::Continue
+        })));
+        let _size = dispatcher.add_handler(logging(&log, "size", HandlerResult::Handled(Some(3))));
+
+        assert_eq!(dispatch_close(&dispatcher), Some(3));
+        assert_eq!(*log.borrow(), ["outer start", "typed resized", "size", "outer end", "typed other", "size"]);
+        assert!(!dispatcher.is_dispatching());
+    }
+
+    #[test]
+    fn changes_in_nested_dispatch_apply_after_the_outermost() {
+        let dispatcher = Rc::new(Dispatcher::new());
+        let log = Log::default();
+        let added = Rc::new(RefCell::new(vec![]));
+        let (weak, inner_log, inner_added) = (Rc::downgrade(&dispatcher), log.clone(), added.clone());
+        let _outer = dispatcher.add_handler(EventHandler::new(WM_CLOSE, Box::new(move |_, _| {
+            let dispatcher = weak.upgrade().unwrap();
+            let before = dispatcher.handler_count();
+            dispatcher.dispatch(WM_SIZE, WPARAM(0), LPARAM(0));
+            // 巢狀分派結束時外層仍在分派中，新增的處理函式還沒生效
+            assert_eq!(dispatcher.handler_count(), before);
+            HandlerResult::Continue
+        })));
+        let (weak, inner_log) = (Rc::downgrade(&dispatcher), inner_log.clone());
+        let _inner = dispatcher.add_handler(EventHandler::new(WM_SIZE, Box::new(move |_, _| {
+            inner_added.borrow_mut().push(weak.upgrade().unwrap().add_handler(logging(&inner_log, "added", HandlerResult::Continue)));
+            HandlerResult::Continue
+        })));
+
+        dispatch_close(&dispatcher);
+        assert_eq!(dispatcher.handler_count(), 3);
+        assert!(log.borrow().is_empty());
+        // 新的處理函式在巢狀的 WM_SIZE 與外層的 WM_CLOSE 各被呼叫一次
+        dispatch_close(&dispatcher);
+        assert_eq!(*log.borrow(), ["added", "added"]);
+        assert_eq!(dispatcher.handler_count(), 4);
+    }
+}
@@ -0,0 +1,335 @@
+use super::messages::*;
+use super::{dpi_to_scale_factor, hiword, loword, Position, Size, LPARAM, WPARAM};
+
+/// `WM_MOUSEWHEEL` 中一格滾輪的大小。
+pub const WHEEL_DELTA: f32 = 120.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+/// 鍵盤訊息 `LPARAM` 中除了虛擬鍵碼以外的資訊。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyInput {
+    /// Win32 虛擬鍵碼（`VK_*`）。
+    pub key: u32,
+    pub scan_code: u32,
+    pub extended: bool,
+    /// 按住不放時由系統自動重複送出的 `KeyDown`。
+    pub repeat: bool,
+    /// 這則訊息合併了幾次按鍵重複。
+    pub repeat_count: u32,
+    /// 來自 `WM_SYSKEYDOWN` / `WM_SYSKEYUP`，通常代表同時按著 Alt。
+    pub system: bool,
+}
+
+/// 從視窗訊息解碼後的事件，座標皆為 client 區域的有號座標。
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event {
+    Resized(Size),
+    Moved(Position),
+    KeyDown(KeyInput),
+    KeyUp(KeyInput),
+    Char(char),
+    MouseMove(Position),
+    MouseButton {
+        button: MouseButton,
+        state: ButtonState,
+        position: Position,
+    },
+    /// 以格為單位，正值代表向前（遠離使用者）或向右。
+    Wheel {
+        delta: f32,
+        horizontal: bool,
+    },
+    FocusGained,
+    FocusLost,
+    /// 使用者要求關閉視窗。處理函式回傳 `HandlerResult::Handled` 即可否決，
+    /// 否則視窗會由預設行為銷毀。
+    CloseRequested,
+    Destroyed,
+    /// 視窗移到縮放比例不同的顯示器，或使用者改了縮放設定。
+    /// 處理函式沒有攔下時，視窗會調整成系統建議的大小，接著收到 `Resized`。
+    DpiChanged {
+        dpi: u32,
+        scale_factor: f64,
+    },
+}
+
+fn signed_position(lparam: LPARAM) -> Position {
+    let value = lparam.0 as u32;
+    Position {
+        x: loword(value) as u16 as i16 as i32,
+        y: hiword(value) as u16 as i16 as i32,
+    }
+}
+
+fn key_input(wparam: WPARAM, lparam: LPARAM, system: bool, pressed: bool) -> KeyInput {
+    let flags = lparam.0 as u32;
+    KeyInput {
+        key: wparam.0 as u32,
+        scan_code: (flags >> 16) & 0xFF,
+        extended: flags & (1 << 24) != 0,
+        // bit 30 為按下前的狀態，KeyUp 永遠為 1，只對 KeyDown 有意義
+        repeat: pressed && flags & (1 << 30) != 0,
+        repeat_count: loword(flags),
+        system,
+    }
+}
+
+fn mouse_button(msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<Event> {
+    let (button, state) = match msg {
+        WM_LBUTTONDOWN => (MouseButton::Left, ButtonState::Pressed),
+        WM_LBUTTONUP => (MouseButton::Left, ButtonState::Released),
+        WM_RBUTTONDOWN => (MouseButton::Right, ButtonState::Pressed),
+        WM_RBUTTONUP => (MouseButton::Right, ButtonState::Released),
+        WM_MBUTTONDOWN => (MouseButton::Middle, ButtonState::Pressed),
+        WM_MBUTTONUP => (MouseButton::Middle, ButtonState::Released),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            let button = match hiword(wparam.0 as u32) {
+                XBUTTON1 => MouseButton::X1,
+                XBUTTON2 => MouseButton::X2,
+                _ => return None,
+            };
+            let state = if msg == WM_XBUTTONDOWN { ButtonState::Pressed } else { ButtonState::Released };
+            (button, state)
+        }
+        _ => return None,
+    };
+    Some(Event::MouseButton { button, state, position: signed_position(lparam) })
+}
+
+/// 把一則視窗訊息解碼成 `Event`，不認得或無法單獨解碼的訊息回傳 `None`。
+/// `WM_CHAR` 的 UTF-16 surrogate 需要前後兩則訊息才能組成字元，請改用 `EventDecoder`。
+pub fn decode(msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<Event> {
+    let event = match msg {
+        WM_SIZE => Event::Resized(Size {
+            width: loword(lparam.0 as u32) as i32,
+            height: hiword(lparam.0 as u32) as i32,
+        }),
+        WM_MOVE => Event::Moved(signed_position(lparam)),
+        WM_KEYDOWN => Event::KeyDown(key_input(wparam, lparam, false, true)),
+        WM_SYSKEYDOWN => Event::KeyDown(key_input(wparam, lparam, true, true)),
+        WM_KEYUP => Event::KeyUp(key_input(wparam, lparam, false, false)),
+        WM_SYSKEYUP => Event::KeyUp(key_input(wparam, lparam, true, false)),
+        WM_CHAR => Event::Char(char::from_u32(wparam.0 as u32)?),
+        WM_MOUSEMOVE => Event::MouseMove(signed_position(lparam)),
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => Event::Wheel {
+            delta: hiword(wparam.0 as u32) as u16 as i16 as f32 / WHEEL_DELTA,
+            horizontal: msg == WM_MOUSEHWHEEL,
+        },
+        WM_SETFOCUS => Event::FocusGained,
+        WM_KILLFOCUS => Event::FocusLost,
+        WM_CLOSE => Event::CloseRequested,
+        WM_DESTROY => Event::Destroyed,
+        // X 與 Y 方向的 DPI 在 Windows 上永遠相同
+        WM_DPICHANGED => {
+            let dpi = loword(wparam.0 as u32);
+            Event::DpiChanged { dpi, scale_factor: dpi_to_scale_factor(dpi) }
+        }
+        _ => return mouse_button(msg, wparam, lparam),
+    };
+    Some(event)
+}
+
+/// 會記住 high surrogate 的解碼器，讓 BMP 以外的字元（例如 emoji）組成單一個 `Event::Char`。
+#[derive(Debug, Default)]
+pub struct EventDecoder {
+    high_surrogate: Option<u16>,
+}
+
+impl EventDecoder {
+    pub fn decode(&mut self, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<Event> {
+        if msg != WM_CHAR {
+            return decode(msg, wparam, lparam);
+        }
+        let unit = wparam.0 as u16;
+        match unit {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                char::decode_utf16([high, unit]).next()?.ok().map(Event::Char)
+            }
+            _ => {
+                self.high_surrogate = None;
+                decode(msg, wparam, lparam)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lparam(low: u16, high: u16) -> LPARAM {
+        LPARAM(((high as u32) << 16 | low as u32) as i32 as isize)
+    }
+
+    fn wparam(low: u16, high: u16) -> WPARAM {
+        WPARAM(((high as u32) << 16 | low as u32) as usize)
+    }
+
+    fn no_params(msg: u32) -> Option<Event> {
+        decode(msg, WPARAM(0), LPARAM(0))
+    }
+
+    #[test]
+    fn resized_is_unsigned() {
+        assert_eq!(decode(WM_SIZE, WPARAM(0), lparam(1920, 1080)), Some(Event::Resized(Size { width: 1920, height: 1080 })));
+        assert_eq!(decode(WM_SIZE, WPARAM(0), lparam(0xFFFF, 0x8000)), Some(Event::Resized(Size { width: 65535, height: 32768 })));
+    }
+
+    #[test]
+    fn moved_is_signed() {
+        // 視窗在主螢幕左上方的顯示器上時座標為負
+        let position = Position { x: -1920, y: -5 };
+        assert_eq!(decode(WM_MOVE, WPARAM(0), lparam(-1920_i16 as u16, -5_i16 as u16)), Some(Event::Moved(position)));
+    }
+
+    #[test]
+    fn mouse_move_is_signed() {
+        // 拖曳到 client 區域外時座標可能為負
+        assert_eq!(decode(WM_MOUSEMOVE, WPARAM(0), lparam(-3_i16 as u16, 40)), Some(Event::MouseMove(Position { x: -3, y: 40 })));
+        assert_eq!(decode(WM_MOUSEMOVE, WPARAM(0), lparam(100, -200_i16 as u16)), Some(Event::MouseMove(Position { x: 100, y: -200 })));
+    }
+
+    #[test]
+    fn mouse_buttons() {
+        let position = Position { x: -1, y: 2 };
+        let params = lparam(-1_i16 as u16, 2);
+        for (msg, button, state) in [
+            (WM_LBUTTONDOWN, MouseButton::Left, ButtonState::Pressed),
+            (WM_LBUTTONUP, MouseButton::Left, ButtonState::Released),
+            (WM_RBUTTONDOWN, MouseButton::Right, ButtonState::Pressed),
+            (WM_RBUTTONUP, MouseButton::Right, ButtonState::Released),
+            (WM_MBUTTONDOWN, MouseButton::Middle, ButtonState::Pressed),
+            (WM_MBUTTONUP, MouseButton::Middle, ButtonState::Released),
+        ] {
+            assert_eq!(decode(msg, WPARAM(0), params), Some(Event::MouseButton { button, state, position }));
+        }
+    }
+
+    #[test]
+    fn x_buttons_come_from_the_high_word() {
+        let position = Position { x: 10, y: 20 };
+        // LOWORD(wparam) 是同時按著的按鍵，不影響是哪一個 X 按鈕
+        let x1 = decode(WM_XBUTTONDOWN, wparam(0x0020, XBUTTON1 as u16), lparam(10, 20));
+        assert_eq!(x1, Some(Event::MouseButton { button: MouseButton::X1, state: ButtonState::Pressed, position }));
+        let x2 = decode(WM_XBUTTONUP, wparam(0x0040, XBUTTON2 as u16), lparam(10, 20));
+        assert_eq!(x2, Some(Event::MouseButton { button: MouseButton::X2, state: ButtonState::Released, position }));
+        assert_eq!(decode(WM_XBUTTONDOWN, wparam(0, 0), lparam(10, 20)), None);
+        assert_eq!(decode(WM_XBUTTONDOWN, wparam(0, 3), lparam(10, 20)), None);
+    }
+
+    #[test]
+    fn wheel_delta_sign() {
+        assert_eq!(decode(WM_MOUSEWHEEL, wparam(0, 120), LPARAM(0)), Some(Event::Wheel { delta: 1.0, horizontal: false }));
+        assert_eq!(decode(WM_MOUSEWHEEL, wparam(0, -240_i16 as u16), LPARAM(0)), Some(Event::Wheel { delta: -2.0, horizontal: false }));
+        // 高精度滾輪一次可能不到一格
+        assert_eq!(decode(WM_MOUSEHWHEEL, wparam(0x0008, -60_i16 as u16), LPARAM(0)), Some(Event::Wheel { delta: -0.5, horizontal: true }));
+    }
+
+    #[test]
+    fn key_down_fields() {
+        // 重複 1 次、scan code 0x1E（A）
+        let Some(Event::KeyDown(input)) = decode(WM_KEYDOWN, WPARAM(0x41), lparam(1, 0x001E)) else {
+            panic!("expected KeyDown");
+        };
+        assert_eq!(input, KeyInput { key: 0x41, scan_code: 0x1E, extended: false, repeat: false, repeat_count: 1, system: false });
+
+        // 右 Ctrl 是 extended key，Alt 組合鍵來自 WM_SYSKEYDOWN
+        let Some(Event::KeyDown(input)) = decode(WM_SYSKEYDOWN, WPARAM(0x11), lparam(1, 0x011D)) else {
+            panic!("expected KeyDown");
+        };
+        assert!(input.extended);
+        assert!(input.system);
+        assert_eq!(input.scan_code, 0x1D);
+    }
+
+    #[test]
+    fn key_repeat_bit() {
+        // bit 30 表示按下前就已經是按著的狀態
+        let Some(Event::KeyDown(input)) = decode(WM_KEYDOWN, WPARAM(0x41), lparam(3, 0x401E)) else {
+            panic!("expected KeyDown");
+        };
+        assert!(input.repeat);
+        assert_eq!(input.repeat_count, 3);
+
+        // KeyUp 的 bit 30 永遠為 1，不算重複
+        let Some(Event::KeyUp(input)) = decode(WM_KEYUP, WPARAM(0x41), lparam(1, 0xC01E)) else {
+            panic!("expected KeyUp");
+        };
+        assert!(!input.repeat);
+        assert!(!input.system);
+        let Some(Event::KeyUp(input)) = decode(WM_SYSKEYUP, WPARAM(0x12), lparam(1, 0xC038)) else {
+            panic!("expected KeyUp");
+        };
+        assert!(input.system);
+    }
+
+    #[test]
+    fn char_in_the_basic_plane() {
+        assert_eq!(decode(WM_CHAR, WPARAM('a' as usize), LPARAM(0)), Some(Event::Char('a')));
+        assert_eq!(decode(WM_CHAR, WPARAM('中' as usize), LPARAM(0)), Some(Event::Char('中')));
+        // 單獨的 surrogate 不是字元
+        assert_eq!(decode(WM_CHAR, WPARAM(0xD83D), LPARAM(0)), None);
+    }
+
+    #[test]
+    fn surrogate_pairs_make_one_char() {
+        let mut decoder = EventDecoder::default();
+        let mut units = [0_u16; 2];
+        '😀'.encode_utf16(&mut units);
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(units[0] as usize), LPARAM(0)), None);
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(units[1] as usize), LPARAM(0)), Some(Event::Char('😀')));
+        // 配對用完後，單獨的 low surrogate 被丟掉
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(units[1] as usize), LPARAM(0)), None);
+    }
+
+    #[test]
+    fn unpaired_high_surrogate_is_dropped() {
+        let mut decoder = EventDecoder::default();
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(0xD83D), LPARAM(0)), None);
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM('x' as usize), LPARAM(0)), Some(Event::Char('x')));
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(0xDE00), LPARAM(0)), None);
+        // 其他訊息不影響等待中的 high surrogate
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(0xD83D), LPARAM(0)), None);
+        assert_eq!(decoder.decode(WM_SETFOCUS, WPARAM(0), LPARAM(0)), Some(Event::FocusGained));
+        assert_eq!(decoder.decode(WM_CHAR, WPARAM(0xDE00), LPARAM(0)), Some(Event::Char('😀')));
+    }
+
+    #[test]
+    fn focus_close_and_destroy() {
+        assert_eq!(no_params(WM_SETFOCUS), Some(Event::FocusGained));
+        assert_eq!(no_params(WM_KILLFOCUS), Some(Event::FocusLost));
+        assert_eq!(no_params(WM_CLOSE), Some(Event::CloseRequested));
+        assert_eq!(no_params(WM_DESTROY), Some(Event::Destroyed));
+    }
+
+    #[test]
+    fn dpi_changed() {
+        assert_eq!(decode(WM_DPICHANGED, wparam(144, 144), LPARAM(0)), Some(Event::DpiChanged { dpi: 144, scale_factor: 1.5 }));
+        assert_eq!(decode(WM_DPICHANGED, wparam(96, 96), LPARAM(0)), Some(Event::DpiChanged { dpi: 96, scale_factor: 1.0 }));
+    }
+
+    #[test]
+    fn unknown_messages() {
+        assert_eq!(no_params(WM_QUIT), None);
+        assert_eq!(no_params(0x7FFF), None);
+    }
+}
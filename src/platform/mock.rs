@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, VecDeque};
+use super::dispatch::{Dispatcher, EventHandler, Subscription};
+use super::messages::*;
+use super::event::{decode, Event};
+use super::{dpi_to_scale_factor, Platform, Position, Size, WindowDesc, WindowId, DEFAULT_DPI, LPARAM, WPARAM};
+
+/// 未指定尺寸時使用的預設 client 區域大小，單位為實體像素。
+pub const DEFAULT_SIZE: Size = Size { width: 800, height: 600 };
+
+struct MockWindow {
+    title: String,
+    position: Position,
+    size: Size,
+    dpi: u32,
+    dispatcher: Dispatcher,
+}
+
+/// 不建立作業系統視窗的平台實作。事件以 `inject_*` 放進佇列，
+/// 在 `pump_events` 時依 Win32 的預設行為處理並分派給處理函式。
+#[derive(Default)]
+pub struct MockPlatform {
+    windows: BTreeMap<WindowId, MockWindow>,
+    next_id: u64,
+    queue: VecDeque<(WindowId, u32, WPARAM, LPARAM)>,
+    exit_code: Option<i32>,
+}
+
+fn pack_lparam(low: i32, high: i32) -> LPARAM {
+    LPARAM((((high as u32 & 0xFFFF) << 16) | (low as u32 & 0xFFFF)) as isize)
+}
+
+impl MockPlatform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub fn window_count(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn window_title(&self, window: WindowId) -> Option<&str> {
+        self.windows.get(&window).map(|w| w.title.as_str())
+    }
+
+    #[cfg(test)]
+    pub fn pending_events(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// 放入任意的原始訊息。
+    pub fn inject(&mut self, window: WindowId, msg: u32, wparam: WPARAM, lparam: LPARAM) {
+        self.queue.push_back((window, msg, wparam, lparam));
+    }
+
+    /// 模擬使用者調整視窗大小，處理時會更新 `window_size` 再送出 `WM_SIZE`。
+    pub fn inject_resize(&mut self, window: WindowId, size: Size) {
+        self.inject(window, WM_SIZE, WPARAM(0), pack_lparam(size.width, size.height));
+    }
+
+    /// 模擬視窗移動，`WM_MOVE` 的座標為有號 16 位元。
+    pub fn inject_move(&mut self, window: WindowId, position: Position) {
+        self.inject(window, WM_MOVE, WPARAM(0), pack_lparam(position.x, position.y));
+    }
+
+    /// 模擬按下關閉按鈕，沒有處理函式攔下 `CloseRequested` 時會銷毀視窗。
+    pub fn inject_close(&mut self, window: WindowId) {
+        self.inject(window, WM_CLOSE, WPARAM(0), LPARAM(0));
+    }
+
+    /// 模擬視窗移到 DPI 不同的顯示器。沒有處理函式攔下 `DpiChanged` 時，
+    /// 與 Win32 建議的做法相同，client 區域依新舊 DPI 的比例縮放並送出 `WM_SIZE`。
+    /// DPI 必須在 1 到 65535 之間，`WM_DPICHANGED` 只有 16 位元可以放。
+    pub fn inject_dpi_change(&mut self, window: WindowId, dpi: u32) -> Result<(), String> {
+        if dpi == 0 || dpi > 0xFFFF {
+            return Err(format!("invalid dpi {}", dpi));
+        }
+        self.inject(window, WM_DPICHANGED, WPARAM(((dpi << 16) | dpi) as usize), LPARAM(0));
+        Ok(())
+    }
+
+    fn process(&mut self, window: WindowId, msg: u32, wparam: WPARAM, lparam: LPARAM) {
+        let Some(target) = self.windows.get_mut(&window) else {
+            return;
+        };
+        match decode(msg, wparam, lparam) {
+            Some(Event::Resized(size)) => target.size = size,
+            Some(Event::Moved(position)) => target.position = position,
+            _ => {}
+        }
+        let handled = target.dispatcher.dispatch(msg, wparam, lparam).is_some();
+
+        // 以 `inject` 直接放入的 0 DPI 不套用，避免之後的縮放除以 0
+        if let Some(Event::DpiChanged { dpi, .. }) = decode(msg, wparam, lparam)
+            && dpi != 0
+        {
+            let old_dpi = std::mem::replace(&mut target.dpi, dpi);
+            if !handled && dpi != old_dpi {
+                let scale = |value: i32| (value as i64 * dpi as i64 / old_dpi as i64) as i32;
+                let size = Size { width: scale(target.size.width), height: scale(target.size.height) };
+                // 建議大小套用後同步送出 WM_SIZE，排在其他已注入的事件之前
+                self.queue.push_front((window, WM_SIZE, WPARAM(0), pack_lparam(size.width, size.height)));
+            }
+        }
+
+        // 與 DefWindowProcW 相同：沒有處理函式攔下 WM_CLOSE 時銷毀視窗
+        if msg == WM_CLOSE && !handled {
+            self.destroy_window(window);
+        }
+    }
+
+    fn destroy_window(&mut self, window: WindowId) {
+        if let Some(target) = self.windows.remove(&window) {
+            target.dispatcher.dispatch(WM_DESTROY, WPARAM(0), LPARAM(0));
+        }
+        // 與 WndClass 相同：最後一個視窗銷毀時結束應用程式
+        if self.windows.is_empty() {
+            self.quit(0);
+        }
+    }
+}
+
+impl Platform for MockPlatform {
+    fn create_window(&mut self, desc: &WindowDesc) -> Result<WindowId, String> {
+        let size = desc.size.map(|size| size.to_physical(dpi_to_scale_factor(DEFAULT_DPI))).unwrap_or(DEFAULT_SIZE);
+        if size.width < 0 || size.height < 0 {
+            return Err(format!("invalid window size {}x{}", size.width, size.height));
+        }
+        self.next_id += 1;
+        let id = WindowId(self.next_id);
+        self.windows.insert(id, MockWindow {
+            title: desc.title.clone(),
+            position: desc.position.unwrap_or(Position { x: 0, y: 0 }),
+            size,
+            dpi: DEFAULT_DPI,
+            dispatcher: Dispatcher::new(),
+        });
+        Ok(id)
+    }
+
+    fn window_size(&self, window: WindowId) -> Option<Size> {
+        self.windows.get(&window).map(|w| w.size)
+    }
+
+    fn window_position(&self, window: WindowId) -> Option<Position> {
+        self.windows.get(&window).map(|w| w.position)
+    }
+
+    fn scale_factor(&self, window: WindowId) -> Option<f64> {
+        self.windows.get(&window).map(|w| dpi_to_scale_factor(w.dpi))
+    }
+
+    fn add_handler(&self, window: WindowId, handler: EventHandler) -> Option<Subscription> {
+        self.windows.get(&window).map(|target| target.dispatcher.add_handler(handler))
+    }
+
+    fn close_window(&mut self, window: WindowId) {
+        self.destroy_window(window);
+    }
+
+    fn pump_events(&mut self) -> bool {
+        while self.exit_code.is_none() {
+            let Some((window, msg, wparam, lparam)) = self.queue.pop_front() else {
+                break;
+            };
+            self.process(window, msg, wparam, lparam);
+        }
+        self.exit_code.is_none()
+    }
+
+    fn quit(&mut self, exit_code: i32) {
+        if self.exit_code.is_none() {
+            self.exit_code = Some(exit_code);
+        }
+    }
+
+    fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::platform::dispatch::HandlerResult;
+
+    // 記錄收到的事件，`veto_close` 為 true 時攔下 `CloseRequested`
+    fn record(platform: &MockPlatform, window: WindowId, veto_close: bool) -> (Rc<RefCell<Vec<Event>>>, Subscription) {
+        let events = Rc::new(RefCell::new(vec![]));
+        let recorded = events.clone();
+        let subscription = platform.add_handler(window, EventHandler::typed(Box::new(move |event: &Event| {
+            recorded.borrow_mut().push(*event);
+            match event {
+                Event::CloseRequested if veto_close => HandlerResult::Handled(None),
+                _ => HandlerResult::Continue,
+            }
+        }))).unwrap();
+        (events, subscription)
+    }
+
+    #[test]
+    fn dispatches_injected_events_in_order() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        let (events, _subscription) = record(&platform, window, false);
+
+        platform.inject_move(window, Position { x: -20, y: 30 });
+        platform.inject(window, WM_SETFOCUS, WPARAM(0), LPARAM(0));
+        assert_eq!(platform.pending_events(), 2);
+        assert!(events.borrow().is_empty());
+
+        assert!(platform.pump_events());
+        assert_eq!(platform.pending_events(), 0);
+        assert_eq!(*events.borrow(), [Event::Moved(Position { x: -20, y: 30 }), Event::FocusGained]);
+        assert_eq!(platform.window_position(window), Some(Position { x: -20, y: 30 }));
+    }
+
+    #[test]
+    fn events_for_unknown_windows_are_dropped() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        let (events, _subscription) = record(&platform, window, false);
+        platform.inject_resize(WindowId(99), Size { width: 1, height: 1 });
+        assert!(platform.pump_events());
+        assert!(events.borrow().is_empty());
+        assert!(platform.add_handler(WindowId(99), EventHandler::typed(Box::new(|_| HandlerResult::Continue))).is_none());
+    }
+
+    #[test]
+    fn resize_updates_window_size_before_dispatch() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test").size(640.0, 480.0)).unwrap();
+        assert_eq!(platform.window_size(window), Some(Size { width: 640, height: 480 }));
+        let default = platform.create_window(&WindowDesc::new("default")).unwrap();
+        assert_eq!(platform.window_size(default), Some(DEFAULT_SIZE));
+
+        platform.inject_resize(window, Size { width: 1280, height: 720 });
+        platform.pump_events();
+        assert_eq!(platform.window_size(window), Some(Size { width: 1280, height: 720 }));
+    }
+
+    #[test]
+    fn unhandled_dpi_change_scales_the_window() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test").size(800.0, 600.0)).unwrap();
+        let (events, _subscription) = record(&platform, window, false);
+
+        platform.inject_dpi_change(window, 144).unwrap();
+        platform.inject_move(window, Position { x: 5, y: 5 });
+        platform.pump_events();
+
+        assert_eq!(platform.scale_factor(window), Some(1.5));
+        assert_eq!(platform.window_size(window), Some(Size { width: 1200, height: 900 }));
+        // 建議大小的 WM_SIZE 排在之前注入的事件前面
+        assert_eq!(*events.borrow(), [
+            Event::DpiChanged { dpi: 144, scale_factor: 1.5 },
+            Event::Resized(Size { width: 1200, height: 900 }),
+            Event::Moved(Position { x: 5, y: 5 }),
+        ]);
+    }
+
+    #[test]
+    fn handled_dpi_change_keeps_the_window_size() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test").size(800.0, 600.0)).unwrap();
+        let _subscription = platform.add_handler(window, EventHandler::new(WM_DPICHANGED, Box::new(|_, _| HandlerResult::Handled(None)))).unwrap();
+        platform.inject_dpi_change(window, 192).unwrap();
+        platform.pump_events();
+        assert_eq!(platform.scale_factor(window), Some(2.0));
+        assert_eq!(platform.window_size(window), Some(Size { width: 800, height: 600 }));
+    }
+
+    #[test]
+    fn rejects_invalid_dpi() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        assert!(platform.inject_dpi_change(window, 0).is_err());
+        assert!(platform.inject_dpi_change(window, 0x10000).is_err());
+        assert_eq!(platform.pending_events(), 0);
+
+        // 直接注入的 0 DPI 被忽略，之後的 DPI 變更仍然正常縮放
+        platform.inject(window, WM_DPICHANGED, WPARAM(0), LPARAM(0));
+        platform.inject_dpi_change(window, 192).unwrap();
+        platform.pump_events();
+        assert_eq!(platform.scale_factor(window), Some(2.0));
+        assert_eq!(platform.window_size(window), Some(Size { width: 1600, height: 1200 }));
+    }
+
+    #[test]
+    fn vetoed_close_keeps_the_window() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        let (events, _subscription) = record(&platform, window, true);
+        platform.inject_close(window);
+        assert!(platform.pump_events());
+        assert_eq!(platform.window_count(), 1);
+        assert_eq!(platform.exit_code(), None);
+        assert_eq!(*events.borrow(), [Event::CloseRequested]);
+    }
+
+    #[test]
+    fn closing_the_last_window_quits_and_drops_later_events() {
+        let mut platform = MockPlatform::new();
+        let first = platform.create_window(&WindowDesc::new("first")).unwrap();
+        let second = platform.create_window(&WindowDesc::new("second")).unwrap();
+        let (first_events, _first) = record(&platform, first, false);
+        let (second_events, _second) = record(&platform, second, false);
+
+        platform.inject_close(first);
+        assert!(platform.pump_events());
+        assert_eq!(*first_events.borrow(), [Event::CloseRequested, Event::Destroyed]);
+        assert_eq!(platform.window_count(), 1);
+        assert_eq!(platform.window_title(second), Some("second"));
+
+        platform.inject_close(second);
+        platform.inject_resize(second, Size { width: 1, height: 1 });
+        assert!(!platform.pump_events());
+        assert_eq!(*second_events.borrow(), [Event::CloseRequested, Event::Destroyed]);
+        assert_eq!(platform.exit_code(), Some(0));
+        // 結束後不再處理剩下的事件
+        assert_eq!(platform.pending_events(), 1);
+        assert!(!platform.pump_events());
+    }
+
+    #[test]
+    fn close_window_skips_close_requested() {
+        let mut platform = MockPlatform::new();
+        let window = platform.create_window(&WindowDesc::new("test")).unwrap();
+        let (events, _subscription) = record(&platform, window, true);
+        platform.close_window(window);
+        assert_eq!(*events.borrow(), [Event::Destroyed]);
+        assert_eq!(platform.window_size(window), None);
+        assert_eq!(platform.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn first_quit_exit_code_wins() {
+        let mut platform = MockPlatform::new();
+        platform.quit(3);
+        platform.quit(4);
+        assert_eq!(platform.exit_code(), Some(3));
+        assert!(!platform.pump_events());
+    }
+}
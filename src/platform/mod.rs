@@ -0,0 +1,174 @@
+//! 視窗與事件的平台抽象層。
+//!
+//! `Platform` 描述應用程式需要的視窗操作，Win32 版本在 `win32` 模組，
+//! `mock` 模組則是不需要作業系統視窗的實作，可以注入合成事件。
+//! 這個模組本身與 `dispatch`、`mock` 不依賴 Win32，任何平台都能編譯。
+
+pub mod dispatch;
+pub mod event;
+#[cfg(any(test, not(windows)))]
+pub mod mock;
+#[cfg(windows)]
+pub mod win32;
+
+use dispatch::{EventHandler, Subscription};
+
+#[cfg(windows)]
+pub use windows::Win32::Foundation::{LPARAM, WPARAM};
+
+/// 與 Win32 `WPARAM` 相同配置的訊息參數，讓事件分派邏輯在非 Windows 平台也能編譯。
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct WPARAM(pub usize);
+
+/// 與 Win32 `LPARAM` 相同配置的訊息參數。
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct LPARAM(pub isize);
+
+/// 平台無關程式碼會用到的視窗訊息編號，數值與 Win32 相同。
+pub mod messages {
+    pub const WM_DESTROY: u32 = 0x0002;
+    pub const WM_MOVE: u32 = 0x0003;
+    pub const WM_SIZE: u32 = 0x0005;
+    pub const WM_SETFOCUS: u32 = 0x0007;
+    pub const WM_KILLFOCUS: u32 = 0x0008;
+    pub const WM_CLOSE: u32 = 0x0010;
+    #[cfg(test)]
+    pub const WM_QUIT: u32 = 0x0012;
+    pub const WM_KEYDOWN: u32 = 0x0100;
+    pub const WM_KEYUP: u32 = 0x0101;
+    pub const WM_CHAR: u32 = 0x0102;
+    pub const WM_SYSKEYDOWN: u32 = 0x0104;
+    pub const WM_SYSKEYUP: u32 = 0x0105;
+    pub const WM_MOUSEMOVE: u32 = 0x0200;
+    pub const WM_LBUTTONDOWN: u32 = 0x0201;
+    pub const WM_LBUTTONUP: u32 = 0x0202;
+    pub const WM_RBUTTONDOWN: u32 = 0x0204;
+    pub const WM_RBUTTONUP: u32 = 0x0205;
+    pub const WM_MBUTTONDOWN: u32 = 0x0207;
+    pub const WM_MBUTTONUP: u32 = 0x0208;
+    pub const WM_MOUSEWHEEL: u32 = 0x020A;
+    pub const WM_XBUTTONDOWN: u32 = 0x020B;
+    pub const WM_XBUTTONUP: u32 = 0x020C;
+    pub const WM_MOUSEHWHEEL: u32 = 0x020E;
+    pub const WM_DPICHANGED: u32 = 0x02E0;
+
+    /// `WM_XBUTTONDOWN` / `WM_XBUTTONUP` 的 `HIWORD(wparam)`。
+    pub const XBUTTON1: u32 = 0x0001;
+    pub const XBUTTON2: u32 = 0x0002;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// 以實體像素為單位的尺寸，swap chain 與 `WM_SIZE` 都使用這個單位。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+pub type PhysicalSize = Size;
+
+/// 以 96 DPI 為基準的邏輯尺寸，在不同縮放比例的顯示器上看起來一樣大。
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LogicalSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl LogicalSize {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self { width, height }
+    }
+
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize {
+        Size {
+            width: (self.width * scale_factor).round() as i32,
+            height: (self.height * scale_factor).round() as i32,
+        }
+    }
+}
+
+/// 縮放比例 100% 時的 DPI。
+pub const DEFAULT_DPI: u32 = 96;
+
+/// DPI 對應的縮放比例，例如 144 DPI 為 1.5。
+pub fn dpi_to_scale_factor(dpi: u32) -> f64 {
+    dpi as f64 / DEFAULT_DPI as f64
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(pub u64);
+
+/// 建立視窗所需的參數，未指定的位置與尺寸由平台決定。
+#[derive(Debug, Clone)]
+pub struct WindowDesc {
+    pub title: String,
+    pub position: Option<Position>,
+    /// client 區域的邏輯尺寸，建立時依視窗所在顯示器的縮放比例換算成實體像素。
+    pub size: Option<LogicalSize>,
+}
+
+impl WindowDesc {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            position: None,
+            size: None,
+        }
+    }
+
+    pub fn position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some(Position { x, y });
+        self
+    }
+
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.size = Some(LogicalSize { width, height });
+        self
+    }
+}
+
+pub trait Platform {
+    fn create_window(&mut self, desc: &WindowDesc) -> Result<WindowId, String>;
+
+    /// client 區域的實體像素尺寸，視窗不存在時回傳 `None`。
+    fn window_size(&self, window: WindowId) -> Option<Size>;
+
+    fn window_position(&self, window: WindowId) -> Option<Position>;
+
+    /// 視窗所在顯示器的縮放比例，1.0 代表 96 DPI。
+    fn scale_factor(&self, window: WindowId) -> Option<f64>;
+
+    /// 註冊事件處理函式，訂閱 drop 時移除。視窗不存在時回傳 `None`。
+    fn add_handler(&self, window: WindowId, handler: EventHandler) -> Option<Subscription>;
+
+    /// 立即銷毀視窗，不經過 `CloseRequested`。最後一個視窗銷毀後應用程式會結束，
+    /// 所以使用視窗的資源（例如 renderer）應該在呼叫前釋放。
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn close_window(&mut self, window: WindowId);
+
+    /// 處理所有待處理的事件，不會阻塞。應用程式應該結束時回傳 `false`。
+    fn pump_events(&mut self) -> bool;
+
+    /// 要求結束應用程式，下一次 `pump_events` 會回傳 `false`。
+    fn quit(&mut self, exit_code: i32);
+
+    /// 已經收到結束要求時回傳結束代碼。
+    fn exit_code(&self) -> Option<i32>;
+}
+
+pub fn hiword(x: u32) -> u32 {
+    (x >> 16) & 0xFFFF
+}
+
+pub fn loword(x: u32) -> u32 {
+    x & 0xFFFF
+}
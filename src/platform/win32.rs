@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use widestring::U16CString;
+use windows::core::PCWSTR;
+use windows::Win32::UI::WindowsAndMessaging::{DestroyWindow, IsWindow, PostQuitMessage, SW_SHOW};
+use crate::window::{Window, WindowBuilder, WndClass};
+use super::dispatch::{EventHandler, Subscription};
+use super::{Platform, Position, Size, WindowDesc, WindowId};
+
+/// 以 `WndClass` / `Window` 實作的 Win32 平台。
+pub struct Win32Platform {
+    class_name: PCWSTR,
+    windows: HashMap<WindowId, Arc<Window>>,
+    exit_code: Option<i32>,
+}
+
+impl Win32Platform {
+    /// 註冊視窗類別，整個程式只能呼叫一次。
+    pub fn new(class_name: PCWSTR) -> Self {
+        WndClass::init(class_name);
+        Self {
+            class_name,
+            windows: HashMap::new(),
+            exit_code: None,
+        }
+    }
+
+    /// 取得底層的 Win32 視窗，例如建立 swap chain 時需要 `HWND`。
+    pub fn window(&self, window: WindowId) -> Option<&Arc<Window>> {
+        self.windows.get(&window)
+    }
+}
+
+impl Platform for Win32Platform {
+    fn create_window(&mut self, desc: &WindowDesc) -> Result<WindowId, String> {
+        let title = U16CString::from_str(&desc.title).map_err(|e| e.to_string())?;
+        let mut builder = WindowBuilder::new()
+            .window_name(PCWSTR(title.as_ptr()))
+            .class_name(self.class_name)
+            .hinstance(WndClass::get_instance().h_instance);
+        if let Some(position) = desc.position {
+            builder = builder.position(position.x, position.y);
+        }
+        if let Some(size) = desc.size {
+            builder = builder.size(size.width, size.height);
+        }
+        let window = builder.build()?;
+        window.show(SW_SHOW);
+
+        let id = WindowId(window.hwnd.0 as u64);
+        self.windows.insert(id, window);
+        Ok(id)
+    }
+
+    fn window_size(&self, window: WindowId) -> Option<Size> {
+        self.windows.get(&window).map(|w| w.get_size())
+    }
+
+    fn window_position(&self, window: WindowId) -> Option<Position> {
+        self.windows.get(&window).map(|w| w.get_position())
+    }
+
+    fn scale_factor(&self, window: WindowId) -> Option<f64> {
+        self.windows.get(&window).map(|w| w.scale_factor())
+    }
+
+    fn add_handler(&self, window: WindowId, handler: EventHandler) -> Option<Subscription> {
+        self.windows.get(&window).map(|target| target.add_handler(handler))
+    }
+
+    fn close_window(&mut self, window: WindowId) {
+        if let Some(target) = self.windows.remove(&window) {
+            unsafe {
+                let _ = DestroyWindow(target.hwnd);
+            }
+        }
+    }
+
+    fn pump_events(&mut self) -> bool {
+        if self.exit_code.is_some() {
+            return false;
+        }
+        if let Some(exit_code) = WndClass::pump_messages() {
+            self.exit_code = Some(exit_code);
+            return false;
+        }
+        // 由 DefWindowProcW 銷毀的視窗不會經過 close_window
+        self.windows.retain(|_, window| unsafe { IsWindow(Some(window.hwnd)).as_bool() });
+        true
+    }
+
+    fn quit(&mut self, exit_code: i32) {
+        unsafe {
+            PostQuitMessage(exit_code);
+        }
+    }
+
+    fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
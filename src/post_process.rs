@@ -0,0 +1,576 @@
+use std::any::Any;
+use windows::core::{s, w};
+use windows::Win32::Graphics::Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT, DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM};
+use crate::d3dutil::{create_pixel_shader, create_sampler, create_vertex_shader, ConstantBuffer, RenderTexture};
+use crate::effect_chain::{ChainEffect, EffectChain, PassInput, TARGET_COUNT};
+use crate::output_format::OutputEncoding;
+
+/// 場景先渲染到這個格式的中間目標，再交給後處理鏈。
+pub const HDR_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16B16A16_FLOAT;
+
+/// 後處理效果共用的 GPU 狀態，在 `PostEffect::apply` 時傳入。
+pub struct PostProcessContext<'a> {
+    pub context: &'a ID3D11DeviceContext,
+    pub width: u32,
+    pub height: u32,
+    fullscreen_vs: &'a ID3D11VertexShader,
+    linear_sampler: &'a ID3D11SamplerState,
+}
+
+impl<'a> PostProcessContext<'a> {
+    /// 以全螢幕三角形執行一次像素著色器，`inputs` 依序綁定到 t0、t1...
+    pub fn draw_fullscreen(&self,
+                           pixel_shader: &ID3D11PixelShader,
+                           inputs: &[&ID3D11ShaderResourceView],
+                           constants: Option<&ID3D11Buffer>,
+                           output: &ID3D11RenderTargetView,
+                           width: u32,
+                           height: u32) {
+        let viewport = D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: width as f32,
+            Height: height as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        };
+        let views: Vec<Option<ID3D11ShaderResourceView>> = inputs.iter().map(|view| Some((*view).clone())).collect();
+        let unbind: Vec<Option<ID3D11ShaderResourceView>> = vec![None; views.len()];
+        unsafe {
+            self.context.OMSetRenderTargets(Some(&[Some(output.clone())]), None);
+            self.context.RSSetViewports(Some(&[viewport]));
+            self.context.IASetInputLayout(None);
+            self.context.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            self.context.VSSetShader(self.fullscreen_vs, None);
+            self.context.PSSetShader(pixel_shader, None);
+            self.context.PSSetSamplers(0, Some(&[Some(self.linear_sampler.clone())]));
+            self.context.PSSetConstantBuffers(0, Some(&[constants.cloned()]));
+            self.context.PSSetShaderResources(0, Some(&views));
+            self.context.Draw(3, 0);
+            // 解除綁定，避免下一個 pass 把同一張紋理當 render target 時發生衝突
+            self.context.PSSetShaderResources(0, Some(&unbind));
+        }
+    }
+}
+
+pub trait PostEffect {
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView);
+
+    /// 視窗尺寸改變時呼叫，需要自有中間紋理的效果在此重建。
+    fn on_resize(&mut self, _device: &ID3D11Device, _width: u32, _height: u32) {}
+
+    /// 裝置失效後在新的裝置上重建所有 GPU 物件，可調整的參數必須保留。
+    fn recreate(&mut self, device: &ID3D11Device, width: u32, height: u32);
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl ChainEffect for Box<dyn PostEffect> {
+    fn name(&self) -> &'static str {
+        PostEffect::name(self.as_ref())
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct OutputParams {
+    encoding: u32,
+    paper_white_nits: f32,
+    _pad: [f32; 2],
+}
+
+/// 依序執行的全螢幕後處理效果，可在執行期開關與調整順序。
+/// 效果之間都以線性 HDR 紋理傳遞，最後由輸出 pass 編碼成 back buffer 的格式。
+pub struct PostProcessChain {
+    effects: EffectChain<Box<dyn PostEffect>>,
+    targets: [RenderTexture; TARGET_COUNT],
+    fullscreen_vs: ID3D11VertexShader,
+    output_ps: ID3D11PixelShader,
+    output_params: OutputParams,
+    output_constants: ConstantBuffer<OutputParams>,
+    linear_sampler: ID3D11SamplerState,
+    width: u32,
+    height: u32,
+}
+
+impl PostProcessChain {
+    pub fn new(device: &ID3D11Device, width: u32, height: u32) -> Self {
+        let (fullscreen_vs, _) = create_vertex_shader(device, w!("hlsl/fullscreen_vs.cso"), w!("hlsl/fullscreen_vs.hlsl"), s!("VS"));
+        let output_ps = create_pixel_shader(device, w!("hlsl/output_ps.cso"), w!("hlsl/output_ps.hlsl"), s!("PS"));
+        let linear_sampler = create_sampler(device, D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_TEXTURE_ADDRESS_CLAMP);
+        let output_params = OutputParams {
+            encoding: OutputEncoding::Srgb as u32,
+            paper_white_nits: 200.0,
+            _pad: [0.0; 2],
+        };
+        Self {
+            effects: EffectChain::new(),
+            targets: Self::create_targets(device, width, height),
+            fullscreen_vs,
+            output_ps,
+            output_params,
+            output_constants: ConstantBuffer::new(device, &output_params),
+            linear_sampler,
+            width,
+            height,
+        }
+    }
+
+    /// 設定最後寫入 back buffer 時的編碼方式，`paper_white_nits` 只在 HDR 輸出時使用。
+    pub fn set_output(&mut self, encoding: OutputEncoding, paper_white_nits: f32) {
+        self.output_params.encoding = encoding as u32;
+        self.output_params.paper_white_nits = paper_white_nits;
+    }
+
+    /// 預設的效果順序：bloom -> tone mapping -> color grading -> vignette -> FXAA。
+    pub fn with_default_effects(device: &ID3D11Device, width: u32, height: u32) -> Self {
+        let mut chain = Self::new(device, width, height);
+        chain.effects.push(Box::new(Bloom::new(device, width, height)));
+        chain.effects.push(Box::new(ToneMapping::new(device)));
+        chain.effects.push(Box::new(ColorGrading::new(device)));
+        chain.effects.push(Box::new(Vignette::new(device)));
+        chain.effects.push(Box::new(Fxaa::new(device)));
+        chain
+    }
+
+    fn create_targets(device: &ID3D11Device, width: u32, height: u32) -> [RenderTexture; TARGET_COUNT] {
+        [
+            RenderTexture::new(device, width, height, HDR_FORMAT),
+            RenderTexture::new(device, width, height, HDR_FORMAT),
+        ]
+    }
+
+    /// 效果的順序與開關。
+    pub fn effects(&self) -> &EffectChain<Box<dyn PostEffect>> {
+        &self.effects
+    }
+
+    pub fn effects_mut(&mut self) -> &mut EffectChain<Box<dyn PostEffect>> {
+        &mut self.effects
+    }
+
+    /// 取得特定型別的效果以調整參數，例如 `chain.effect_mut::<ToneMapping>()`。
+    pub fn effect_mut<T: PostEffect + 'static>(&mut self) -> Option<&mut T> {
+        self.effects.iter_mut().find_map(|effect| effect.as_any_mut().downcast_mut::<T>())
+    }
+
+    pub fn on_resize(&mut self, device: &ID3D11Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.targets = Self::create_targets(device, width, height);
+        for effect in self.effects.iter_mut() {
+            effect.on_resize(device, width, height);
+        }
+    }
+
+    /// 在新的裝置上重建共用資源與所有效果，效果的順序、開關與參數不變。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        let (width, height) = (self.width, self.height);
+        let rebuilt = Self::new(device, width, height);
+        self.targets = rebuilt.targets;
+        self.fullscreen_vs = rebuilt.fullscreen_vs;
+        self.output_ps = rebuilt.output_ps;
+        self.output_constants = rebuilt.output_constants;
+        self.linear_sampler = rebuilt.linear_sampler;
+        for effect in self.effects.iter_mut() {
+            effect.recreate(device, width, height);
+        }
+    }
+
+    /// 從 `input` 開始依序套用所有啟用的效果，再經過輸出 pass 寫入 `output`。
+    pub fn run(&self, context: &ID3D11DeviceContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let ctx = PostProcessContext {
+            context,
+            width: self.width,
+            height: self.height,
+            fullscreen_vs: &self.fullscreen_vs,
+            linear_sampler: &self.linear_sampler,
+        };
+
+        let texture = |source: PassInput| match source {
+            PassInput::Scene => input,
+            PassInput::Target(index) => &self.targets[index].shader_resource_view,
+        };
+        for pass in self.effects.passes() {
+            pass.effect.apply(&ctx, texture(pass.input), &self.targets[pass.output].render_target_view);
+        }
+
+        self.output_constants.update(context, &self.output_params);
+        ctx.draw_fullscreen(&self.output_ps, &[texture(self.effects.output_input())], Some(&self.output_constants.buffer), output, self.width, self.height);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToneMapOperator {
+    Reinhard,
+    AcesFilmic,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ToneMappingParams {
+    exposure: f32,
+    operator: u32,
+    _pad: [f32; 2],
+}
+
+pub struct ToneMapping {
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+    pixel_shader: ID3D11PixelShader,
+    constants: ConstantBuffer<ToneMappingParams>,
+}
+
+impl ToneMapping {
+    pub fn new(device: &ID3D11Device) -> Self {
+        Self {
+            exposure: 1.0,
+            operator: ToneMapOperator::AcesFilmic,
+            pixel_shader: create_pixel_shader(device, w!("hlsl/tone_mapping_ps.cso"), w!("hlsl/tone_mapping_ps.hlsl"), s!("PS")),
+            constants: ConstantBuffer::new(device, &ToneMappingParams { exposure: 1.0, operator: 1, _pad: [0.0; 2] }),
+        }
+    }
+}
+
+impl PostEffect for ToneMapping {
+    fn name(&self) -> &'static str {
+        "tone_mapping"
+    }
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let params = ToneMappingParams {
+            exposure: self.exposure,
+            operator: match self.operator {
+                ToneMapOperator::Reinhard => 0,
+                ToneMapOperator::AcesFilmic => 1,
+            },
+            _pad: [0.0; 2],
+        };
+        self.constants.update(ctx.context, &params);
+        ctx.draw_fullscreen(&self.pixel_shader, &[input], Some(&self.constants.buffer), output, ctx.width, ctx.height);
+    }
+
+    fn recreate(&mut self, device: &ID3D11Device, _width: u32, _height: u32) {
+        *self = Self { exposure: self.exposure, operator: self.operator, ..Self::new(device) };
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct BloomParams {
+    threshold: f32,
+    intensity: f32,
+    texel_size: [f32; 2],
+}
+
+/// 亮部擷取 -> 半解析度高斯模糊（水平、垂直）-> 與原圖疊加。
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    bright_pass_ps: ID3D11PixelShader,
+    blur_horizontal_ps: ID3D11PixelShader,
+    blur_vertical_ps: ID3D11PixelShader,
+    composite_ps: ID3D11PixelShader,
+    constants: ConstantBuffer<BloomParams>,
+    half_targets: [RenderTexture; 2],
+}
+
+impl Bloom {
+    pub fn new(device: &ID3D11Device, width: u32, height: u32) -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.6,
+            bright_pass_ps: create_pixel_shader(device, w!("hlsl/bloom_bright_pass_ps.cso"), w!("hlsl/bloom_ps.hlsl"), s!("BrightPassPS")),
+            blur_horizontal_ps: create_pixel_shader(device, w!("hlsl/bloom_blur_h_ps.cso"), w!("hlsl/bloom_ps.hlsl"), s!("BlurHorizontalPS")),
+            blur_vertical_ps: create_pixel_shader(device, w!("hlsl/bloom_blur_v_ps.cso"), w!("hlsl/bloom_ps.hlsl"), s!("BlurVerticalPS")),
+            composite_ps: create_pixel_shader(device, w!("hlsl/bloom_composite_ps.cso"), w!("hlsl/bloom_ps.hlsl"), s!("CompositePS")),
+            constants: ConstantBuffer::new(device, &BloomParams { threshold: 1.0, intensity: 0.6, texel_size: [0.0; 2] }),
+            half_targets: Self::create_half_targets(device, width, height),
+        }
+    }
+
+    fn create_half_targets(device: &ID3D11Device, width: u32, height: u32) -> [RenderTexture; 2] {
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+        [
+            RenderTexture::new(device, half_width, half_height, HDR_FORMAT),
+            RenderTexture::new(device, half_width, half_height, HDR_FORMAT),
+        ]
+    }
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let [ping, pong] = &self.half_targets;
+        let params = BloomParams {
+            threshold: self.threshold,
+            intensity: self.intensity,
+            texel_size: [1.0 / ping.width as f32, 1.0 / ping.height as f32],
+        };
+        self.constants.update(ctx.context, &params);
+        let constants = Some(&self.constants.buffer);
+
+        ctx.draw_fullscreen(&self.bright_pass_ps, &[input], constants, &ping.render_target_view, ping.width, ping.height);
+        ctx.draw_fullscreen(&self.blur_horizontal_ps, &[&ping.shader_resource_view], constants, &pong.render_target_view, pong.width, pong.height);
+        ctx.draw_fullscreen(&self.blur_vertical_ps, &[&pong.shader_resource_view], constants, &ping.render_target_view, ping.width, ping.height);
+        ctx.draw_fullscreen(&self.composite_ps, &[input, &ping.shader_resource_view], constants, output, ctx.width, ctx.height);
+    }
+
+    fn on_resize(&mut self, device: &ID3D11Device, width: u32, height: u32) {
+        self.half_targets = Self::create_half_targets(device, width, height);
+    }
+
+    fn recreate(&mut self, device: &ID3D11Device, width: u32, height: u32) {
+        *self = Self { threshold: self.threshold, intensity: self.intensity, ..Self::new(device, width, height) };
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct FxaaParams {
+    rcp_frame: [f32; 2],
+    span_max: f32,
+    reduce_mul: f32,
+}
+
+pub struct Fxaa {
+    pub span_max: f32,
+    pub reduce_mul: f32,
+    pixel_shader: ID3D11PixelShader,
+    constants: ConstantBuffer<FxaaParams>,
+}
+
+impl Fxaa {
+    pub fn new(device: &ID3D11Device) -> Self {
+        Self {
+            span_max: 8.0,
+            reduce_mul: 1.0 / 8.0,
+            pixel_shader: create_pixel_shader(device, w!("hlsl/fxaa_ps.cso"), w!("hlsl/fxaa_ps.hlsl"), s!("PS")),
+            constants: ConstantBuffer::new(device, &FxaaParams { rcp_frame: [0.0; 2], span_max: 8.0, reduce_mul: 1.0 / 8.0 }),
+        }
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn name(&self) -> &'static str {
+        "fxaa"
+    }
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let params = FxaaParams {
+            rcp_frame: [1.0 / ctx.width as f32, 1.0 / ctx.height as f32],
+            span_max: self.span_max,
+            reduce_mul: self.reduce_mul,
+        };
+        self.constants.update(ctx.context, &params);
+        ctx.draw_fullscreen(&self.pixel_shader, &[input], Some(&self.constants.buffer), output, ctx.width, ctx.height);
+    }
+
+    fn recreate(&mut self, device: &ID3D11Device, _width: u32, _height: u32) {
+        *self = Self { span_max: self.span_max, reduce_mul: self.reduce_mul, ..Self::new(device) };
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ColorGradingParams {
+    lut_size: f32,
+    strength: f32,
+    _pad: [f32; 2],
+}
+
+/// 以 3D LUT 做色彩校正，預設為不改變顏色的 identity LUT。
+pub struct ColorGrading {
+    pub strength: f32,
+    lut_size: u32,
+    // 重建裝置時用來重新上傳 LUT
+    lut_texels: Vec<[u8; 4]>,
+    lut: ID3D11ShaderResourceView,
+    pixel_shader: ID3D11PixelShader,
+    constants: ConstantBuffer<ColorGradingParams>,
+}
+
+impl ColorGrading {
+    pub const DEFAULT_LUT_SIZE: u32 = 16;
+
+    pub fn new(device: &ID3D11Device) -> Self {
+        let lut_size = Self::DEFAULT_LUT_SIZE;
+        let lut_texels = Self::identity_lut(lut_size).unwrap();
+        Self {
+            strength: 1.0,
+            lut_size,
+            lut: Self::create_lut(device, lut_size, &lut_texels),
+            lut_texels,
+            pixel_shader: create_pixel_shader(device, w!("hlsl/color_grading_ps.cso"), w!("hlsl/color_grading_ps.hlsl"), s!("PS")),
+            constants: ConstantBuffer::new(device, &ColorGradingParams { lut_size: lut_size as f32, strength: 1.0, _pad: [0.0; 2] }),
+        }
+    }
+
+    /// LUT 每邊至少 2 個 texel 才能內插，最多是 3D 紋理的最大尺寸。
+    fn validate_lut_size(size: u32) -> Result<(), String> {
+        if !(2..=D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION).contains(&size) {
+            return Err(format!("LUT size must be between 2 and {}, got {}", D3D11_REQ_TEXTURE3D_U_V_OR_W_DIMENSION, size));
+        }
+        Ok(())
+    }
+
+    /// 產生 `size`^3 的 identity LUT，索引順序為 r 最快、b 最慢。
+    pub fn identity_lut(size: u32) -> Result<Vec<[u8; 4]>, String> {
+        Self::validate_lut_size(size)?;
+        let max = (size - 1) as f32;
+        let mut texels = Vec::with_capacity(size as usize * size as usize * size as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    texels.push([
+                        (r as f32 / max * 255.0).round() as u8,
+                        (g as f32 / max * 255.0).round() as u8,
+                        (b as f32 / max * 255.0).round() as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+        Ok(texels)
+    }
+
+    /// 替換 LUT，`texels` 長度必須是 `size`^3。
+    // 目前還沒有載入自訂 LUT 的地方，只使用預設的 identity LUT
+    #[allow(dead_code)]
+    pub fn set_lut(&mut self, device: &ID3D11Device, size: u32, texels: &[[u8; 4]]) -> Result<(), String> {
+        Self::validate_lut_size(size)?;
+        let expected = size as usize * size as usize * size as usize;
+        if texels.len() != expected {
+            return Err(format!("LUT of size {} must contain {} texels, got {}", size, expected, texels.len()));
+        }
+        self.lut_size = size;
+        self.lut = Self::create_lut(device, size, texels);
+        self.lut_texels = texels.to_vec();
+        Ok(())
+    }
+
+    fn create_lut(device: &ID3D11Device, size: u32, texels: &[[u8; 4]]) -> ID3D11ShaderResourceView {
+        let desc = D3D11_TEXTURE3D_DESC {
+            Width: size,
+            Height: size,
+            Depth: size,
+            MipLevels: 1,
+            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+        let init_data = D3D11_SUBRESOURCE_DATA {
+            pSysMem: texels.as_ptr() as _,
+            SysMemPitch: size * 4,
+            SysMemSlicePitch: size * size * 4,
+        };
+        let mut texture: Option<ID3D11Texture3D> = None;
+        let mut view: Option<ID3D11ShaderResourceView> = None;
+        unsafe {
+            device.CreateTexture3D(&desc, Some(&init_data), Some(&mut texture)).expect("CreateTexture3D failed");
+            device.CreateShaderResourceView(texture.as_ref().unwrap(), None, Some(&mut view)).expect("CreateShaderResourceView failed");
+        }
+        view.unwrap()
+    }
+}
+
+impl PostEffect for ColorGrading {
+    fn name(&self) -> &'static str {
+        "color_grading"
+    }
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let params = ColorGradingParams {
+            lut_size: self.lut_size as f32,
+            strength: self.strength,
+            _pad: [0.0; 2],
+        };
+        self.constants.update(ctx.context, &params);
+        ctx.draw_fullscreen(&self.pixel_shader, &[input, &self.lut], Some(&self.constants.buffer), output, ctx.width, ctx.height);
+    }
+
+    fn recreate(&mut self, device: &ID3D11Device, _width: u32, _height: u32) {
+        self.lut = Self::create_lut(device, self.lut_size, &self.lut_texels);
+        self.pixel_shader = create_pixel_shader(device, w!("hlsl/color_grading_ps.cso"), w!("hlsl/color_grading_ps.hlsl"), s!("PS"));
+        self.constants = ConstantBuffer::new(device, &ColorGradingParams { lut_size: self.lut_size as f32, strength: self.strength, _pad: [0.0; 2] });
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct VignetteParams {
+    intensity: f32,
+    radius: f32,
+    softness: f32,
+    _pad: f32,
+}
+
+pub struct Vignette {
+    pub intensity: f32,
+    pub radius: f32,
+    pub softness: f32,
+    pixel_shader: ID3D11PixelShader,
+    constants: ConstantBuffer<VignetteParams>,
+}
+
+impl Vignette {
+    pub fn new(device: &ID3D11Device) -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.75,
+            softness: 0.45,
+            pixel_shader: create_pixel_shader(device, w!("hlsl/vignette_ps.cso"), w!("hlsl/vignette_ps.hlsl"), s!("PS")),
+            constants: ConstantBuffer::new(device, &VignetteParams { intensity: 0.5, radius: 0.75, softness: 0.45, _pad: 0.0 }),
+        }
+    }
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn apply(&self, ctx: &PostProcessContext, input: &ID3D11ShaderResourceView, output: &ID3D11RenderTargetView) {
+        let params = VignetteParams {
+            intensity: self.intensity,
+            radius: self.radius,
+            softness: self.softness,
+            _pad: 0.0,
+        };
+        self.constants.update(ctx.context, &params);
+        ctx.draw_fullscreen(&self.pixel_shader, &[input], Some(&self.constants.buffer), output, ctx.width, ctx.height);
+    }
+
+    fn recreate(&mut self, device: &ID3D11Device, _width: u32, _height: u32) {
+        *self = Self { intensity: self.intensity, radius: self.radius, softness: self.softness, ..Self::new(device) };
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
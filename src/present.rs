@@ -0,0 +1,80 @@
+/// swap chain 的 buffer 數量與 present 方式。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PresentSettings {
+    /// flip model 至少需要 2 個 buffer，3 個可以讓 GPU 在等待垂直同步時繼續畫下一幀。
+    pub buffer_count: u32,
+    /// present 時等待垂直同步。
+    pub vsync: bool,
+    /// 關閉垂直同步時允許畫面撕裂，可變更新率（G-SYNC、FreeSync）顯示器需要這個選項才能不受更新率限制。
+    pub allow_tearing: bool,
+    /// CPU 最多領先 GPU 幾幀，越小輸入延遲越低。
+    pub max_frame_latency: u32,
+}
+
+impl PresentSettings {
+    pub const MIN_BUFFER_COUNT: u32 = 2;
+    /// 與 `DXGI_MAX_SWAP_CHAIN_BUFFERS` 相同。
+    pub const MAX_BUFFER_COUNT: u32 = 16;
+    pub const MAX_FRAME_LATENCY: u32 = 16;
+
+    /// 依目前狀態決定 `Present` 的參數。獨占全螢幕時不能要求撕裂，驅動程式會自己處理。
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn present_params(&self, tearing_supported: bool, exclusive_fullscreen: bool) -> PresentParams {
+        if self.vsync {
+            return PresentParams { sync_interval: 1, allow_tearing: false };
+        }
+        PresentParams {
+            sync_interval: 0,
+            allow_tearing: self.allow_tearing && tearing_supported && !exclusive_fullscreen,
+        }
+    }
+}
+
+impl Default for PresentSettings {
+    fn default() -> Self {
+        Self {
+            buffer_count: 2,
+            vsync: false,
+            allow_tearing: true,
+            max_frame_latency: 1,
+        }
+    }
+}
+
+/// 傳給 `IDXGISwapChain::Present` 的參數。
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct PresentParams {
+    pub sync_interval: u32,
+    /// 使用 `DXGI_PRESENT_ALLOW_TEARING`。
+    pub allow_tearing: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_TEARING: PresentParams = PresentParams { sync_interval: 0, allow_tearing: false };
+
+    #[test]
+    fn vsync_never_tears() {
+        let settings = PresentSettings { vsync: true, ..PresentSettings::default() };
+        assert_eq!(settings.present_params(true, false), PresentParams { sync_interval: 1, allow_tearing: false });
+    }
+
+    #[test]
+    fn tearing_when_supported_and_allowed() {
+        let settings = PresentSettings::default();
+        assert_eq!(settings.present_params(true, false), PresentParams { sync_interval: 0, allow_tearing: true });
+    }
+
+    #[test]
+    fn tearing_falls_back_to_immediate_present() {
+        let settings = PresentSettings::default();
+        assert_eq!(settings.present_params(false, false), NO_TEARING);
+        // 獨占全螢幕時由驅動程式處理，不能加上 ALLOW_TEARING
+        assert_eq!(settings.present_params(true, true), NO_TEARING);
+        let disallowed = PresentSettings { allow_tearing: false, ..settings };
+        assert_eq!(disallowed.present_params(true, false), NO_TEARING);
+    }
+}
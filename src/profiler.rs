@@ -0,0 +1,610 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 保留最近幾幀的紀錄。
+pub const FRAME_HISTORY: usize = 240;
+
+/// 一個結束的 scope。時間都是相對於 profiler 開始的時間。
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScopeEvent {
+    pub name: &'static str,
+    pub thread: u32,
+    pub start: Duration,
+    pub duration: Duration,
+    /// 同一個執行緒上外層還有幾個 scope。
+    pub depth: u32,
+}
+
+/// 一幀之間結束的所有 scope，包含其他執行緒上的。
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRecord {
+    pub index: u64,
+    /// 呼叫 `end_frame` 的執行緒。
+    pub thread: u32,
+    pub start: Duration,
+    pub duration: Duration,
+    pub scopes: Vec<ScopeEvent>,
+}
+
+// 每個執行緒自己的紀錄，只有自己與 `end_frame` 會鎖，幾乎不會互相等待
+struct ThreadBuffer {
+    id: u32,
+    name: String,
+    events: Mutex<Vec<ScopeEvent>>,
+}
+
+struct FrameHistory {
+    frames: VecDeque<FrameRecord>,
+    next_index: u64,
+    frame_start: Duration,
+}
+
+struct Profiler {
+    epoch: Instant,
+    next_thread_id: AtomicU32,
+    threads: Mutex<Vec<Arc<ThreadBuffer>>>,
+    history: Mutex<FrameHistory>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROFILER: OnceLock<Profiler> = OnceLock::new();
+
+thread_local! {
+    static THREAD_BUFFER: RefCell<Option<Arc<ThreadBuffer>>> = const { RefCell::new(None) };
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+fn profiler() -> &'static Profiler {
+    PROFILER.get_or_init(|| Profiler {
+        epoch: Instant::now(),
+        next_thread_id: AtomicU32::new(0),
+        threads: Mutex::new(vec![]),
+        history: Mutex::new(FrameHistory { frames: VecDeque::with_capacity(FRAME_HISTORY), next_index: 0, frame_start: Duration::ZERO }),
+    })
+}
+
+// 第一次在這個執行緒記錄時登記，以執行緒名稱命名
+fn thread_buffer() -> Arc<ThreadBuffer> {
+    THREAD_BUFFER.with_borrow_mut(|buffer| buffer.get_or_insert_with(|| {
+        let profiler = profiler();
+        let id = profiler.next_thread_id.fetch_add(1, Ordering::Relaxed);
+        let thread = std::thread::current();
+        let name = thread.name().map(str::to_string).unwrap_or_else(|| format!("thread {}", id));
+        let buffer = Arc::new(ThreadBuffer { id, name, events: Mutex::new(vec![]) });
+        profiler.threads.lock().unwrap().push(buffer.clone());
+        buffer
+    }).clone())
+}
+
+/// 開始或停止記錄。停止時 scope 只剩一次 atomic 讀取的成本。
+pub fn set_enabled(enabled: bool) {
+    if enabled {
+        // 從現在開始算第一幀
+        let profiler = profiler();
+        profiler.history.lock().unwrap().frame_start = profiler.epoch.elapsed();
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 由 `profile_scope!` 建立，離開所在的區塊時記錄經過的時間。
+pub struct Scope {
+    name: &'static str,
+    // 沒有啟用時為 None
+    start: Option<Instant>,
+    depth: u32,
+}
+
+impl Scope {
+    pub fn new(name: &'static str) -> Self {
+        if !is_enabled() {
+            return Self { name, start: None, depth: 0 };
+        }
+        let depth = DEPTH.get();
+        DEPTH.set(depth + 1);
+        Self { name, start: Some(Instant::now()), depth }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let duration = start.elapsed();
+        DEPTH.set(self.depth);
+        let buffer = thread_buffer();
+        let event = ScopeEvent {
+            name: self.name,
+            thread: buffer.id,
+            start: start.saturating_duration_since(profiler().epoch),
+            duration,
+            depth: self.depth,
+        };
+        buffer.events.lock().unwrap().push(event);
+    }
+}
+
+/// 記錄到所在區塊結束為止的時間，可以巢狀使用，也可以在任何執行緒使用：
+///
+/// ```ignore
+/// fn update() {
+///     profile_scope!("update");
+///     ...
+/// }
+/// ```
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::Scope::new($name);
+    };
+}
+pub(crate) use profile_scope;
+
+/// 結束目前這一幀，把所有執行緒在這一幀之間結束的 scope 收進最近幾幀的紀錄。
+pub fn end_frame() {
+    if !is_enabled() {
+        return;
+    }
+    let profiler = profiler();
+    let now = profiler.epoch.elapsed();
+    let thread = thread_buffer().id;
+    let mut scopes = vec![];
+    for buffer in profiler.threads.lock().unwrap().iter() {
+        scopes.append(&mut buffer.events.lock().unwrap());
+    }
+    scopes.sort_by_key(|scope| (scope.thread, scope.start));
+    let mut history = profiler.history.lock().unwrap();
+    let record = FrameRecord {
+        index: history.next_index,
+        thread,
+        start: history.frame_start,
+        duration: now.saturating_sub(history.frame_start),
+        scopes,
+    };
+    if history.frames.len() == FRAME_HISTORY {
+        history.frames.pop_front();
+    }
+    history.frames.push_back(record);
+    history.next_index += 1;
+    history.frame_start = now;
+}
+
+/// 最近幾幀的紀錄，舊的在前。
+pub fn frames() -> Vec<FrameRecord> {
+    profiler().history.lock().unwrap().frames.iter().cloned().collect()
+}
+
+/// 曾經記錄過的執行緒編號與名稱。
+pub fn threads() -> Vec<(u32, String)> {
+    profiler().threads.lock().unwrap().iter().map(|buffer| (buffer.id, buffer.name.clone())).collect()
+}
+
+fn write_json_string(output: &mut String, text: &str) {
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(output, "\\u{:04x}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// 轉成 Chrome trace event 格式的 JSON，可以用 `chrome://tracing` 或 Perfetto 開啟。
+/// 每一幀是呼叫 `end_frame` 的執行緒上的一個 `frame N` 區段。
+pub fn chrome_trace(frames: &[FrameRecord], threads: &[(u32, String)]) -> String {
+    let mut events = vec![];
+    for (id, name) in threads {
+        let mut event = format!(r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{},"args":{{"name":"#, id);
+        write_json_string(&mut event, name);
+        event.push_str("}}");
+        events.push(event);
+    }
+    for frame in frames {
+        events.push(format!(
+            r#"{{"name":"frame {}","cat":"frame","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":{}}}"#,
+            frame.index, micros(frame.start), micros(frame.duration), frame.thread,
+        ));
+        for scope in &frame.scopes {
+            let mut event = String::from(r#"{"name":"#);
+            write_json_string(&mut event, scope.name);
+            write!(
+                event,
+                r#","cat":"scope","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":{}}}"#,
+                micros(scope.start), micros(scope.duration), scope.thread,
+            ).unwrap();
+            events.push(event);
+        }
+    }
+    format!("{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n", events.join(",\n"))
+}
+
+/// 把最近幾幀寫成 Chrome trace 檔案。
+pub fn write_chrome_trace(path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    std::fs::write(path, chrome_trace(&frames(), &threads())).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 一個 scope 名稱在多幀之間的統計。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeSummary {
+    pub name: &'static str,
+    pub calls: u64,
+    /// 所有呼叫的時間總和，包含巢狀的子 scope。
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// 多幀的統計，scope 依總時間由多到少排列。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfileSummary {
+    pub frames: usize,
+    pub total_frame_time: Duration,
+    pub max_frame_time: Duration,
+    pub scopes: Vec<ScopeSummary>,
+}
+
+impl ProfileSummary {
+    pub fn new(frames: &[FrameRecord]) -> Self {
+        let mut scopes: HashMap<&'static str, ScopeSummary> = HashMap::new();
+        for scope in frames.iter().flat_map(|frame| &frame.scopes) {
+            let summary = scopes.entry(scope.name).or_insert(ScopeSummary { name: scope.name, calls: 0, total: Duration::ZERO, max: Duration::ZERO });
+            summary.calls += 1;
+            summary.total += scope.duration;
+            summary.max = summary.max.max(scope.duration);
+        }
+        let mut scopes: Vec<ScopeSummary> = scopes.into_values().collect();
+        scopes.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+        Self {
+            frames: frames.len(),
+            total_frame_time: frames.iter().map(|frame| frame.duration).sum(),
+            max_frame_time: frames.iter().map(|frame| frame.duration).max().unwrap_or_default(),
+            scopes,
+        }
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        self.total_frame_time.checked_div(self.frames as u32).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ProfileSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} frames, {:.3} ms average, {:.3} ms max",
+            self.frames, ms(self.average_frame_time()), ms(self.max_frame_time),
+        )?;
+        let frames = self.frames.max(1) as f64;
+        for scope in &self.scopes {
+            write!(
+                f,
+                "\n  {:<24} {:>9.3} ms/frame {:>7.1} calls/frame {:>9.3} ms max",
+                scope.name, ms(scope.total) / frames, scope.calls as f64 / frames, ms(scope.max),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        fn get(&self, key: &str) -> Option<&Json> {
+            match self {
+                Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+                _ => None,
+            }
+        }
+
+        fn as_str(&self) -> Option<&str> {
+            match self {
+                Json::String(text) => Some(text),
+                _ => None,
+            }
+        }
+
+        fn as_f64(&self) -> Option<f64> {
+            match self {
+                Json::Number(value) => Some(*value),
+                _ => None,
+            }
+        }
+    }
+
+    /// 只為了檢查輸出而寫的嚴格 JSON 解析，字串中不允許未跳脫的控制字元。
+    fn parse_json(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected {:?} after the value", c)),
+        }
+    }
+
+    fn skip_whitespace(chars: &mut Peekable<Chars>) {
+        while chars.next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+        match chars.next() {
+            Some(c) if c == expected => Ok(()),
+            c => Err(format!("expected {:?}, got {:?}", expected, c)),
+        }
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+        skip_whitespace(chars);
+        match chars.peek().copied() {
+            Some('{') => {
+                chars.next();
+                let mut fields = vec![];
+                skip_whitespace(chars);
+                if chars.next_if_eq(&'}').is_some() {
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    skip_whitespace(chars);
+                    let key = parse_string(chars)?;
+                    skip_whitespace(chars);
+                    expect(chars, ':')?;
+                    fields.push((key, parse_value(chars)?));
+                    skip_whitespace(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields)),
+                        c => return Err(format!("expected ',' or '}}' in object, got {:?}", c)),
+                    }
+                }
+            }
+            Some('[') => {
+                chars.next();
+                let mut values = vec![];
+                skip_whitespace(chars);
+                if chars.next_if_eq(&']').is_some() {
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(parse_value(chars)?);
+                    skip_whitespace(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(values)),
+                        c => return Err(format!("expected ',' or ']' in array, got {:?}", c)),
+                    }
+                }
+            }
+            Some('"') => parse_string(chars).map(Json::String),
+            Some('-' | '0'..='9') => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+                    number.push(c);
+                }
+                number.parse().map(Json::Number).map_err(|_| format!("invalid number {}", number))
+            }
+            _ => {
+                let word: String = std::iter::from_fn(|| chars.next_if(char::is_ascii_alphabetic)).collect();
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(format!("unexpected {:?}", word)),
+                }
+            }
+        }
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+        expect(chars, '"')?;
+        let mut text = String::new();
+        loop {
+            match chars.next().ok_or("unterminated string")? {
+                '"' => return Ok(text),
+                '\\' => match chars.next().ok_or("unterminated escape")? {
+                    '"' => text.push('"'),
+                    '\\' => text.push('\\'),
+                    '/' => text.push('/'),
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'r' => text.push('\r'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\u{}", hex))?;
+                        text.push(char::from_u32(code).ok_or_else(|| format!("unsupported escape \\u{}", hex))?);
+                    }
+                    c => return Err(format!("invalid escape \\{}", c)),
+                },
+                c if (c as u32) < 0x20 => return Err(format!("unescaped control character {:?}", c)),
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    fn scope(name: &'static str, thread: u32, start: u64, duration: u64, depth: u32) -> ScopeEvent {
+        ScopeEvent { name, thread, start: ms(start), duration: ms(duration), depth }
+    }
+
+    fn frame(index: u64, start: u64, duration: u64, scopes: Vec<ScopeEvent>) -> FrameRecord {
+        FrameRecord { index, thread: 0, start: ms(start), duration: ms(duration), scopes }
+    }
+
+    #[test]
+    fn json_checker_rejects_invalid_json() {
+        for text in ["", "{", r#"{"a":1,}"#, r#"["a" "b"]"#, "\"tab\there\"", r#""\x""#, "[1] 2", "nul"] {
+            assert!(parse_json(text).is_err(), "accepted {:?}", text);
+        }
+        assert_eq!(parse_json(r#" {"a" : [1, -2.5e3, "A\n"], "b": null} "#).unwrap(), Json::Object(vec![
+            ("a".to_string(), Json::Array(vec![Json::Number(1.0), Json::Number(-2500.0), Json::String("A\n".to_string())])),
+            ("b".to_string(), Json::Null),
+        ]));
+    }
+
+    #[test]
+    fn chrome_trace_is_valid_json() {
+        let frames = [
+            frame(0, 0, 16, vec![scope("update", 0, 1, 5, 0), scope("physics", 0, 2, 3, 1), scope("load_asset", 1, 3, 10, 0)]),
+            frame(1, 16, 17, vec![]),
+        ];
+        let threads = [(0, "main".to_string()), (1, "asset-loader-0".to_string())];
+        let trace = parse_json(&chrome_trace(&frames, &threads)).unwrap();
+
+        assert_eq!(trace.get("displayTimeUnit").and_then(Json::as_str), Some("ms"));
+        let Some(Json::Array(events)) = trace.get("traceEvents") else {
+            panic!("traceEvents is not an array: {:?}", trace);
+        };
+        assert_eq!(events.len(), threads.len() + frames.len() + 3);
+        for event in events {
+            for key in ["name", "ph", "pid", "tid"] {
+                assert!(event.get(key).is_some(), "{:?} has no {}", event, key);
+            }
+        }
+
+        let names: Vec<&str> = events.iter().filter_map(|event| event.get("name")?.as_str()).collect();
+        assert_eq!(names, ["thread_name", "thread_name", "frame 0", "update", "physics", "load_asset", "frame 1"]);
+        assert_eq!(events[1].get("args").and_then(|args| args.get("name")).and_then(Json::as_str), Some("asset-loader-0"));
+
+        // 時間以微秒為單位
+        let physics = &events[4];
+        assert_eq!(physics.get("ph").and_then(Json::as_str), Some("X"));
+        assert_eq!(physics.get("ts").and_then(Json::as_f64), Some(2000.0));
+        assert_eq!(physics.get("dur").and_then(Json::as_f64), Some(3000.0));
+        assert_eq!(events[5].get("tid").and_then(Json::as_f64), Some(1.0));
+        assert_eq!(events[6].get("ts").and_then(Json::as_f64), Some(16000.0));
+    }
+
+    #[test]
+    fn chrome_trace_escapes_names() {
+        const NAME: &str = "quote \" backslash \\ newline \n tab \t bell \u{7} unit \u{1f} 中文";
+        let frames = [frame(0, 0, 1, vec![scope(NAME, 0, 0, 1, 0)])];
+        let threads = [(0, "thread \"0\"\r\n".to_string())];
+        let text = chrome_trace(&frames, &threads);
+        assert!(text.contains(r#"bell \u0007 unit \u001f"#));
+        assert!(text.contains(r#"newline \u000a tab \u0009"#));
+
+        let trace = parse_json(&text).unwrap();
+        let Some(Json::Array(events)) = trace.get("traceEvents") else {
+            panic!("traceEvents is not an array");
+        };
+        assert_eq!(events[0].get("args").and_then(|args| args.get("name")).and_then(Json::as_str), Some("thread \"0\"\r\n"));
+        assert_eq!(events[2].get("name").and_then(Json::as_str), Some(NAME));
+    }
+
+    #[test]
+    fn empty_trace_is_valid_json() {
+        let trace = parse_json(&chrome_trace(&[], &[])).unwrap();
+        assert_eq!(trace.get("traceEvents"), Some(&Json::Array(vec![])));
+    }
+
+    #[test]
+    fn summary_aggregates_scopes_across_frames() {
+        let frames = [
+            frame(0, 0, 10, vec![scope("render", 0, 0, 4, 0), scope("update", 0, 4, 3, 0), scope("physics", 0, 5, 1, 1)]),
+            frame(1, 10, 20, vec![scope("render", 0, 10, 6, 0), scope("physics", 0, 16, 2, 1)]),
+            frame(2, 30, 6, vec![scope("update", 0, 30, 7, 0), scope("audio", 1, 30, 3, 0)]),
+        ];
+        let summary = ProfileSummary::new(&frames);
+        assert_eq!(summary.frames, 3);
+        assert_eq!(summary.total_frame_time, ms(36));
+        assert_eq!(summary.max_frame_time, ms(20));
+        assert_eq!(summary.average_frame_time(), ms(12));
+
+        // 總時間相同時依名稱排列
+        let scopes: Vec<(&str, u64, Duration, Duration)> = summary.scopes.iter().map(|scope| (scope.name, scope.calls, scope.total, scope.max)).collect();
+        assert_eq!(scopes, [
+            ("render", 2, ms(10), ms(6)),
+            ("update", 2, ms(10), ms(7)),
+            ("audio", 1, ms(3), ms(3)),
+            ("physics", 2, ms(3), ms(2)),
+        ]);
+
+        let text = summary.to_string();
+        assert!(text.starts_with("3 frames, 12.000 ms average, 20.000 ms max\n  render "), "{}", text);
+        assert_eq!(text.lines().count(), 5);
+    }
+
+    #[test]
+    fn empty_summary_has_no_average() {
+        let summary = ProfileSummary::new(&[]);
+        assert_eq!(summary, ProfileSummary::default());
+        assert_eq!(summary.average_frame_time(), Duration::ZERO);
+        assert_eq!(summary.to_string(), "0 frames, 0.000 ms average, 0.000 ms max");
+    }
+
+    #[test]
+    fn records_nested_scopes() {
+        // 其他測試的執行緒也可能在同一幀記錄，只檢查這個執行緒的 scope
+        let recorded = std::thread::Builder::new().name("profiler-test".to_string()).spawn(|| {
+            set_enabled(true);
+            {
+                profile_scope!("outer");
+                {
+                    profile_scope!("middle");
+                    profile_scope!("inner");
+                }
+                profile_scope!("sibling");
+            }
+            let thread = thread_buffer().id;
+            end_frame();
+            set_enabled(false);
+            {
+                profile_scope!("disabled");
+            }
+            assert!(thread_buffer().events.lock().unwrap().is_empty());
+            let frames = frames();
+            let scopes: Vec<ScopeEvent> = frames.iter()
+                .flat_map(|frame| &frame.scopes)
+                .filter(|scope| scope.thread == thread)
+                .copied()
+                .collect();
+            (thread, scopes)
+        }).unwrap().join().unwrap();
+
+        let (thread, mut scopes) = recorded;
+        assert!(threads().contains(&(thread, "profiler-test".to_string())));
+        // 計時器精度不夠時巢狀的 scope 可能有相同的開始時間
+        scopes.sort_by_key(|scope| (scope.start, scope.depth));
+        let names: Vec<(&str, u32)> = scopes.iter().map(|scope| (scope.name, scope.depth)).collect();
+        assert_eq!(names, [("outer", 0), ("middle", 1), ("inner", 2), ("sibling", 1)]);
+        let outer = scopes[0];
+        for scope in &scopes[1..] {
+            assert!(scope.start >= outer.start && scope.start + scope.duration <= outer.start + outer.duration, "{:?} is not inside {:?}", scope, outer);
+        }
+        assert!(scopes[2].start >= scopes[1].start && scopes[2].start + scopes[2].duration <= scopes[1].start + scopes[1].duration);
+    }
+}
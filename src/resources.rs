@@ -0,0 +1,260 @@
+use std::fmt;
+use std::path::Path;
+use windows::core::s;
+use windows::Win32::Graphics::Direct3D11::*;
+use crate::assets::{ShaderCode, ShaderData, ShaderStage};
+use crate::capabilities::ShaderModel;
+use crate::d3dutil::{blob_as_bytes, compile_shader, shader_model};
+use crate::handle::{Handle, SlotMap, SlotStats};
+
+pub type BufferHandle = Handle<GpuBuffer>;
+pub type TextureHandle = Handle<GpuTexture>;
+pub type ShaderHandle = Handle<GpuShader>;
+
+/// 由 `ResourceManager` 管理的緩衝區，保留建立時的描述與初始資料，裝置失效後可以重建。
+pub struct GpuBuffer {
+    pub buffer: ID3D11Buffer,
+    pub desc: D3D11_BUFFER_DESC,
+    initial_data: Option<Vec<u8>>,
+}
+
+impl GpuBuffer {
+    fn create(device: &ID3D11Device, desc: &D3D11_BUFFER_DESC, initial_data: Option<Vec<u8>>) -> Self {
+        let init_data = initial_data.as_ref().map(|data| D3D11_SUBRESOURCE_DATA {
+            pSysMem: data.as_ptr() as _,
+            SysMemPitch: 0,
+            SysMemSlicePitch: 0,
+        });
+        let mut buffer: Option<ID3D11Buffer> = None;
+        unsafe {
+            device.CreateBuffer(desc, init_data.as_ref().map(|data| data as *const _), Some(&mut buffer)).expect("CreateBuffer failed");
+        }
+        Self { buffer: buffer.unwrap(), desc: *desc, initial_data }
+    }
+}
+
+// 紋理的初始資料與每列的 byte 數
+struct TextureData {
+    texels: Vec<u8>,
+    row_pitch: u32,
+}
+
+/// 由 `ResourceManager` 管理的 2D 紋理，依 bind flag 一併建立 shader resource view 與 render target view。
+// 目前的 render target 都由後處理鏈自己建立，還沒有透過 handle 建立紋理的呼叫端
+#[allow(dead_code)]
+pub struct GpuTexture {
+    pub texture: ID3D11Texture2D,
+    pub shader_resource_view: Option<ID3D11ShaderResourceView>,
+    pub render_target_view: Option<ID3D11RenderTargetView>,
+    pub desc: D3D11_TEXTURE2D_DESC,
+    initial_data: Option<TextureData>,
+}
+
+impl GpuTexture {
+    fn create(device: &ID3D11Device, desc: &D3D11_TEXTURE2D_DESC, initial_data: Option<TextureData>) -> Self {
+        let init_data = initial_data.as_ref().map(|data| D3D11_SUBRESOURCE_DATA {
+            pSysMem: data.texels.as_ptr() as _,
+            SysMemPitch: data.row_pitch,
+            SysMemSlicePitch: 0,
+        });
+        let mut texture: Option<ID3D11Texture2D> = None;
+        let mut shader_resource_view: Option<ID3D11ShaderResourceView> = None;
+        let mut render_target_view: Option<ID3D11RenderTargetView> = None;
+        unsafe {
+            device.CreateTexture2D(desc, init_data.as_ref().map(|data| data as *const _), Some(&mut texture)).expect("CreateTexture2D failed");
+            let texture = texture.as_ref().unwrap();
+            if desc.BindFlags & D3D11_BIND_SHADER_RESOURCE.0 as u32 != 0 {
+                device.CreateShaderResourceView(texture, None, Some(&mut shader_resource_view)).expect("CreateShaderResourceView failed");
+            }
+            if desc.BindFlags & D3D11_BIND_RENDER_TARGET.0 as u32 != 0 {
+                device.CreateRenderTargetView(texture, None, Some(&mut render_target_view)).expect("CreateRenderTargetView failed");
+            }
+        }
+        Self {
+            texture: texture.unwrap(),
+            shader_resource_view,
+            render_target_view,
+            desc: *desc,
+            initial_data,
+        }
+    }
+}
+
+pub enum ShaderObject {
+    Vertex(ID3D11VertexShader),
+    Pixel(ID3D11PixelShader),
+}
+
+/// 由 `ResourceManager` 管理的著色器，保留編譯後的 bytecode，用來建立 input layout 與重建。
+pub struct GpuShader {
+    pub shader: ShaderObject,
+    bytecode: Vec<u8>,
+}
+
+impl GpuShader {
+    fn create_vertex(device: &ID3D11Device, bytecode: Vec<u8>) -> Self {
+        let mut shader: Option<ID3D11VertexShader> = None;
+        unsafe {
+            device.CreateVertexShader(&bytecode, None, Some(&mut shader)).expect("CreateVertexShader failed");
+        }
+        Self { shader: ShaderObject::Vertex(shader.unwrap()), bytecode }
+    }
+
+    fn create_pixel(device: &ID3D11Device, bytecode: Vec<u8>) -> Self {
+        let mut shader: Option<ID3D11PixelShader> = None;
+        unsafe {
+            device.CreatePixelShader(&bytecode, None, Some(&mut shader)).expect("CreatePixelShader failed");
+        }
+        Self { shader: ShaderObject::Pixel(shader.unwrap()), bytecode }
+    }
+
+    fn recreate(&self, device: &ID3D11Device) -> Self {
+        match self.shader {
+            ShaderObject::Vertex(_) => Self::create_vertex(device, self.bytecode.clone()),
+            ShaderObject::Pixel(_) => Self::create_pixel(device, self.bytecode.clone()),
+        }
+    }
+
+    pub fn vertex_shader(&self) -> Option<&ID3D11VertexShader> {
+        match &self.shader {
+            ShaderObject::Vertex(shader) => Some(shader),
+            ShaderObject::Pixel(_) => None,
+        }
+    }
+
+    pub fn pixel_shader(&self) -> Option<&ID3D11PixelShader> {
+        match &self.shader {
+            ShaderObject::Pixel(shader) => Some(shader),
+            ShaderObject::Vertex(_) => None,
+        }
+    }
+
+    pub fn bytecode(&self) -> &[u8] {
+        &self.bytecode
+    }
+}
+
+/// 各類資源的使用統計。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ResourceStats {
+    pub buffers: SlotStats,
+    pub textures: SlotStats,
+    pub shaders: SlotStats,
+}
+
+impl fmt::Display for ResourceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "buffers: {}", self.buffers)?;
+        writeln!(f, "textures: {}", self.textures)?;
+        write!(f, "shaders: {}", self.shaders)
+    }
+}
+
+/// 以世代 handle 管理 GPU 資源。資源只在 `destroy_*` 時釋放，
+/// 之後再使用同一個 handle 會查不到資源並計入統計的過期查詢。
+#[derive(Default)]
+pub struct ResourceManager {
+    buffers: SlotMap<GpuBuffer>,
+    textures: SlotMap<GpuTexture>,
+    shaders: SlotMap<GpuShader>,
+}
+
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `data` 為 `None` 時不給初始資料，例如 dynamic buffer。
+    pub fn create_buffer(&mut self, device: &ID3D11Device, desc: &D3D11_BUFFER_DESC, data: Option<&[u8]>) -> BufferHandle {
+        self.buffers.insert(GpuBuffer::create(device, desc, data.map(<[u8]>::to_vec)))
+    }
+
+    /// `data` 是 (texel 資料, 每列的 byte 數)，只上傳第一個 mip level。
+    #[allow(dead_code)]
+    pub fn create_texture(&mut self, device: &ID3D11Device, desc: &D3D11_TEXTURE2D_DESC, data: Option<(&[u8], u32)>) -> TextureHandle {
+        let data = data.map(|(texels, row_pitch)| TextureData { texels: texels.to_vec(), row_pitch });
+        self.textures.insert(GpuTexture::create(device, desc, data))
+    }
+
+    pub fn create_vertex_shader(&mut self, device: &ID3D11Device, bytecode: &[u8]) -> ShaderHandle {
+        self.shaders.insert(GpuShader::create_vertex(device, bytecode.to_vec()))
+    }
+
+    pub fn create_pixel_shader(&mut self, device: &ID3D11Device, bytecode: &[u8]) -> ShaderHandle {
+        self.shaders.insert(GpuShader::create_pixel(device, bytecode.to_vec()))
+    }
+
+    /// 以 `AssetRegistry` 載入的 shader 建立資源。`.hlsl` 原始碼在這裡依裝置的 shader model 編譯，
+    /// 進入點為 `VS` 或 `PS`；`path` 是檔案系統中的路徑，用於 `#include` 與錯誤訊息。
+    pub fn create_shader(&mut self, device: &ID3D11Device, path: &Path, shader: &ShaderData) -> Result<ShaderHandle, String> {
+        let (entry_point, profile) = match shader.stage {
+            ShaderStage::Vertex => (s!("VS"), s!("vs_5_0")),
+            ShaderStage::Pixel => (s!("PS"), s!("ps_5_0")),
+        };
+        let compiled;
+        let bytecode = match &shader.code {
+            // 預先編譯的 .cso 是 shader model 5.0
+            ShaderCode::Bytecode(_) if shader_model() != ShaderModel::Sm5_0 => {
+                return Err(format!("{}: precompiled shaders need shader model 5.0", path.display()));
+            }
+            ShaderCode::Bytecode(bytecode) => bytecode.as_slice(),
+            ShaderCode::Hlsl(source) => {
+                let source_name = path.to_string_lossy().replace('\\', "/");
+                compiled = compile_shader(source.as_bytes(), &source_name, entry_point, profile)?;
+                blob_as_bytes(&compiled)
+            }
+        };
+        Ok(match shader.stage {
+            ShaderStage::Vertex => self.create_vertex_shader(device, bytecode),
+            ShaderStage::Pixel => self.create_pixel_shader(device, bytecode),
+        })
+    }
+
+    pub fn buffer(&self, handle: BufferHandle) -> Option<&GpuBuffer> {
+        self.buffers.get(handle)
+    }
+
+    #[allow(dead_code)]
+    pub fn texture(&self, handle: TextureHandle) -> Option<&GpuTexture> {
+        self.textures.get(handle)
+    }
+
+    pub fn shader(&self, handle: ShaderHandle) -> Option<&GpuShader> {
+        self.shaders.get(handle)
+    }
+
+    pub fn destroy_buffer(&mut self, handle: BufferHandle) -> Result<(), String> {
+        self.buffers.remove(handle).map(drop).ok_or_else(|| format!("cannot destroy {}: handle is stale", handle))
+    }
+
+    #[allow(dead_code)]
+    pub fn destroy_texture(&mut self, handle: TextureHandle) -> Result<(), String> {
+        self.textures.remove(handle).map(drop).ok_or_else(|| format!("cannot destroy {}: handle is stale", handle))
+    }
+
+    pub fn destroy_shader(&mut self, handle: ShaderHandle) -> Result<(), String> {
+        self.shaders.remove(handle).map(drop).ok_or_else(|| format!("cannot destroy {}: handle is stale", handle))
+    }
+
+    /// 裝置失效後在新的裝置上重建所有資源，handle 維持不變。
+    /// 沒有初始資料的資源只重建空的物件，內容要由使用者重新上傳。
+    pub fn recreate(&mut self, device: &ID3D11Device) {
+        for (_, buffer) in self.buffers.iter_mut() {
+            *buffer = GpuBuffer::create(device, &buffer.desc, buffer.initial_data.take());
+        }
+        for (_, texture) in self.textures.iter_mut() {
+            *texture = GpuTexture::create(device, &texture.desc, texture.initial_data.take());
+        }
+        for (_, shader) in self.shaders.iter_mut() {
+            *shader = shader.recreate(device);
+        }
+    }
+
+    pub fn stats(&self) -> ResourceStats {
+        ResourceStats {
+            buffers: self.buffers.stats(),
+            textures: self.textures.stats(),
+            shaders: self.shaders.stats(),
+        }
+    }
+}
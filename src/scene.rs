@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use directx_math::*;
+use crate::profiler::profile_scope;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct MeshId(pub usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct MaterialId(pub usize);
+
+/// 每個 instance 送進 GPU 的資料，配置需與 `instanced_vs.hlsl` 的 per-instance 輸入一致。
+/// `world` 以 row-major 儲存，shader 內用四個 float4 重組成矩陣。
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct InstanceData {
+    pub world: XMFLOAT4X4,
+    pub color: XMFLOAT4,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl InstanceData {
+    pub fn new(world: XMMATRIX, color: XMFLOAT4) -> Self {
+        let mut stored = XMFLOAT4X4::default();
+        XMStoreFloat4x4(&mut stored, world);
+        Self { world: stored, color }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct SceneObject {
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub instance: InstanceData,
+}
+
+/// 同一組 mesh + material 的所有 instance，可以一次 draw call 畫完。
+#[derive(Debug)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Batch {
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub instances: Vec<InstanceData>,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Scene {
+    pub objects: Vec<SceneObject>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl Scene {
+    pub fn new() -> Self {
+        Self { objects: vec![] }
+    }
+
+    pub fn add(&mut self, mesh: MeshId, material: MaterialId, world: XMMATRIX, color: XMFLOAT4) {
+        self.objects.push(SceneObject {
+            mesh,
+            material,
+            instance: InstanceData::new(world, color),
+        });
+    }
+
+    /// 依 (mesh, material) 分組，回傳順序固定以便每幀的狀態切換一致。
+    pub fn batches(&self) -> Vec<Batch> {
+        profile_scope!("scene_traversal");
+        let mut groups: BTreeMap<(MeshId, MaterialId), Vec<InstanceData>> = BTreeMap::new();
+        for object in self.objects.iter() {
+            groups.entry((object.mesh, object.material)).or_default().push(object.instance);
+        }
+        groups.into_iter()
+            .map(|((mesh, material), instances)| Batch { mesh, material, instances })
+            .collect()
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+use directx_math::*;
+use serde::Deserialize;
+use crate::vfs;
+
+/// 場景檔中可以使用的網格：內建的 `"cube"`，或相對於場景檔的 `.obj` 檔案路徑。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum MeshKind {
+    Cube,
+    File(PathBuf),
+}
+
+impl TryFrom<String> for MeshKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        if name == "cube" {
+            return Ok(MeshKind::Cube);
+        }
+        let path = PathBuf::from(name);
+        let is_obj = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj"));
+        if !is_obj {
+            return Err(format!("unknown mesh \"{}\", expected \"cube\" or an .obj file", path.display()));
+        }
+        Ok(MeshKind::File(path))
+    }
+}
+
+/// 場景檔中的一個物件。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectDesc {
+    pub mesh: MeshKind,
+    /// 內建網格的半邊長，檔案網格不使用，以 `scale` 調整大小。
+    #[serde(default = "ObjectDesc::default_size")]
+    pub size: f32,
+    #[serde(default)]
+    pub position: [f32; 3],
+    /// 以度為單位的 pitch、yaw、roll。
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "ObjectDesc::default_scale")]
+    pub scale: [f32; 3],
+    #[serde(default = "ObjectDesc::default_color")]
+    pub color: [f32; 4],
+}
+
+impl ObjectDesc {
+    fn default_size() -> f32 {
+        0.5
+    }
+
+    fn default_scale() -> [f32; 3] {
+        [1.0; 3]
+    }
+
+    fn default_color() -> [f32; 4] {
+        [1.0; 4]
+    }
+
+    /// 依 scale、rotation、position 的順序組成 world 矩陣。
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn world(&self) -> XMMATRIX {
+        let [sx, sy, sz] = self.scale;
+        let [pitch, yaw, roll] = self.rotation.map(XMConvertToRadians);
+        let [x, y, z] = self.position;
+        let scale_rotation = XMMatrixMultiply(XMMatrixScaling(sx, sy, sz), &XMMatrixRotationRollPitchYaw(pitch, yaw, roll));
+        XMMatrixMultiply(scale_rotation, &XMMatrixTranslation(x, y, z))
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn color(&self) -> XMFLOAT4 {
+        let [x, y, z, w] = self.color;
+        XMFLOAT4 { x, y, z, w }
+    }
+}
+
+/// 以 TOML 描述的場景：
+///
+/// ```toml
+/// [[object]]
+/// mesh = "cube"  # 或相對於場景檔的 "meshes/pyramid.obj"
+/// size = 0.5
+/// position = [0.0, 1.0, 0.0]
+/// rotation = [0.0, 45.0, 0.0]
+/// color = [1.0, 0.5, 0.2, 1.0]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDesc {
+    #[serde(default, rename = "object")]
+    pub objects: Vec<ObjectDesc>,
+}
+
+impl SceneDesc {
+    /// 場景用到的網格檔案，重複的只列一次。
+    pub fn mesh_files(&self) -> Vec<&Path> {
+        let mut files: Vec<&Path> = vec![];
+        for object in &self.objects {
+            if let MeshKind::File(path) = &object.mesh
+                && !files.contains(&path.as_path())
+            {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let scene: SceneDesc = toml::from_str(text).map_err(|e| e.to_string())?;
+        for (index, object) in scene.objects.iter().enumerate() {
+            if object.size.is_nan() || object.size <= 0.0 {
+                return Err(format!("object {}: size must be positive, got {}", index, object.size));
+            }
+        }
+        Ok(scene)
+    }
+
+    /// 目前只支援 `.toml` 場景檔，其他模型格式會回傳錯誤。
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if !extension.eq_ignore_ascii_case("toml") {
+            return Err(format!("{}: unsupported scene format \"{}\", only .toml scene files can be loaded", path.display(), extension));
+        }
+        let text = vfs::file_system().read_to_string(path)?;
+        Self::from_toml_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(name: &str) -> Result<MeshKind, String> {
+        MeshKind::try_from(name.to_string())
+    }
+
+    #[test]
+    fn parses_mesh_names() {
+        assert_eq!(mesh("cube"), Ok(MeshKind::Cube));
+        assert_eq!(mesh("meshes/pyramid.obj"), Ok(MeshKind::File(PathBuf::from("meshes/pyramid.obj"))));
+        assert_eq!(mesh("PYRAMID.OBJ"), Ok(MeshKind::File(PathBuf::from("PYRAMID.OBJ"))));
+        for name in ["Cube", "sphere", "model.fbx", "obj", "meshes/pyramid.obj.bak"] {
+            assert!(mesh(name).is_err(), "accepted {}", name);
+        }
+    }
+
+    #[test]
+    fn fills_in_object_defaults() {
+        let scene = SceneDesc::from_toml_str("[[object]]\nmesh = \"cube\"\n\n[[object]]\nmesh = \"a.obj\"\nsize = 2.0\nposition = [1.0, 2.0, 3.0]\n").unwrap();
+        assert_eq!(scene.objects.len(), 2);
+        let cube = &scene.objects[0];
+        assert_eq!(cube.size, 0.5);
+        assert_eq!(cube.scale, [1.0; 3]);
+        assert_eq!(cube.color, [1.0; 4]);
+        assert_eq!(scene.objects[1].position, [1.0, 2.0, 3.0]);
+        assert!(SceneDesc::from_toml_str("").unwrap().objects.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_objects() {
+        assert!(SceneDesc::from_toml_str("[[object]]\nmesh = \"model.fbx\"\n").unwrap_err().contains("unknown mesh \"model.fbx\""));
+        assert!(SceneDesc::from_toml_str("[[object]]\nmesh = \"cube\"\ncolour = [1.0, 1.0, 1.0, 1.0]\n").is_err());
+        assert!(SceneDesc::from_toml_str("[[objects]]\nmesh = \"cube\"\n").is_err());
+        assert!(SceneDesc::from_toml_str("[[object]]\nsize = 1.0\n").is_err());
+    }
+
+    #[test]
+    fn size_must_be_positive() {
+        for size in ["0.0", "-1.0", "nan"] {
+            let error = SceneDesc::from_toml_str(&format!("[[object]]\nmesh = \"cube\"\n\n[[object]]\nmesh = \"cube\"\nsize = {}\n", size)).unwrap_err();
+            assert!(error.starts_with("object 1: size must be positive"), "{}: {}", size, error);
+        }
+    }
+
+    #[test]
+    fn lists_each_mesh_file_once() {
+        let scene = SceneDesc::from_toml_str(
+            "[[object]]\nmesh = \"b.obj\"\n[[object]]\nmesh = \"cube\"\n[[object]]\nmesh = \"a.obj\"\n[[object]]\nmesh = \"b.obj\"\n",
+        ).unwrap();
+        assert_eq!(scene.mesh_files(), [Path::new("b.obj"), Path::new("a.obj")]);
+    }
+
+    #[test]
+    fn load_only_reads_toml_files() {
+        for path in ["scene.obj", "scene.gltf", "scene"] {
+            let error = SceneDesc::load(path).unwrap_err();
+            assert!(error.contains("only .toml scene files can be loaded"), "{}", error);
+        }
+        // 副檔名正確時才會去讀檔案
+        let error = SceneDesc::load("missing/scene.TOML").unwrap_err();
+        assert!(!error.contains("unsupported scene format"), "{}", error);
+    }
+}
@@ -0,0 +1,580 @@
+use windows::core::{Interface, BOOL, HRESULT};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND};
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::{Common, IDXGIDevice, IDXGIFactory2, IDXGIFactory5, IDXGIOutput, IDXGISwapChain1, IDXGISwapChain2, IDXGISwapChain3, DXGI_ENUM_MODES, DXGI_FEATURE_PRESENT_ALLOW_TEARING, DXGI_MWA_NO_ALT_ENTER, DXGI_PRESENT, DXGI_PRESENT_ALLOW_TEARING, DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT, DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_CHAIN_FLAG, DXGI_SWAP_CHAIN_FLAG_ALLOW_MODE_SWITCH, DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING, DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT, DXGI_SWAP_CHAIN_FULLSCREEN_DESC, DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT};
+use windows::Win32::System::Threading::WaitForSingleObjectEx;
+use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_D24_UNORM_S8_UINT, DXGI_MODE_DESC, DXGI_MODE_SCALING_UNSPECIFIED, DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED, DXGI_RATIONAL};
+use crate::camera::Camera;
+use crate::d3d11::MsaaMode;
+use crate::d3dutil::RenderTexture;
+use crate::device_lost::{is_device_lost, DeviceLost, DeviceLostReason};
+use crate::display::{closest_mode, DisplayMode, WindowMode};
+use crate::output_format::OutputFormat;
+use crate::post_process::{PostProcessChain, HDR_FORMAT};
+use crate::present::PresentSettings;
+use crate::profiler::profile_scope;
+use crate::scene::Scene;
+use crate::window::{Position, Size, Window};
+
+// 等待 frame latency waitable object 的上限，避免裝置失效時永遠等不到
+const FRAME_WAIT_TIMEOUT_MS: u32 = 1000;
+
+// ResizeBuffers 在裝置失效時回傳 DEVICE_REMOVED / DEVICE_RESET，與 present 相同交給 renderer 重建，
+// 原因以裝置回報的為準；其他錯誤代表呼叫方式有誤，直接結束
+fn check_resize(device: &ID3D11Device, result: windows::core::Result<()>) -> Result<(), DeviceLost> {
+    let Err(e) = result else {
+        return Ok(());
+    };
+    let hresult = e.code().0;
+    if !is_device_lost(hresult) {
+        panic!("ResizeBuffers failed: {}", e);
+    }
+    let removed_reason = unsafe { device.GetDeviceRemovedReason() }.err().map_or(hresult, |e| e.code().0);
+    Err(DeviceLost {
+        hresult: removed_reason,
+        reason: DeviceLostReason::from_hresult(removed_reason).unwrap_or(DeviceLostReason::Unknown(hresult)),
+    })
+}
+
+/// renderer 中每個視窗的編號，`SurfaceId::PRIMARY` 是建立 renderer 時的視窗。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SurfaceId(pub usize);
+
+impl SurfaceId {
+    pub const PRIMARY: SurfaceId = SurfaceId(0);
+}
+
+/// 一個視窗的繪製目標：swap chain、back buffer 與 depth 的 view、
+/// HDR 中間目標、後處理鏈、viewport 與相機。裝置與場景資源由 renderer 共用。
+pub struct Surface {
+    pub hwnd: HWND,
+    // 只有重建時短暫為 None
+    swap_chain: Option<IDXGISwapChain1>,
+    // 建立時決定，之後每次 ResizeBuffers 都要帶同樣的旗標
+    swap_chain_flags: DXGI_SWAP_CHAIN_FLAG,
+    tearing_supported: bool,
+    frame_latency_waitable: Option<HANDLE>,
+    present: PresentSettings,
+    render_target_view: Option<ID3D11RenderTargetView>,
+    depth_stencil_view: Option<ID3D11DepthStencilView>,
+    hdr_target: RenderTexture,
+    msaa_target: Option<RenderTexture>,
+    output_format: OutputFormat,
+    paper_white_nits: f32,
+    post_process: PostProcessChain,
+    camera: Camera,
+    /// 這個視窗專用的場景，`None` 時畫 renderer 共用的場景。
+    pub scene: Option<Scene>,
+    window_mode: WindowMode,
+    /// 獨占全螢幕使用的顯示模式，`None` 時使用最接近桌面解析度的模式。
+    pub fullscreen_display_mode: Option<DisplayMode>,
+    pos: Position,
+    size: Size,
+}
+
+impl Surface {
+    pub fn new(device: &ID3D11Device, window: &Window, msaa: MsaaMode, output_format: OutputFormat, paper_white_nits: f32, present: PresentSettings) -> Surface {
+        let pos = window.get_position();
+        let size = Self::clamp_size(window.get_size());
+        let (swap_chain, swap_chain_flags, tearing_supported) = Self::create_swap_chain(device, window.hwnd, size, output_format, present);
+        let frame_latency_waitable = Self::frame_latency_waitable(&swap_chain, present.max_frame_latency);
+        let output_format = Self::configure_output(device, &swap_chain, size, output_format, present.buffer_count, swap_chain_flags)
+            .unwrap_or_else(|lost| panic!("{}", lost));
+        let (render_target_view, depth_stencil_view) = Self::create_views(device, &swap_chain, size, msaa, output_format);
+        let (hdr_target, msaa_target) = Self::create_scene_targets(device, size, msaa);
+        let mut post_process = PostProcessChain::with_default_effects(device, size.width as u32, size.height as u32);
+        post_process.set_output(output_format.encoding(), paper_white_nits);
+        Surface {
+            hwnd: window.hwnd,
+            swap_chain: Some(swap_chain),
+            swap_chain_flags,
+            tearing_supported,
+            frame_latency_waitable,
+            present,
+            render_target_view: Some(render_target_view),
+            depth_stencil_view: Some(depth_stencil_view),
+            hdr_target,
+            msaa_target,
+            output_format,
+            paper_white_nits,
+            post_process,
+            camera: Camera::new(size.width as f32 / size.height as f32),
+            scene: None,
+            window_mode: WindowMode::Windowed,
+            fullscreen_display_mode: None,
+            pos,
+            size,
+        }
+    }
+
+    // 最小化時 client 區域為 0，建立 0 大小的 buffer 會失敗
+    fn clamp_size(size: Size) -> Size {
+        Size { width: size.width.max(1), height: size.height.max(1) }
+    }
+
+    /// 建立 flip model 的 swap chain，回傳 (swap chain, 建立時的旗標, 是否支援撕裂)。
+    fn create_swap_chain(device: &ID3D11Device, hwnd : HWND, size : Size, output_format: OutputFormat, present: PresentSettings) -> (IDXGISwapChain1, DXGI_SWAP_CHAIN_FLAG, bool) {
+        let dxgi_device = device.clone().cast::<IDXGIDevice>().unwrap();
+        let adapter = unsafe {
+            dxgi_device.GetAdapter().unwrap()
+        };
+
+        let factory = unsafe {
+            adapter.GetParent::<IDXGIFactory2>().unwrap()
+        };
+
+        // 獨占全螢幕時允許 DXGI 切換顯示器的解析度
+        let mut flags = DXGI_SWAP_CHAIN_FLAG_ALLOW_MODE_SWITCH | DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT;
+        let tearing_supported = present.allow_tearing && Self::check_tearing_support(&factory);
+        if tearing_supported {
+            flags |= DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING;
+        }
+
+        let swap_chain_desc = DXGI_SWAP_CHAIN_DESC1 {
+            Width: size.width as u32, //binding window size
+            Height: size.height as u32,
+            Format: output_format.swap_chain_format(),
+            Stereo: Default::default(),
+            SampleDesc: Common::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            BufferCount: present.buffer_count,
+            Scaling: Default::default(),
+            SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+            AlphaMode: Default::default(),
+            Flags: flags.0 as u32,
+        };
+
+        let fullscreen_desc = DXGI_SWAP_CHAIN_FULLSCREEN_DESC{
+            RefreshRate: Common::DXGI_RATIONAL {
+                Numerator: 60,
+                Denominator: 1
+            },
+            ScanlineOrdering: DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
+            Scaling: DXGI_MODE_SCALING_UNSPECIFIED,
+            Windowed: BOOL::from(true),
+        };
+
+        let swap_chain = unsafe {
+            factory.CreateSwapChainForHwnd(&*device, hwnd, &swap_chain_desc, Some(&fullscreen_desc), None)
+        }.unwrap();
+
+        // Alt+Enter 由應用程式自己處理，才能在視窗、無邊框與獨占全螢幕之間切換
+        unsafe {
+            let _ = factory.MakeWindowAssociation(hwnd, DXGI_MWA_NO_ALT_ENTER);
+        }
+
+        (swap_chain, flags, tearing_supported)
+    }
+
+    // 需要 DXGI 1.5 與支援可變更新率的驅動程式，舊系統上取不到 IDXGIFactory5
+    fn check_tearing_support(factory: &IDXGIFactory2) -> bool {
+        let Ok(factory5) = factory.cast::<IDXGIFactory5>() else {
+            return false;
+        };
+        let mut allow_tearing = BOOL::default();
+        let result = unsafe {
+            factory5.CheckFeatureSupport(
+                DXGI_FEATURE_PRESENT_ALLOW_TEARING,
+                &mut allow_tearing as *mut BOOL as *mut _,
+                size_of::<BOOL>() as u32,
+            )
+        };
+        result.is_ok() && allow_tearing.as_bool()
+    }
+
+    // 限制 CPU 領先的幀數，並取得每幀開始前要等待的 handle
+    fn frame_latency_waitable(swap_chain: &IDXGISwapChain1, max_frame_latency: u32) -> Option<HANDLE> {
+        let swap_chain2 = swap_chain.cast::<IDXGISwapChain2>().ok()?;
+        unsafe {
+            swap_chain2.SetMaximumFrameLatency(max_frame_latency).ok()?;
+            let handle = swap_chain2.GetFrameLatencyWaitableObject();
+            (!handle.is_invalid()).then_some(handle)
+        }
+    }
+
+    /// 設定 swap chain 的色彩空間並回傳實際採用的輸出格式。
+    /// 不支援所要求的 HDR 色彩空間時會退回 SDR，必要時以 ResizeBuffers 換掉 back buffer 格式，
+    /// 因此呼叫前不能有任何 back buffer 的 view 存在。
+    fn configure_output(device: &ID3D11Device, swap_chain: &IDXGISwapChain1, size: Size, requested: OutputFormat, buffer_count: u32, flags: DXGI_SWAP_CHAIN_FLAG) -> Result<OutputFormat, DeviceLost> {
+        let swap_chain3 = swap_chain.cast::<IDXGISwapChain3>().ok();
+        let try_color_space = |format: OutputFormat| -> bool {
+            match &swap_chain3 {
+                Some(swap_chain3) => unsafe {
+                    let support = swap_chain3.CheckColorSpaceSupport(format.color_space()).unwrap_or(0);
+                    support & DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT.0 as u32 != 0
+                        && swap_chain3.SetColorSpace1(format.color_space()).is_ok()
+                },
+                // 舊版 DXGI 只能輸出預設的 sRGB 色彩空間
+                None => !format.is_hdr(),
+            }
+        };
+
+        if try_color_space(requested) {
+            return Ok(requested);
+        }
+
+        let fallback = requested.sdr_fallback();
+        println!("Output format {:?} is not supported by the display, falling back to {:?}", requested, fallback);
+        if fallback.swap_chain_format() != requested.swap_chain_format() {
+            check_resize(device, unsafe {
+                swap_chain.ResizeBuffers(buffer_count, size.width as u32, size.height as u32, fallback.swap_chain_format(), flags)
+            })?;
+        }
+        try_color_space(fallback);
+        Ok(fallback)
+    }
+
+    // swap chain 要先初始化完成
+    // depth buffer 的取樣數必須與場景的 render target 一致
+    fn create_views(device : &ID3D11Device, swap_chain : &IDXGISwapChain1, size: Size, msaa: MsaaMode, output_format: OutputFormat) -> (ID3D11RenderTargetView, ID3D11DepthStencilView) {
+        let back_buffer = unsafe {
+            swap_chain.GetBuffer::<ID3D11Texture2D>(0).unwrap()
+        };
+        // sRGB 模式下 buffer 是 UNORM，view 用 _SRGB 格式讓硬體在寫入時做編碼
+        let render_target_view_desc = D3D11_RENDER_TARGET_VIEW_DESC {
+            Format: output_format.render_target_format(),
+            ViewDimension: D3D11_RTV_DIMENSION_TEXTURE2D,
+            Anonymous: D3D11_RENDER_TARGET_VIEW_DESC_0 {
+                Texture2D: D3D11_TEX2D_RTV { MipSlice: 0 },
+            },
+        };
+        let mut render_target_view: Option<ID3D11RenderTargetView> = None;
+        unsafe {
+            device.CreateRenderTargetView(&back_buffer, Some(&render_target_view_desc), Some(&mut render_target_view)).unwrap();
+        }
+
+        let render_target_view = render_target_view.unwrap();
+
+        let depth_stencil_desc = D3D11_TEXTURE2D_DESC {
+            Width: size.width as u32,
+            Height: size.height as u32,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_D24_UNORM_S8_UINT,
+            SampleDesc: msaa.sample_desc(),
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_DEPTH_STENCIL.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: 0,
+        };
+
+        let mut depth_stencil_buffer: Option<ID3D11Texture2D> = None;
+
+        unsafe {
+            device.CreateTexture2D(&depth_stencil_desc, None, Some(&mut depth_stencil_buffer)).unwrap()
+        }
+
+        let depth_stencil_buffer = depth_stencil_buffer.unwrap();
+
+        let mut depth_stencil_view: Option<ID3D11DepthStencilView> = None;
+        unsafe {
+            device.CreateDepthStencilView(&depth_stencil_buffer, None, Some(&mut depth_stencil_view)).unwrap();
+        }
+
+        let depth_stencil_view = depth_stencil_view.unwrap();
+
+        (render_target_view, depth_stencil_view)
+    }
+
+    /// 回傳 (resolve 後給後處理讀取的 HDR 目標, 開啟 MSAA 時實際繪製的多重取樣目標)。
+    fn create_scene_targets(device: &ID3D11Device, size: Size, msaa: MsaaMode) -> (RenderTexture, Option<RenderTexture>) {
+        let (width, height) = (size.width as u32, size.height as u32);
+        let hdr_target = RenderTexture::new(device, width, height, HDR_FORMAT);
+        let msaa_target = if msaa.is_enabled() {
+            Some(RenderTexture::multisampled(device, width, height, HDR_FORMAT, msaa.sample_desc()))
+        } else {
+            None
+        };
+        (hdr_target, msaa_target)
+    }
+
+    fn swap_chain(&self) -> &IDXGISwapChain1 {
+        self.swap_chain.as_ref().unwrap()
+    }
+
+    /// 場景實際繪製的目標，開啟 MSAA 時為多重取樣目標。
+    pub fn scene_target(&self) -> &ID3D11RenderTargetView {
+        match &self.msaa_target {
+            Some(target) => &target.render_target_view,
+            None => &self.hdr_target.render_target_view,
+        }
+    }
+
+    pub fn depth_stencil_view(&self) -> &ID3D11DepthStencilView {
+        self.depth_stencil_view.as_ref().unwrap()
+    }
+
+    pub fn viewport(&self) -> D3D11_VIEWPORT {
+        D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: self.size.width as f32,
+            Height: self.size.height as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn post_process(&self) -> &PostProcessChain {
+        &self.post_process
+    }
+
+    pub fn post_process_mut(&mut self) -> &mut PostProcessChain {
+        &mut self.post_process
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// 目前的顯示方式。獨占全螢幕在失去焦點時可能被系統切回視窗模式，所以每次都向 swap chain 確認。
+    pub fn window_mode(&self) -> WindowMode {
+        if self.window_mode == WindowMode::Fullscreen {
+            let mut fullscreen = BOOL::default();
+            unsafe {
+                let _ = self.swap_chain().GetFullscreenState(Some(&mut fullscreen as *mut BOOL), None);
+            }
+            if !fullscreen.as_bool() {
+                return WindowMode::Windowed;
+            }
+        }
+        self.window_mode
+    }
+
+    /// 視窗所在顯示器以目前 back buffer 格式支援的所有顯示模式。
+    pub fn display_modes(&self) -> Vec<DisplayMode> {
+        let Ok(output) = (unsafe { self.swap_chain().GetContainingOutput() }) else {
+            return vec![];
+        };
+        let format = self.output_format.swap_chain_format();
+        let mut count = 0_u32;
+        let mut descs = vec![];
+        unsafe {
+            if output.GetDisplayModeList(format, DXGI_ENUM_MODES(0), &mut count, None).is_err() {
+                return vec![];
+            }
+            descs.resize(count as usize, DXGI_MODE_DESC::default());
+            if output.GetDisplayModeList(format, DXGI_ENUM_MODES(0), &mut count, Some(descs.as_mut_ptr())).is_err() {
+                return vec![];
+            }
+        }
+        descs.truncate(count as usize);
+        descs.iter().map(|desc| DisplayMode {
+            width: desc.Width,
+            height: desc.Height,
+            refresh_numerator: desc.RefreshRate.Numerator,
+            refresh_denominator: desc.RefreshRate.Denominator,
+        }).collect()
+    }
+
+    // 沒有指定顯示模式時，用最接近目前桌面解析度的模式，避免切換時改變解析度
+    fn fullscreen_target(&self) -> Option<DisplayMode> {
+        if self.fullscreen_display_mode.is_some() {
+            return self.fullscreen_display_mode;
+        }
+        let output = unsafe { self.swap_chain().GetContainingOutput() }.ok()?;
+        let desktop = unsafe { output.GetDesc() }.ok()?.DesktopCoordinates;
+        let (width, height) = ((desktop.right - desktop.left) as u32, (desktop.bottom - desktop.top) as u32);
+        closest_mode(&self.display_modes(), width, height)
+    }
+
+    fn mode_desc(&self, mode: DisplayMode, refresh_rate: bool) -> DXGI_MODE_DESC {
+        DXGI_MODE_DESC {
+            Width: mode.width,
+            Height: mode.height,
+            RefreshRate: if refresh_rate {
+                DXGI_RATIONAL { Numerator: mode.refresh_numerator, Denominator: mode.refresh_denominator }
+            } else {
+                DXGI_RATIONAL::default()
+            },
+            Format: self.output_format.swap_chain_format(),
+            ScanlineOrdering: DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
+            Scaling: DXGI_MODE_SCALING_UNSPECIFIED,
+        }
+    }
+
+    /// 切換顯示方式並依切換後的 client 大小重建 buffer，回傳實際採用的模式。
+    /// 無法進入獨占全螢幕時（例如視窗不在任何顯示器上）退回無邊框。
+    /// 限制與 `resize` 相同；切換期間系統會同步送出 `WM_SIZE`，處理函式不能再鎖住 renderer。
+    pub fn set_window_mode(&mut self, device: &ID3D11Device, window: &Window, mode: WindowMode, msaa: MsaaMode) -> Result<WindowMode, DeviceLost> {
+        let mut mode = mode;
+        if self.window_mode() == WindowMode::Fullscreen {
+            unsafe {
+                let _ = self.swap_chain().SetFullscreenState(false, None::<&IDXGIOutput>);
+            }
+        }
+        match mode {
+            WindowMode::Windowed => window.exit_borderless(),
+            WindowMode::Borderless => window.enter_borderless(),
+            WindowMode::Fullscreen => {
+                window.exit_borderless();
+                let target = self.fullscreen_target();
+                unsafe {
+                    if let Some(target) = target {
+                        let _ = self.swap_chain().ResizeTarget(&self.mode_desc(target, true));
+                    }
+                    if let Err(e) = self.swap_chain().SetFullscreenState(true, None::<&IDXGIOutput>) {
+                        println!("Exclusive fullscreen is not available ({}), using borderless instead", e);
+                        window.enter_borderless();
+                        mode = WindowMode::Borderless;
+                    } else if let Some(target) = target {
+                        // 進入全螢幕後再設一次，更新率歸零避免 DXGI 因為頻率不符而重新切換模式
+                        let _ = self.swap_chain().ResizeTarget(&self.mode_desc(target, false));
+                    }
+                }
+            }
+        }
+        self.window_mode = mode;
+        self.resize(device, window.get_position(), window.get_size(), msaa)?;
+        Ok(mode)
+    }
+
+    /// 切換垂直同步，下一次 present 生效。
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.present.vsync = vsync;
+    }
+
+    /// 這個 swap chain 是否能在關閉 vsync 時撕裂，需要系統與顯示器支援可變更新率。
+    pub fn tearing_supported(&self) -> bool {
+        self.tearing_supported
+    }
+
+    /// 等到 swap chain 可以接受下一幀。在讀取輸入、更新場景前呼叫，
+    /// CPU 就不會領先 GPU 超過 `max_frame_latency` 幀，輸入延遲也跟著降低。
+    pub fn wait_for_next_frame(&self) {
+        if let Some(handle) = self.frame_latency_waitable {
+            unsafe {
+                WaitForSingleObjectEx(handle, FRAME_WAIT_TIMEOUT_MS, true);
+            }
+        }
+    }
+
+    fn close_frame_latency_waitable(&mut self) {
+        if let Some(handle) = self.frame_latency_waitable.take() {
+            unsafe {
+                let _ = CloseHandle(handle);
+            }
+        }
+    }
+
+    /// 場景畫完後呼叫：resolve MSAA、執行後處理寫進 back buffer 並 present，
+    /// 回傳 `Present` 的結果，裝置失效時由 renderer 處理。
+    pub fn finish_frame(&self, context: &ID3D11DeviceContext) -> HRESULT {
+        // 多重取樣的結果要先 resolve 成一般紋理，後處理才能取樣
+        if let Some(msaa_target) = &self.msaa_target {
+            unsafe {
+                context.ResolveSubresource(&self.hdr_target.texture, 0, &msaa_target.texture, 0, HDR_FORMAT);
+            }
+        }
+
+        // 後處理鏈把 HDR 結果寫進 back buffer
+        {
+            profile_scope!("post_process");
+            self.post_process.run(context, &self.hdr_target.shader_resource_view, self.render_target_view.as_ref().unwrap());
+        }
+
+        profile_scope!("present");
+        let params = self.present.present_params(self.tearing_supported, self.window_mode() == WindowMode::Fullscreen);
+        let flags = if params.allow_tearing { DXGI_PRESENT_ALLOW_TEARING } else { DXGI_PRESENT(0) };
+        unsafe {
+            self.swap_chain().Present(params.sync_interval, flags)
+        }
+    }
+
+    /// 裝置失效後在新的裝置上重建 swap chain 與所有繪製目標。
+    /// 相機、場景、後處理參數與視窗大小都保留；獨占全螢幕無法恢復時改為視窗模式。
+    /// 重建期間裝置又失效時回傳原因。
+    pub fn recreate(&mut self, device: &ID3D11Device, msaa: MsaaMode) -> Result<(), DeviceLost> {
+        let was_fullscreen = self.window_mode() == WindowMode::Fullscreen;
+        self.render_target_view = None;
+        self.depth_stencil_view = None;
+        unsafe {
+            let _ = self.swap_chain().SetFullscreenState(false, None::<&IDXGIOutput>);
+        }
+        // 舊的 swap chain 屬於失效的裝置，先釋放再為同一個視窗建立新的，flip model 不允許兩個同時存在
+        self.close_frame_latency_waitable();
+        self.swap_chain = None;
+        let (swap_chain, swap_chain_flags, tearing_supported) = Self::create_swap_chain(device, self.hwnd, self.size, self.output_format, self.present);
+        self.frame_latency_waitable = Self::frame_latency_waitable(&swap_chain, self.present.max_frame_latency);
+        self.swap_chain = Some(swap_chain);
+        self.swap_chain_flags = swap_chain_flags;
+        self.tearing_supported = tearing_supported;
+        self.output_format = Self::configure_output(device, self.swap_chain(), self.size, self.output_format, self.present.buffer_count, swap_chain_flags)?;
+        self.post_process.set_output(self.output_format.encoding(), self.paper_white_nits);
+        self.post_process.recreate(device);
+        if was_fullscreen && unsafe { self.swap_chain().SetFullscreenState(true, None::<&IDXGIOutput>) }.is_err() {
+            println!("Cannot restore exclusive fullscreen after device reset, using windowed mode");
+            self.window_mode = WindowMode::Windowed;
+        }
+        // 進入全螢幕會改變 client 大小，由 resize 依目前大小重建 view 與場景目標
+        self.resize(device, self.pos, self.size, msaa)
+    }
+
+    /// 重建與尺寸或 MSAA 有關的所有資源，`context` 上不能還綁著這個 surface 的 back buffer。
+    /// 裝置失效時回傳原因，由 renderer 的 `recover` 重建，期間這個 surface 沒有 back buffer 的 view。
+    pub fn resize(&mut self, device: &ID3D11Device, pos: Position, size: Size, msaa: MsaaMode) -> Result<(), DeviceLost> {
+        let size = Self::clamp_size(size);
+        self.render_target_view = None;
+        self.depth_stencil_view = None;
+
+        check_resize(device, unsafe {
+            self.swap_chain().ResizeBuffers(self.present.buffer_count, size.width as u32, size.height as u32, self.output_format.swap_chain_format(), self.swap_chain_flags)
+        })?;
+
+        let (render_target_view, depth_stencil_view) = Self::create_views(device, self.swap_chain(), size, msaa, self.output_format);
+        self.render_target_view = Some(render_target_view);
+        self.depth_stencil_view = Some(depth_stencil_view);
+        let (hdr_target, msaa_target) = Self::create_scene_targets(device, size, msaa);
+        self.hdr_target = hdr_target;
+        self.msaa_target = msaa_target;
+        self.post_process.on_resize(device, size.width as u32, size.height as u32);
+        self.camera.set_viewport_size(size.width as u32, size.height as u32);
+        self.pos = pos;
+        self.size = size;
+        Ok(())
+    }
+
+    /// 切換 back buffer 格式，回傳實際採用的格式。限制與錯誤處理與 `resize` 相同。
+    pub fn set_output_format(&mut self, device: &ID3D11Device, requested: OutputFormat, msaa: MsaaMode) -> Result<OutputFormat, DeviceLost> {
+        self.render_target_view = None;
+        let size = self.size;
+        check_resize(device, unsafe {
+            self.swap_chain().ResizeBuffers(self.present.buffer_count, size.width as u32, size.height as u32, requested.swap_chain_format(), self.swap_chain_flags)
+        })?;
+        self.output_format = Self::configure_output(device, self.swap_chain(), size, requested, self.present.buffer_count, self.swap_chain_flags)?;
+        self.post_process.set_output(self.output_format.encoding(), self.paper_white_nits);
+        self.resize(device, self.pos, size, msaa)?;
+        Ok(self.output_format)
+    }
+}
+
+impl Drop for Surface {
+    // swap chain 不能在全螢幕狀態下釋放
+    fn drop(&mut self) {
+        self.close_frame_latency_waitable();
+        if let Some(swap_chain) = &self.swap_chain {
+            unsafe {
+                let _ = swap_chain.SetFullscreenState(false, None::<&IDXGIOutput>);
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// 固定數量的背景執行緒，依提交順序執行工作。釋放時等所有已提交的工作做完。
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// 保留一個核心給主執行緒，至少一個執行緒。
+    pub fn default_thread_count() -> usize {
+        thread::available_parallelism().map(|count| count.get().saturating_sub(1)).unwrap_or(1).max(1)
+    }
+
+    /// `name` 用來命名執行緒，方便在除錯器與 profiler 中辨識。
+    pub fn new(threads: usize, name: &str) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1)).map(|index| {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || loop {
+                    // 只在取工作時持有鎖，執行期間其他執行緒可以繼續取
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+                .expect("failed to spawn worker thread")
+        }).collect();
+        Self { sender: Some(sender), workers }
+    }
+
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.sender.as_ref().unwrap().send(Box::new(job)).expect("thread pool workers have exited");
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // 關閉 channel 讓 recv 失敗，工作執行緒做完手上的工作後結束
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn runs_every_job() {
+        let pool = ThreadPool::new(3, "test");
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..20 {
+            let sender = sender.clone();
+            pool.execute(move || sender.send(i).unwrap());
+        }
+        let mut results: Vec<i32> = (0..20).map(|_| receiver.recv_timeout(Duration::from_secs(10)).unwrap()).collect();
+        results.sort();
+        assert_eq!(results, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn has_at_least_one_thread() {
+        assert_eq!(ThreadPool::new(4, "test").workers.len(), 4);
+        assert_eq!(ThreadPool::new(0, "test").workers.len(), 1);
+        assert!(ThreadPool::default_thread_count() >= 1);
+    }
+
+    #[test]
+    fn workers_are_named() {
+        let pool = ThreadPool::new(1, "loader");
+        let (sender, receiver) = mpsc::channel();
+        pool.execute(move || sender.send(thread::current().name().map(str::to_string)).unwrap());
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap().as_deref(), Some("loader-0"));
+    }
+
+    #[test]
+    fn drop_waits_for_submitted_jobs() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let pool = ThreadPool::new(2, "test");
+        for _ in 0..8 {
+            let finished = finished.clone();
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(5));
+                finished.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+        assert_eq!(finished.load(Ordering::SeqCst), 8);
+    }
+}
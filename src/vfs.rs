@@ -0,0 +1,347 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+/// 掛載到 `Vfs` 的檔案來源。路徑是已經去掉掛載前綴、以 `/` 分隔的相對路徑。
+pub trait Mount: Send + Sync {
+    /// 檔案不在這個來源時回傳 `Ok(None)`，`Vfs` 會繼續找下一個來源。
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, String>;
+    /// 這個來源中的所有檔案，用於列出內容與除錯。
+    // 還沒有列出檔案的命令，目前只有測試呼叫
+    #[allow(dead_code)]
+    fn files(&self) -> Vec<String>;
+    fn describe(&self) -> String;
+}
+
+/// 實體目錄。
+pub struct DirectoryMount {
+    root: PathBuf,
+}
+
+impl DirectoryMount {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            if entry.path().is_dir() {
+                Self::collect_files(&entry.path(), &path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+}
+
+impl Mount for DirectoryMount {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = self.root.join(path);
+        match std::fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut files = vec![];
+        Self::collect_files(&self.root, "", &mut files);
+        files
+    }
+
+    fn describe(&self) -> String {
+        format!("directory {}", self.root.display())
+    }
+}
+
+/// 編譯進執行檔的檔案，由 `include_bytes!` 產生。
+pub struct EmbeddedMount {
+    name: &'static str,
+    files: &'static [(&'static str, &'static [u8])],
+}
+
+impl EmbeddedMount {
+    pub const fn new(name: &'static str, files: &'static [(&'static str, &'static [u8])]) -> Self {
+        Self { name, files }
+    }
+}
+
+impl Mount for EmbeddedMount {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.files.iter().find(|(name, _)| *name == path).map(|(_, data)| data.to_vec()))
+    }
+
+    fn files(&self) -> Vec<String> {
+        self.files.iter().map(|(name, _)| name.to_string()).collect()
+    }
+
+    fn describe(&self) -> String {
+        format!("embedded {} ({} files)", self.name, self.files.len())
+    }
+}
+
+macro_rules! embed_shaders {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_bytes!(concat!("../hlsl/", $name)))),*]
+    };
+}
+
+/// 內建的 shader 原始碼，找不到 shader 目錄時（例如從其他工作目錄執行）仍然可以編譯。
+pub const EMBEDDED_SHADERS: EmbeddedMount = EmbeddedMount::new("shaders", embed_shaders![
+    "bloom_ps.hlsl",
+    "color_grading_ps.hlsl",
+    "debug_line_ps.hlsl",
+    "debug_line_vs.hlsl",
+    "fullscreen_vs.hlsl",
+    "fxaa_ps.hlsl",
+    "instanced.hlsli",
+    "instanced_ps.hlsl",
+    "instanced_vs.hlsl",
+    "output_ps.hlsl",
+    "post_process.hlsli",
+    "tone_mapping_ps.hlsl",
+    "triangle.hlsli",
+    "triangle_ps.hlsl",
+    "triangle_vs.hlsl",
+    "vignette_ps.hlsl",
+]);
+
+/// 把路徑轉成以 `/` 分隔、去掉 `.` 的相對路徑，例如 `a/./b.obj` 與 `a\b.obj` 都是 `a/b.obj`。
+/// 絕對路徑與 `..` 會回傳錯誤，虛擬路徑不能離開掛載點。
+pub fn normalize_path(path: &Path) -> Result<String, String> {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let mut parts = vec![];
+    for component in Path::new(&text).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return Err(format!("{}: virtual paths must be relative and must not contain \"..\"", path.display())),
+        }
+    }
+    if parts.is_empty() {
+        return Err("path must not be empty".to_string());
+    }
+    Ok(parts.join("/"))
+}
+
+struct MountPoint {
+    // 正規化後的前綴，空字串表示掛在根目錄
+    prefix: String,
+    mount: Box<dyn Mount>,
+}
+
+/// 虛擬檔案系統。每個來源掛在一個前綴下，後掛載的來源優先，
+/// 例如先掛內建 shader 再掛 shader 目錄，目錄中有的檔案會取代內建的版本。
+#[derive(Default)]
+pub struct Vfs {
+    mounts: Vec<MountPoint>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `prefix` 為空字串時掛在根目錄。
+    pub fn mount(&mut self, prefix: &str, mount: impl Mount + 'static) -> Result<(), String> {
+        let prefix = if prefix.is_empty() { String::new() } else { normalize_path(Path::new(prefix))? };
+        self.mounts.push(MountPoint { prefix, mount: Box::new(mount) });
+        Ok(())
+    }
+
+    // 去掉前綴後的路徑，不在這個掛載點下時回傳 None
+    fn strip_prefix<'a>(prefix: &str, path: &'a str) -> Option<&'a str> {
+        if prefix.is_empty() {
+            return Some(path);
+        }
+        path.strip_prefix(prefix)?.strip_prefix('/')
+    }
+
+    /// 讀取檔案。絕對路徑與含 `..` 的路徑不屬於任何掛載點，直接讀取實體檔案，
+    /// 例如命令列指定的 `--scene ../scenes/a.toml`。
+    pub fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, String> {
+        let path = path.as_ref();
+        if path.is_absolute() || path.components().any(|component| component == Component::ParentDir) {
+            return std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e));
+        }
+        let virtual_path = normalize_path(path)?;
+        for mount_point in self.mounts.iter().rev() {
+            if let Some(relative) = Self::strip_prefix(&mount_point.prefix, &virtual_path)
+                && let Some(data) = mount_point.mount.read(relative)?
+            {
+                return Ok(data);
+            }
+        }
+        Err(format!("{}: file not found in any mount", virtual_path))
+    }
+
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String, String> {
+        let path = path.as_ref();
+        let data = self.read(path)?;
+        String::from_utf8(data).map_err(|_| format!("{}: file is not valid UTF-8", path.display()))
+    }
+
+    /// 所有來源中的檔案，依路徑排序，重複的只列一次。
+    #[allow(dead_code)]
+    pub fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.mounts.iter().flat_map(|mount_point| {
+            mount_point.mount.files().into_iter().map(|file| match mount_point.prefix.as_str() {
+                "" => file,
+                prefix => format!("{}/{}", prefix, file),
+            })
+        }).collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+}
+
+impl fmt::Display for Vfs {
+    /// 依優先順序列出掛載點，優先的在前。
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, mount_point) in self.mounts.iter().rev().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "/{} <- {}", mount_point.prefix, mount_point.mount.describe())?;
+        }
+        Ok(())
+    }
+}
+
+static FILE_SYSTEM: OnceLock<Arc<Vfs>> = OnceLock::new();
+
+/// 設定所有載入程式使用的檔案系統，必須在載入任何檔案前呼叫。已經設定過時回傳 `false`。
+pub fn install(vfs: Vfs) -> bool {
+    FILE_SYSTEM.set(Arc::new(vfs)).is_ok()
+}
+
+/// 目前的檔案系統。沒有呼叫 `install` 時只有內建 shader 與工作目錄。
+pub fn file_system() -> Arc<Vfs> {
+    FILE_SYSTEM.get_or_init(|| {
+        let mut vfs = Vfs::new();
+        vfs.mount("hlsl", EMBEDDED_SHADERS).unwrap();
+        vfs.mount("", DirectoryMount::new(".")).unwrap();
+        Arc::new(vfs)
+    }).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: EmbeddedMount = EmbeddedMount::new("base", &[("a.txt", b"base a"), ("b.txt", b"base b")]);
+    const PATCH: EmbeddedMount = EmbeddedMount::new("patch", &[("a.txt", b"patch a"), ("c.txt", b"patch c")]);
+    const SHADERS: EmbeddedMount = EmbeddedMount::new("shaders", &[("x.hlsl", b"shader x")]);
+
+    /// 每次讀取都回傳錯誤的來源，例如沒有讀取權限的目錄。
+    struct FailingMount;
+
+    impl Mount for FailingMount {
+        fn read(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+            Err(format!("{}: permission denied", path))
+        }
+
+        fn files(&self) -> Vec<String> {
+            vec![]
+        }
+
+        fn describe(&self) -> String {
+            "failing".to_string()
+        }
+    }
+
+    fn read_string(vfs: &Vfs, path: &str) -> Result<String, String> {
+        vfs.read_to_string(path)
+    }
+
+    #[test]
+    fn later_mounts_take_priority() {
+        let mut vfs = Vfs::new();
+        vfs.mount("", BASE).unwrap();
+        vfs.mount("", PATCH).unwrap();
+        assert_eq!(read_string(&vfs, "a.txt").unwrap(), "patch a");
+        assert_eq!(read_string(&vfs, "b.txt").unwrap(), "base b");
+        assert_eq!(read_string(&vfs, "./c.txt").unwrap(), "patch c");
+        assert_eq!(read_string(&vfs, "d.txt").unwrap_err(), "d.txt: file not found in any mount");
+        assert_eq!(vfs.to_string(), "/ <- embedded patch (2 files)\n/ <- embedded base (2 files)");
+    }
+
+    #[test]
+    fn prefixes_match_whole_components() {
+        let mut vfs = Vfs::new();
+        vfs.mount("hlsl", SHADERS).unwrap();
+        assert_eq!(read_string(&vfs, "hlsl/x.hlsl").unwrap(), "shader x");
+        assert_eq!(read_string(&vfs, "hlsl\\x.hlsl").unwrap(), "shader x");
+        assert!(vfs.read("hlslx/x.hlsl").is_err());
+        assert!(vfs.read("x.hlsl").is_err());
+        assert!(vfs.read("hlsl").is_err());
+
+        assert_eq!(Vfs::strip_prefix("hlsl", "hlsl/a/b.hlsl"), Some("a/b.hlsl"));
+        assert_eq!(Vfs::strip_prefix("hlsl", "hlslx/b.hlsl"), None);
+        assert_eq!(Vfs::strip_prefix("", "hlslx/b.hlsl"), Some("hlslx/b.hlsl"));
+    }
+
+    #[test]
+    fn parent_and_absolute_paths_bypass_the_mounts() {
+        let mut vfs = Vfs::new();
+        vfs.mount("", FailingMount).unwrap();
+        // 測試在 crate 目錄下執行
+        let manifest = std::fs::read("Cargo.toml").unwrap();
+        assert_eq!(vfs.read("src/../Cargo.toml").unwrap(), manifest);
+        assert_eq!(vfs.read(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")).unwrap(), manifest);
+        assert!(vfs.read("../missing/file.txt").unwrap_err().starts_with("../missing/file.txt: "));
+
+        assert!(normalize_path(Path::new("src/../Cargo.toml")).is_err());
+        assert!(normalize_path(Path::new(env!("CARGO_MANIFEST_DIR"))).is_err());
+        assert!(normalize_path(Path::new(".")).is_err());
+        assert_eq!(normalize_path(Path::new("./a\\./b.obj")), Ok("a/b.obj".to_string()));
+    }
+
+    #[test]
+    fn mount_errors_stop_the_lookup() {
+        let mut vfs = Vfs::new();
+        vfs.mount("", BASE).unwrap();
+        vfs.mount("", FailingMount).unwrap();
+        // 不會退回較早掛載的來源，以免默默讀到舊的檔案
+        assert_eq!(read_string(&vfs, "a.txt").unwrap_err(), "a.txt: permission denied");
+
+        let mut vfs = Vfs::new();
+        vfs.mount("other", FailingMount).unwrap();
+        vfs.mount("", BASE).unwrap();
+        assert_eq!(read_string(&vfs, "a.txt").unwrap(), "base a");
+    }
+
+    #[test]
+    fn lists_files_once() {
+        let mut vfs = Vfs::new();
+        vfs.mount("", BASE).unwrap();
+        vfs.mount("", PATCH).unwrap();
+        vfs.mount("./hlsl/", SHADERS).unwrap();
+        assert_eq!(vfs.files(), ["a.txt", "b.txt", "c.txt", "hlsl/x.hlsl"]);
+        assert!(vfs.mount("../outside", BASE).is_err());
+    }
+
+    fn includes(source: &str) -> impl Iterator<Item = &str> {
+        source.lines().filter_map(|line| line.trim().strip_prefix("#include")?.trim().strip_prefix('"')?.strip_suffix('"'))
+    }
+
+    #[test]
+    fn embedded_shader_includes_resolve() {
+        let file_system = file_system();
+        for name in EMBEDDED_SHADERS.files() {
+            let source = String::from_utf8(EMBEDDED_SHADERS.read(&name).unwrap().unwrap()).unwrap();
+            for include in includes(&source) {
+                // 大小寫必須與內建的檔名相同，Windows 以外的檔案系統也一樣
+                assert!(EMBEDDED_SHADERS.read(include).unwrap().is_some(), "{} includes missing file {}", name, include);
+                file_system.read(format!("hlsl/{}", include)).unwrap_or_else(|e| panic!("{} includes {}: {}", name, include, e));
+            }
+        }
+    }
+}
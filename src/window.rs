@@ -9,42 +9,22 @@ use windows::Win32::Foundation::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 use widestring::{u16str, U16Str};
 use windows::Win32::Foundation;
-use windows::Win32::Graphics::Gdi::UpdateWindow;
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, UpdateWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::HiDpi::{AdjustWindowRectExForDpi, GetDpiForWindow, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2};
 use once_cell::sync::OnceCell;
 use windows_core::w;
 use crate::d3d11::D3d11Renderer;
-
-#[derive(Debug, Copy, Clone)]
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
-}
-
-#[derive(Debug, Copy, Clone)]
-pub struct Size {
-    pub width: i32,
-    pub height: i32,
-}
-
-pub struct EventHandler {
-    pub msg: u32,
-    pub handler: Box<dyn FnMut(WPARAM, LPARAM)>
-}
-
-impl EventHandler {
-    pub fn new(msg: u32, handler: Box<dyn FnMut(WPARAM, LPARAM)>) -> EventHandler {
-        return  EventHandler {
-            msg: msg,
-            handler
-        };
-    }
-}
+use crate::platform::dispatch::Dispatcher;
+pub use crate::platform::dispatch::{EventHandler, Subscription};
+pub use crate::platform::{dpi_to_scale_factor, LogicalSize, Position, Size};
 
 
 pub struct Window {
     pub hwnd : HWND,
-    callbacks: RwLock<Vec<EventHandler>>
+    dispatcher: Dispatcher,
+    /// 進入無邊框全螢幕前的 style 與視窗位置，離開時還原。
+    windowed_placement: Mutex<Option<(i32, RECT)>>,
 }
 
 impl Window {
@@ -83,7 +63,7 @@ impl Window {
 
         let hwnd = hwnd.ok().unwrap();
 
-        let window_instance = Arc::new(Window { hwnd,  callbacks: vec![].into() });
+        let window_instance = Arc::new(Window { hwnd,  dispatcher: Dispatcher::new(), windowed_placement: Mutex::new(None) });
         WndClass::get_instance().window_instances.write().unwrap().insert(hwnd.0, Arc::downgrade(&window_instance));
 
         Ok(window_instance)
@@ -96,6 +76,7 @@ impl Window {
         }
     }
 
+    /// client 區域的實體像素尺寸。
     pub fn get_size(&self) -> Size {
         return unsafe {
             let mut rect = RECT::default();
@@ -112,9 +93,87 @@ impl Window {
         }
     }
 
-    pub fn add_handler(&self, handler: EventHandler) {
-        let mut callbacks = self.callbacks.write().unwrap();
-        callbacks.push(handler);
+    /// 視窗所在顯示器的 DPI。
+    pub fn dpi(&self) -> u32 {
+        unsafe { GetDpiForWindow(self.hwnd) }
+    }
+
+    /// 目前的縮放比例，1.0 代表 96 DPI。
+    pub fn scale_factor(&self) -> f64 {
+        dpi_to_scale_factor(self.dpi())
+    }
+
+    /// 依目前的縮放比例把 client 區域設成 `size`，邊框大小也以同一個 DPI 計算。
+    pub fn set_logical_size(&self, size: LogicalSize) {
+        let dpi = self.dpi();
+        let physical = size.to_physical(dpi_to_scale_factor(dpi));
+        let mut rect = RECT { left: 0, top: 0, right: physical.width, bottom: physical.height };
+        unsafe {
+            let style = WINDOW_STYLE(GetWindowLongW(self.hwnd, GWL_STYLE) as u32);
+            let ex_style = WINDOW_EX_STYLE(GetWindowLongW(self.hwnd, GWL_EXSTYLE) as u32);
+            AdjustWindowRectExForDpi(&mut rect, style, false, ex_style, dpi).expect("AdjustWindowRectExForDpi failed");
+            let _ = SetWindowPos(
+                self.hwnd,
+                None,
+                0,
+                0,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    /// 拿掉邊框並覆蓋視窗所在的整個螢幕。會同步送出 `WM_SIZE`。
+    pub fn enter_borderless(&self) {
+        let mut placement = self.windowed_placement.lock().unwrap();
+        if placement.is_some() {
+            return;
+        }
+        unsafe {
+            let style = GetWindowLongW(self.hwnd, GWL_STYLE);
+            let mut rect = RECT::default();
+            GetWindowRect(self.hwnd, &mut rect).expect("GetWindowRect failed");
+            *placement = Some((style, rect));
+            drop(placement);
+
+            let mut monitor_info = MONITORINFO { cbSize: size_of::<MONITORINFO>() as u32, ..Default::default() };
+            let _ = GetMonitorInfoW(MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONEAREST), &mut monitor_info);
+            let monitor = monitor_info.rcMonitor;
+            SetWindowLongW(self.hwnd, GWL_STYLE, ((style as u32 & !WS_OVERLAPPEDWINDOW.0) | WS_POPUP.0) as i32);
+            let _ = SetWindowPos(
+                self.hwnd,
+                Some(HWND_TOP),
+                monitor.left,
+                monitor.top,
+                monitor.right - monitor.left,
+                monitor.bottom - monitor.top,
+                SWP_FRAMECHANGED | SWP_NOOWNERZORDER,
+            );
+        }
+    }
+
+    /// 還原 `enter_borderless` 之前的邊框與位置。會同步送出 `WM_SIZE`。
+    pub fn exit_borderless(&self) {
+        let Some((style, rect)) = self.windowed_placement.lock().unwrap().take() else {
+            return;
+        };
+        unsafe {
+            SetWindowLongW(self.hwnd, GWL_STYLE, style);
+            let _ = SetWindowPos(
+                self.hwnd,
+                None,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_FRAMECHANGED | SWP_NOZORDER | SWP_NOOWNERZORDER,
+            );
+        }
+    }
+
+    pub fn add_handler(&self, handler: EventHandler) -> Subscription {
+        self.dispatcher.add_handler(handler)
     }
 
     pub fn wnd_proc(&self,
@@ -122,21 +181,31 @@ impl Window {
                     msg: u32,
                     wparam: WPARAM,
                     lparam: LPARAM) -> LRESULT {
-        for handler in self.callbacks.write().unwrap().iter_mut() {
-            if handler.msg == msg {
-                (handler.handler)(wparam, lparam);
-            }
+        if let Some(result) = self.dispatcher.dispatch(msg, wparam, lparam) {
+            return LRESULT(result);
         }
         match msg {
             WM_PAINT => {
                 return LRESULT(0);
             },
-            WM_DESTROY => {
+            // lparam 指向系統建議的視窗矩形，照著調整可以在新的顯示器上維持相同的邏輯大小
+            WM_DPICHANGED => {
+                let suggested = unsafe { *(lparam.0 as *const RECT) };
                 unsafe {
-                    PostQuitMessage(0);
+                    let _ = SetWindowPos(
+                        hwnd,
+                        None,
+                        suggested.left,
+                        suggested.top,
+                        suggested.right - suggested.left,
+                        suggested.bottom - suggested.top,
+                        SWP_NOZORDER | SWP_NOACTIVATE,
+                    );
                 }
-                // temp
-                panic!("close window");
+                return LRESULT(0);
+            }
+            // 最後一個視窗的 WM_NCDESTROY 會送出 WM_QUIT，這裡不需要其他處理
+            WM_DESTROY => {
                 return LRESULT(0);
             }
             _ => {
@@ -152,7 +221,7 @@ pub struct WindowBuilder {
     window_name : Option<PCWSTR>,
     dw_style : WINDOW_STYLE,
     pos : Position,
-    size : Size,
+    size : Option<LogicalSize>,
     parent : HWND,
     menu : HMENU,
     h_instance : Option<HINSTANCE>,
@@ -168,7 +237,7 @@ impl<'a> WindowBuilder {
             window_name: None,
             dw_style: WS_OVERLAPPEDWINDOW,
             pos: { Position { x : CW_USEDEFAULT, y : CW_USEDEFAULT } } ,
-            size: { Size { width : CW_USEDEFAULT, height : CW_USEDEFAULT } },
+            size: None,
             parent: HWND::default(),
             menu: HMENU::default(),
             h_instance: None,
@@ -206,9 +275,9 @@ impl<'a> WindowBuilder {
         self
     }
 
-    /// 設定視窗尺寸。
-    pub fn size(mut self, width: i32, height: i32) -> Self {
-        self.size = Size { width, height };
+    /// 設定 client 區域的邏輯尺寸，建立後依視窗所在顯示器的縮放比例換算成實體像素。
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.size = Some(LogicalSize::new(width, height));
         self
     }
 
@@ -248,9 +317,10 @@ impl<'a> WindowBuilder {
         let dw_ex_style = self.dw_ex_style;
         let dw_style = self.dw_style;
         let pos = self.pos;
-        let size = self.size;
+        // 建立前還不知道視窗會落在哪個顯示器，先用預設大小建立，再依實際的 DPI 調整
+        let size = Size { width : CW_USEDEFAULT, height : CW_USEDEFAULT };
 
-        Window::new(
+        let window = Window::new(
             dw_ex_style,
             class_name,
             window_name,
@@ -261,7 +331,11 @@ impl<'a> WindowBuilder {
             self.menu,
             h_instance,
             self.lp_param,
-        )
+        )?;
+        if let Some(size) = self.size {
+            window.set_logical_size(size);
+        }
+        Ok(window)
     }
 }
 
@@ -282,7 +356,12 @@ unsafe impl Sync for WndClass {}
 static WND_CLASS: OnceLock<WndClass> = OnceLock::new();
 
 impl WndClass {
+    /// 註冊視窗類別，並在建立任何視窗前把程式設為 per-monitor v2 DPI awareness。
     pub fn init(class_name : PCWSTR) {
+        // 可能已經由 manifest 設定過，或系統版本太舊（Windows 10 1703 以前），失敗時維持系統縮放
+        if let Err(e) = unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) } {
+            println!("Per-monitor DPI awareness is not available: {}", e);
+        }
         let h_instance : HINSTANCE = unsafe {
             GetModuleHandleW(PCWSTR::null())
         }.unwrap().into();
@@ -310,14 +389,25 @@ impl WndClass {
     pub fn get_instance() -> &'static Self {
         WND_CLASS.get().unwrap()
     }
-    pub fn msg_loop() {
+    /// 不阻塞地處理所有待處理的訊息，收到 `WM_QUIT` 時回傳 `PostQuitMessage` 傳入的結束代碼。
+    pub fn pump_messages() -> Option<i32> {
         let mut msg = MSG::default();
         unsafe {
-            while(GetMessageW(&mut msg, Option::from(HWND::default()), 0, 0)).into() {
+            while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).into() {
+                if msg.message == WM_QUIT {
+                    return Some(msg.wParam.0 as i32);
+                }
                 let _ = TranslateMessage(&msg);
                 DispatchMessageW(&msg);
             }
         }
+        None
+    }
+    /// 取得 `hwnd` 對應的視窗。讀取鎖在回傳前就釋放，
+    /// 處理函式中建立新視窗或觸發巢狀訊息時才不會卡在 `window_instances` 上。
+    fn find_window(hwnd: HWND) -> Option<Arc<Window>> {
+        let map = WndClass::get_instance().window_instances.read().unwrap();
+        map.get(&(hwnd.0 as *const c_void)).and_then(Weak::upgrade)
     }
     pub extern "system" fn wnd_proc(
         hwnd: HWND,
@@ -333,12 +423,9 @@ impl WndClass {
                 unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
             }
             WM_DESTROY => {
-                let map = &WndClass::get_instance().window_instances.read().unwrap();
-                if let Some(window_weak_arc) = map.get(&(hwnd.0 as *const c_void)) {
-                    if let Some(window_arc) = window_weak_arc.upgrade() {
-                        let result = window_arc.wnd_proc(hwnd, msg, wparam, lparam);
-                        return result;
-                    }
+                if let Some(window_arc) = WndClass::find_window(hwnd) {
+                    let result = window_arc.wnd_proc(hwnd, msg, wparam, lparam);
+                    return result;
                 }
                 unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
             }
@@ -358,11 +445,8 @@ impl WndClass {
                 LRESULT(0)
             }
             _ => {
-                let map = &WndClass::get_instance().window_instances.read().unwrap();
-                if let Some(window_weak_arc) = map.get(&(hwnd.0 as *const c_void)) {
-                    if let Some(window_arc) = window_weak_arc.upgrade() {
-                        return window_arc.wnd_proc(hwnd, msg, wparam, lparam);
-                    }
+                if let Some(window_arc) = WndClass::find_window(hwnd) {
+                    return window_arc.wnd_proc(hwnd, msg, wparam, lparam);
                 }
                 unsafe {
                     DefWindowProcW(hwnd, msg, wparam, lparam)
@@ -370,12 +454,4 @@ impl WndClass {
             }
         }
     }
-}
-
-pub fn HIWORD(x: u32) -> u32 {
-    (x >> 16) & 0xFFFF
-}
-
-pub fn LOWORD(x: u32) -> u32 {
-    x & 0xFFFF
 }
\ No newline at end of file